@@ -18,7 +18,7 @@ fn dep_to_dependency_injection_statements(dep: &PatType) -> TokenStream {
 pub fn create_dependency_injection_statements(deps: Vec<PatType>) -> TokenStream {
     let mut out = TokenStream::new();
     for dep in deps.iter() {
-        out.extend(dep_to_dependency_injection_statements(&dep));
+        out.extend(dep_to_dependency_injection_statements(dep));
     }
     out
 }
@@ -1,14 +1,53 @@
 mod descriptivegraph;
 mod executablegraph;
 mod node;
+mod sourcemap;
 mod task;
+mod validation;
 
 use crate::graph::descriptivegraph::DescriptiveGraph;
 use proc_macro2::TokenStream;
 use quote::quote;
+use std::path::PathBuf;
+use syn::LitStr;
 use crate::graph::executablegraph::ExecutableGraph;
+use crate::graph::sourcemap::SourceMap;
+use crate::graph::validation::validate;
 
-pub fn graph_impl(graph_str: String) -> TokenStream {
-    let graph = ExecutableGraph::from(DescriptiveGraph::from(&graph_str));
-    quote! { #graph }
+pub fn graph_impl(source_map: SourceMap) -> TokenStream {
+    let graph = DescriptiveGraph::from(source_map)
+        .and_then(|graph| validate(&graph).map(|_| graph));
+    match graph {
+        Ok(graph) => {
+            let graph = ExecutableGraph::from(graph);
+            quote! { #graph }
+        },
+        Err(errors) => errors.to_compile_error(),
+    }
+}
+
+pub fn graph_tokens_impl(graph: TokenStream) -> TokenStream {
+    graph_impl(SourceMap::from_tokens(graph))
+}
+
+pub fn graph_file_impl(path: LitStr) -> TokenStream {
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(manifest_dir).join(path.value());
+    let graph_str = match std::fs::read_to_string(&full_path) {
+        Ok(graph_str) => graph_str,
+        Err(e) => {
+            let message = format!("unable to read graph file {}: {}", full_path.display(), e);
+            return syn::Error::new(path.span(), message).to_compile_error();
+        }
+    };
+    let source_map = SourceMap::from_file(
+        graph_str, full_path.display().to_string(), path.span()
+    );
+    let graph = graph_impl(source_map);
+    // Referencing the file through `include_str!` makes cargo rebuild when the file changes.
+    let full_path_str = full_path.to_string_lossy().into_owned();
+    quote! {
+        #graph
+        const _: &str = include_str!(#full_path_str);
+    }
 }
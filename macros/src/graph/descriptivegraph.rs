@@ -1,57 +1,361 @@
-use crate::graph::node::Nodes;
-use std::collections::HashMap;
+use crate::graph::node::{Branches, Nodes};
+use crate::graph::sourcemap::{unquote, SourceMap};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
 use proc_macro2::{Ident, TokenStream};
 use quote::{format_ident, quote};
 
-use dot_structures::{Attribute, Edge as GvEdge, EdgeTy, Graph as GvGraph, Id, Node as GvNode, Stmt, Vertex};
+use dot_structures::{
+    Attribute, Edge as GvEdge, EdgeTy, Graph as GvGraph, GraphAttributes, Id, Node as GvNode,
+    Stmt, Subgraph, Vertex
+};
 
+const CLUSTER_PREFIX: &str = "cluster";
 const NODE_TYPE_ATTR: &str = "type";
-const NODE_BRANCH_ATTR: &str = "branch";
-const NODE_START_ATTR: &str = "start";
-const EDGE_VALUE_ATTR: &str = "value";
+pub const NODE_BRANCH_ATTR: &str = "branch";
+pub const NODE_START_ATTR: &str = "start";
+pub const NODE_JOIN_ATTR: &str = "join";
+pub const NODE_JOIN_ALL_VAL: &str = "all";
+pub const NODE_FANOUT_ATTR: &str = "fanout";
+pub const NODE_GATHER_ATTR: &str = "gather";
+pub const NODE_MAX_CONCURRENCY_ATTR: &str = "max_concurrency";
+pub const NODE_MAX_CONCURRENT_ELEMENTS_ATTR: &str = "max_concurrent_elements";
+pub const NODE_MAX_ITERATIONS_ATTR: &str = "max_iterations";
+pub const NODE_YIELD_ATTR: &str = "yield";
+pub const NODE_TIMEOUT_ATTR: &str = "timeout";
+pub const NODE_RETRY_ATTR: &str = "retry";
+pub const NODE_BACKOFF_ATTR: &str = "backoff";
+pub const NODE_RETRY_DELAY_ATTR: &str = "retry_delay";
+const NODE_BACKOFF_FIXED_VAL: &str = "fixed";
+const NODE_BACKOFF_EXPONENTIAL_VAL: &str = "exponential";
+const GRAPH_FLAVOR_ATTR: &str = "flavor";
+const GRAPH_FLAVOR_MULTI_THREAD_VAL: &str = "multi_thread";
+const GRAPH_FLAVOR_CURRENT_THREAD_VAL: &str = "current_thread";
+const GRAPH_WORKER_THREADS_ATTR: &str = "worker_threads";
+const GRAPH_MAX_BLOCKING_THREADS_ATTR: &str = "max_blocking_threads";
+const GRAPH_THREAD_NAME_ATTR: &str = "thread_name";
+pub const GRAPH_ON_RETRY_ATTR: &str = "on_retry";
+pub const EDGE_VALUE_ATTR: &str = "value";
+pub const EDGE_PATTERN_ATTR: &str = "pattern";
+pub const EDGE_WHEN_ATTR: &str = "when";
+pub const EDGE_MAP_ATTR: &str = "map";
+pub const EDGE_MAX_ITERATIONS_ATTR: &str = "max_iterations";
+pub const EDGE_ON_EXHAUSTED_ATTR: &str = "on_exhausted";
+pub const EDGE_TIMEOUT_VAL: &str = "timeout";
+
+/// A node statement as written in the graph, whether or not it describes an executable node.
+pub struct NodeDeclaration {
+    pub name: String,
+    pub nodetype: Option<String>,
+    pub branch: String,
+    pub join: String,
+    pub fanout: bool,
+    pub max_concurrent_elements: Option<usize>,
+    pub max_iterations: Option<usize>,
+}
+
+/// An edge as written in the graph, whether or not it connects two executable nodes.
+pub struct EdgeDeclaration {
+    pub source: String,
+    pub destination: String,
+    pub value: String,
+    pub pattern: String,
+    pub guard: String,
+    pub map: String,
+    pub max_iterations: Option<usize>,
+    pub on_exhausted: bool,
+    /// Whether this is the `value=timeout` edge of a node with a `timeout`.
+    pub on_timeout: bool,
+}
+
+/// How many times a failed node is run again, set by its `retry` attribute, and how long to wait
+/// before each retry, set by `retry_delay` and `backoff`.
+#[derive(Clone)]
+pub struct RetryPolicy {
+    pub retries: usize,
+    pub delay: Option<Duration>,
+    /// Whether the delay doubles with each retry, rather than staying fixed.
+    pub exponential: bool,
+}
+
+/// The tokio runtime the graph's `run` methods create, set by the graph's own `flavor`,
+/// `worker_threads`, `max_blocking_threads` and `thread_name` attributes.
+#[derive(Default)]
+pub struct RuntimeSettings {
+    pub current_thread: bool,
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: Option<usize>,
+    pub thread_name: Option<String>,
+}
+
+/// Default node and edge attributes set by `node[...]` and `edge[...]` statements.
+///
+/// Following DOT semantics, defaults apply to the nodes and edges that come after them in the same
+/// graph or subgraph, including nested subgraphs, and attributes given directly to a node or edge
+/// take precedence over the defaults.
+#[derive(Clone)]
+struct AttributeDefaults {
+    node: Vec<Attribute>,
+    edge: Vec<Attribute>,
+}
+impl AttributeDefaults {
+    fn new() -> Self {
+        Self {
+            node: Vec::<Attribute>::new(),
+            edge: Vec::<Attribute>::new(),
+        }
+    }
+
+    /// Adds new defaults ahead of the existing ones so that later statements win.
+    fn add(&mut self, attributes: &GraphAttributes) {
+        match attributes {
+            GraphAttributes::Node(attrs) => self.node = with_defaults(attrs, &self.node),
+            GraphAttributes::Edge(attrs) => self.edge = with_defaults(attrs, &self.edge),
+            GraphAttributes::Graph(_) => {},
+        }
+    }
+
+    fn node_attributes(&self, attrs: &[Attribute]) -> Vec<Attribute> {
+        with_defaults(attrs, &self.node)
+    }
+
+    fn edge_attributes(&self, attrs: &[Attribute]) -> Vec<Attribute> {
+        with_defaults(attrs, &self.edge)
+    }
+}
+
+/// A node the graph can be started from.
+///
+/// Nodes labeled `start=true` are the graph's default entry point, run by the `run`, `run_with`,
+/// `run_on`, `run_graph` and `start_graph` methods.  Nodes labeled `start=<name>` are named entry
+/// points with their own `run_<name>`, `run_with_<name>` and so on.
+pub struct EntryPoint {
+    pub name: Option<String>,
+    pub node: String,
+}
+
+/// Prefixes of the methods generated for every entry point.
+pub const ENTRY_POINT_METHOD_PREFIXES: [&str; 5] =
+    ["run", "run_with", "run_on", "run_graph", "start_graph"];
+
+impl EntryPoint {
+    /// Name of the method with the given prefix that starts the graph from this entry point.
+    pub fn method_name(&self, prefix: &str) -> String {
+        match &self.name {
+            Some(name) => format!("{}_{}", prefix, name),
+            None => String::from(prefix),
+        }
+    }
+}
 
 /// The parsed graph structure of the application.
 ///
 /// A DescriptiveGraph contains the data extracted from parsing the raw string provided to the
 /// `graph` macro.  It needs to be passed into the `ExecutableGraph` to be converted into Rust
 /// source code that can be compiled and executed.
+///
+/// Problems found while building the graph are collected as `syn::Error`s located at the
+/// offending node, edge or attribute, so that all of them can be reported at once.
 pub struct DescriptiveGraph {
     name: String,
     nodes: HashMap<String, Nodes>,
-    start_node: String,
-    source: String,
+    entry_points: Vec<EntryPoint>,
+    join_nodes: HashSet<String>,
+    gather_nodes: HashSet<String>,
+    yield_nodes: HashSet<String>,
+    max_concurrency: HashMap<String, usize>,
+    max_concurrent_elements: HashMap<String, usize>,
+    max_iterations: HashMap<String, usize>,
+    timeouts: HashMap<String, Duration>,
+    retry_policies: HashMap<String, RetryPolicy>,
+    declarations: Vec<NodeDeclaration>,
+    node_attributes: HashMap<String, Vec<Attribute>>,
+    edges: Vec<EdgeDeclaration>,
+    clusters: Vec<String>,
+    qualified_names: HashMap<String, String>,
+    subgraph_members: HashMap<String, Vec<String>>,
+    runtime: RuntimeSettings,
+    on_retry: Option<String>,
+    source_map: SourceMap,
+    errors: Option<syn::Error>,
 }
 impl DescriptiveGraph {
-    pub fn from(raw_source: &String) -> DescriptiveGraph {
-        let gv_graph = match graphviz_rust::parse(&raw_source) {
+    pub fn from(source_map: SourceMap) -> Result<DescriptiveGraph, syn::Error> {
+        let gv_graph = match graphviz_rust::parse(source_map.get_text()) {
             Ok(g) => g,
-            Err(_) => panic!("unable to parse graph")
+            Err(e) => return Err(parse_error_to_syn_error(&source_map, &e)),
         };
-        let mut graph = DescriptiveGraph::new(&get_graph_name(&gv_graph));
-        graph.source = raw_source.clone();
+        let mut graph = DescriptiveGraph::new(&get_graph_name(&gv_graph), source_map);
         graph.process_graph(gv_graph);
-        graph
+        graph.check_entry_points();
+        match graph.errors.take() {
+            Some(errors) => Err(errors),
+            None => Ok(graph),
+        }
     }
 
-    fn new(name: &String) -> DescriptiveGraph {
+    fn new(name: &String, source_map: SourceMap) -> DescriptiveGraph {
         DescriptiveGraph {
-            name: name.clone(),
+            name: name.to_owned(),
             nodes: HashMap::<String, Nodes>::new(),
-            start_node: String::new(),
-            source: String::new(),
+            entry_points: Vec::<EntryPoint>::new(),
+            join_nodes: HashSet::<String>::new(),
+            gather_nodes: HashSet::<String>::new(),
+            yield_nodes: HashSet::<String>::new(),
+            max_concurrency: HashMap::<String, usize>::new(),
+            max_concurrent_elements: HashMap::<String, usize>::new(),
+            max_iterations: HashMap::<String, usize>::new(),
+            timeouts: HashMap::<String, Duration>::new(),
+            retry_policies: HashMap::<String, RetryPolicy>::new(),
+            declarations: Vec::<NodeDeclaration>::new(),
+            node_attributes: HashMap::<String, Vec<Attribute>>::new(),
+            edges: Vec::<EdgeDeclaration>::new(),
+            clusters: Vec::<String>::new(),
+            qualified_names: HashMap::<String, String>::new(),
+            subgraph_members: HashMap::<String, Vec<String>>::new(),
+            runtime: RuntimeSettings::default(),
+            on_retry: None,
+            source_map,
+            errors: None,
+        }
+    }
+
+    /// Records an error, unless the same error was already recorded, as when a node's attributes
+    /// are read again after another statement adds to them.
+    fn push_error(&mut self, error: syn::Error) {
+        let is_reported = self.errors.iter()
+            .flat_map(|errors| errors.clone())
+            .any(|reported| {
+                reported.to_string() == error.to_string()
+                    && format!("{:?}", reported.span()) == format!("{:?}", error.span())
+            });
+        if is_reported {
+            return;
+        }
+        match &mut self.errors {
+            Some(errors) => errors.combine(error),
+            None => self.errors = Some(error),
         }
     }
 
-    fn add_node(&mut self, name: &String, nodetype: &String, branch: &String) {
-        self.nodes.insert(name.clone(), Nodes::new_node(&name, &nodetype, &branch));
+    fn add_node(&mut self, name: &String, nodetype: &String, branch: &str, fanout: bool) {
+        let qualified_name = self.clusters.iter()
+            .chain(std::iter::once(name))
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("_");
+        if !is_valid_ident(&format!("execute_{}", qualified_name)) {
+            let message = format!("node name `{}` cannot be used in a Rust identifier", name);
+            self.push_error(self.source_map.error_at_node(name, &message));
+            return;
+        }
+        self.qualified_names.entry(name.clone()).or_insert(qualified_name);
+        if !is_valid_ident(nodetype) {
+            let message = format!("node type `{}` is not a valid Rust identifier", nodetype);
+            self.push_error(self.source_map.error_at_node_attribute(name, NODE_TYPE_ATTR, &message));
+            return;
+        }
+        self.nodes.insert(name.clone(), Nodes::new_node(name, nodetype, branch, fanout));
     }
 
-    fn add_edge(&mut self, source: &String, destination: &String, value: &String) {
-        match self.nodes.get_mut(source) {
-            Some(node) => {
-                node.add_destination(&value, &destination);
+    fn add_edge(&mut self, source: &str, destination: &str, attributes: &[Attribute]) {
+        let value = get_match_value_from_edge_attributes(attributes);
+        let pattern = get_pattern_from_edge_attributes(attributes);
+        let guard = get_guard_from_edge_attributes(attributes);
+        let map = get_map_from_edge_attributes(attributes);
+        let on_exhausted = get_on_exhausted_from_edge_attributes(attributes);
+        self.check_edge_function_path(source, destination, EDGE_WHEN_ATTR, &guard);
+        self.check_edge_function_path(source, destination, EDGE_MAP_ATTR, &map);
+        let max_iterations = get_positive_integer_attribute(attributes, EDGE_MAX_ITERATIONS_ATTR);
+        let max_iterations = match max_iterations {
+            Ok(max_iterations) => max_iterations,
+            Err(message) => {
+                let error = self.source_map.error_at_edge_attribute(
+                    source, destination, EDGE_MAX_ITERATIONS_ATTR, &message
+                );
+                self.push_error(error);
+                None
             },
-            None => {}
+        };
+        self.edges.push(EdgeDeclaration {
+            source: source.to_owned(),
+            destination: destination.to_owned(),
+            value,
+            pattern,
+            guard,
+            map,
+            max_iterations,
+            on_exhausted,
+            on_timeout: false,
+        });
+        let index = self.edges.len() - 1;
+        self.add_edge_destination(index);
+    }
+
+    /// Adds the destination of an edge to its source node, if the source node has been declared.
+    /// Edges leaving a node declared later are added when it is.
+    fn add_edge_destination(&mut self, index: usize) {
+        let edge = &mut self.edges[index];
+        // The `on_exhausted` edge is only followed in place of an edge that reached its iteration
+        // limit, and the timeout edge in place of the node's output, so neither is one of the
+        // node's destinations.
+        edge.on_timeout =
+            edge.value == EDGE_TIMEOUT_VAL && self.timeouts.contains_key(&edge.source);
+        let result = match self.nodes.get_mut(&edge.source) {
+            Some(node) if !edge.on_exhausted && !edge.on_timeout => node.add_destination(
+                &edge.value, &edge.pattern, &edge.guard, &edge.destination
+            ),
+            _ => Ok(()),
+        };
+        if let Err(message) = result {
+            let error = self.source_map.error_at_edge_attribute(
+                &edge.source, &edge.destination, EDGE_VALUE_ATTR, &message
+            );
+            self.push_error(error);
+        }
+    }
+
+    /// Reports an edge attribute naming a function that isn't a valid path.
+    fn check_edge_function_path(
+        &mut self,
+        source: &str,
+        destination: &str,
+        key: &str,
+        path: &str
+    ) {
+        if path.is_empty() || syn::parse_str::<syn::Path>(path).is_ok() {
+            return;
+        }
+        let message = format!(
+            "`{}` must be the path of a function, like `my_module::my_function`, found `{}`",
+            key, path
+        );
+        let error = self.source_map.error_at_edge_attribute(source, destination, key, &message);
+        self.push_error(error);
+    }
+
+    fn check_entry_points(&mut self) {
+        let mut errors = Vec::<syn::Error>::new();
+        for entry_point in self.entry_points.iter() {
+            let name = match &entry_point.name {
+                Some(name) => name,
+                None => continue,
+            };
+            if !is_valid_ident(&format!("run_graph_{}", name)) {
+                let message = format!("entry point name `{}` is not a valid Rust identifier", name);
+                errors.push(self.source_map.error_at_node_attribute(
+                    &entry_point.node, NODE_START_ATTR, &message
+                ));
+            }
+        }
+        for error in errors {
+            self.push_error(error);
+        }
+        if self.entry_points.is_empty() {
+            let error = self.source_map.error(
+                "No starting node found!  Give one node the attribute 'start'."
+            );
+            self.push_error(error);
         }
     }
 
@@ -59,108 +363,732 @@ impl DescriptiveGraph {
         match gv_graph {
             GvGraph::Graph {id: _, stmts, strict: _}
                 | GvGraph::DiGraph {id: _, stmts, strict: _} => {
-                for statement in stmts.iter() {
-                    self.process_statement(statement);
+                let attributes = get_graph_attributes(&stmts);
+                self.runtime = self.get_runtime_settings_from_graph_attributes(&attributes);
+                self.on_retry = self.get_on_retry_from_graph_attributes(&attributes);
+                self.process_statements(&stmts, AttributeDefaults::new());
+            }
+        }
+    }
+
+    /// Reads the runtime settings from the attributes of the graph itself.
+    fn get_runtime_settings_from_graph_attributes(
+        &mut self,
+        attributes: &[Attribute]
+    ) -> RuntimeSettings {
+        let flavor = attributes.iter()
+            .find(|attr| id_to_string(&attr.0) == GRAPH_FLAVOR_ATTR)
+            .map(|attr| id_to_string_value(&attr.1));
+        let current_thread = match flavor.as_deref() {
+            None | Some(GRAPH_FLAVOR_MULTI_THREAD_VAL) => false,
+            Some(GRAPH_FLAVOR_CURRENT_THREAD_VAL) => true,
+            Some(flavor) => {
+                let message = format!(
+                    "unknown `{}` `{}`; supported flavors are `{}` and `{}`",
+                    GRAPH_FLAVOR_ATTR, flavor, GRAPH_FLAVOR_MULTI_THREAD_VAL,
+                    GRAPH_FLAVOR_CURRENT_THREAD_VAL
+                );
+                let error = self.source_map.error_at_graph_attribute(GRAPH_FLAVOR_ATTR, &message);
+                self.push_error(error);
+                false
+            },
+        };
+        let mut get_positive_integer = |key| {
+            match get_positive_integer_attribute(attributes, key) {
+                Ok(value) => value,
+                Err(message) => {
+                    let error = self.source_map.error_at_graph_attribute(key, &message);
+                    self.push_error(error);
+                    None
+                },
+            }
+        };
+        let worker_threads = get_positive_integer(GRAPH_WORKER_THREADS_ATTR);
+        let max_blocking_threads = get_positive_integer(GRAPH_MAX_BLOCKING_THREADS_ATTR);
+        if current_thread && worker_threads.is_some() {
+            let message = format!(
+                "`{}` only applies to the `{}` flavor, not `{}`",
+                GRAPH_WORKER_THREADS_ATTR, GRAPH_FLAVOR_MULTI_THREAD_VAL,
+                GRAPH_FLAVOR_CURRENT_THREAD_VAL
+            );
+            let error = self.source_map.error_at_graph_attribute(
+                GRAPH_WORKER_THREADS_ATTR, &message
+            );
+            self.push_error(error);
+        }
+        let thread_name = attributes.iter()
+            .find(|attr| id_to_string(&attr.0) == GRAPH_THREAD_NAME_ATTR)
+            .map(|attr| id_to_string_value(&attr.1));
+        RuntimeSettings { current_thread, worker_threads, max_blocking_threads, thread_name }
+    }
+
+    /// Reads the path of the function the graph calls before each retry of a node from the
+    /// graph's `on_retry` attribute.
+    fn get_on_retry_from_graph_attributes(&mut self, attributes: &[Attribute]) -> Option<String> {
+        let on_retry = attributes.iter()
+            .find(|attr| id_to_string(&attr.0) == GRAPH_ON_RETRY_ATTR)
+            .map(|attr| id_to_string_value(&attr.1))?;
+        if syn::parse_str::<syn::Path>(&on_retry).is_err() {
+            let message = format!(
+                "`{}` must be the path of a function, like `my_module::my_function`, found `{}`",
+                GRAPH_ON_RETRY_ATTR, on_retry
+            );
+            let error = self.source_map.error_at_graph_attribute(GRAPH_ON_RETRY_ATTR, &message);
+            self.push_error(error);
+            return None;
+        }
+        Some(on_retry)
+    }
+
+    /// Processes the statements of the graph or a subgraph, returning the names of all the nodes
+    /// mentioned in them.
+    fn process_statements(
+        &mut self,
+        stmts: &[Stmt],
+        mut defaults: AttributeDefaults
+    ) -> Vec<String> {
+        let mut members = Vec::<String>::new();
+        for statement in stmts.iter() {
+            for member in self.process_statement(statement, &mut defaults) {
+                if !members.contains(&member) {
+                    members.push(member);
                 }
             }
         }
+        members
     }
 
-    fn process_statement(&mut self, statement: &Stmt) {
+    fn process_statement(
+        &mut self,
+        statement: &Stmt,
+        defaults: &mut AttributeDefaults
+    ) -> Vec<String> {
         match statement {
-            Stmt::Node(node) => self.process_node(node),
-            Stmt::Edge(edge) => self.process_edge(edge),
-            _ => {}
+            Stmt::Node(node) => vec![self.process_node(node, defaults)],
+            Stmt::Edge(edge) => self.process_edge(edge, defaults),
+            Stmt::GAttribute(attributes) => {
+                defaults.add(attributes);
+                Vec::<String>::new()
+            },
+            Stmt::Subgraph(subgraph) => self.process_subgraph(subgraph, defaults),
+            _ => Vec::<String>::new(),
         }
     }
 
-    fn process_node(&mut self, node: &GvNode) {
-        match get_nodetype_from_gv_node(node) {
-            Some(nodetype) => {
-                let node_id = id_to_string(&node.id.0);
-                let branch = get_branch_value_from_node_attributes(&node.attributes);
-                self.add_node(&node_id, &nodetype, &branch);
-                if is_start_node(&node) {
-                    self.start_node = node_id;
+    /// Processes a subgraph, returning the names of the nodes it contains.
+    ///
+    /// Nodes declared in a subgraph are part of the graph like any other node.  Subgraphs whose
+    /// names start with "cluster" additionally prefix the remainder of their name to the generated
+    /// task names of the nodes declared inside them.  As in DOT, subgraphs sharing a name are the
+    /// same subgraph, so `subgraph cluster_x {}` in an edge refers to every node declared in any
+    /// `cluster_x` so far.
+    fn process_subgraph(&mut self, subgraph: &Subgraph, defaults: &AttributeDefaults) -> Vec<String> {
+        let cluster = get_cluster_name(&subgraph.id);
+        if let Some(cluster) = &cluster {
+            self.clusters.push(cluster.clone());
+        }
+        // Defaults set inside the subgraph stay inside the subgraph.
+        let mut members = self.process_statements(&subgraph.stmts, defaults.clone());
+        if cluster.is_some() {
+            self.clusters.pop();
+        }
+        if let Id::Anonymous(_) = subgraph.id {
+            return members;
+        }
+        let all_members = self.subgraph_members.entry(id_to_string(&subgraph.id)).or_default();
+        for member in members.drain(..) {
+            if !all_members.contains(&member) {
+                all_members.push(member);
+            }
+        }
+        all_members.clone()
+    }
+
+    /// Declares a node from a node statement.
+    ///
+    /// As in DOT, the default node attributes only apply to a node the first time it's mentioned,
+    /// whether in a node statement or an edge.  Later statements for the same node add to the
+    /// attributes it already has, overriding any they give again, and the node keeps its edges.
+    fn process_node(&mut self, node: &GvNode, defaults: &AttributeDefaults) -> String {
+        let node_id = id_to_string(&node.id.0);
+        let attributes = match self.node_attributes.get(&node_id) {
+            Some(declared) => with_defaults(&node.attributes, declared),
+            None => defaults.node_attributes(&node.attributes),
+        };
+        self.declare_node(node_id.clone(), &attributes);
+        node_id
+    }
+
+    /// Declares a node with all of its attributes, replacing any earlier declaration of it and
+    /// adding the destinations of the edges already leaving it.
+    fn declare_node(&mut self, node_id: String, attributes: &[Attribute]) {
+        self.forget_node(&node_id);
+        self.node_attributes.insert(node_id.clone(), attributes.to_vec());
+        let nodetype = get_nodetype_from_attributes(attributes);
+        let branch = get_branch_value_from_node_attributes(attributes);
+        let join = get_join_value_from_node_attributes(attributes);
+        let fanout = get_fanout_from_node_attributes(attributes);
+        let gather = get_gather_from_node_attributes(attributes);
+        let yields = get_yield_from_node_attributes(attributes);
+        let max_concurrency = self.get_positive_integer_from_node_attributes(
+            &node_id, attributes, NODE_MAX_CONCURRENCY_ATTR
+        );
+        let max_concurrent_elements = self.get_positive_integer_from_node_attributes(
+            &node_id, attributes, NODE_MAX_CONCURRENT_ELEMENTS_ATTR
+        );
+        let max_iterations = self.get_positive_integer_from_node_attributes(
+            &node_id, attributes, NODE_MAX_ITERATIONS_ATTR
+        );
+        let timeout = self.get_duration_from_node_attributes(
+            &node_id, attributes, NODE_TIMEOUT_ATTR
+        );
+        let retry_policy = self.get_retry_policy_from_node_attributes(&node_id, attributes);
+        let entry_point = get_entry_point_name(attributes)
+            .filter(|_| nodetype.is_some())
+            .map(|entry_name| EntryPoint { name: entry_name, node: node_id.clone() });
+        // A node declared again keeps its place among the entry points.
+        match self.entry_points.iter().position(|entry_point| entry_point.node == node_id) {
+            Some(index) => match entry_point {
+                Some(entry_point) => self.entry_points[index] = entry_point,
+                None => {
+                    self.entry_points.remove(index);
+                },
+            },
+            None => self.entry_points.extend(entry_point),
+        }
+        if let Some(nodetype) = &nodetype {
+            self.add_node(&node_id, nodetype, &branch, fanout);
+            if join == NODE_JOIN_ALL_VAL {
+                self.join_nodes.insert(node_id.clone());
+            }
+            if gather {
+                self.gather_nodes.insert(node_id.clone());
+            }
+            if yields {
+                self.yield_nodes.insert(node_id.clone());
+            }
+            if let Some(max_concurrency) = max_concurrency {
+                self.max_concurrency.insert(node_id.clone(), max_concurrency);
+            }
+            if let Some(max_concurrent_elements) = max_concurrent_elements {
+                self.max_concurrent_elements.insert(node_id.clone(), max_concurrent_elements);
+            }
+            if let Some(max_iterations) = max_iterations {
+                self.max_iterations.insert(node_id.clone(), max_iterations);
+            }
+            if let Some(timeout) = timeout {
+                self.timeouts.insert(node_id.clone(), timeout);
+            }
+            if let Some(retry_policy) = retry_policy {
+                self.retry_policies.insert(node_id.clone(), retry_policy);
+            }
+            for index in 0..self.edges.len() {
+                if self.edges[index].source == node_id {
+                    self.add_edge_destination(index);
                 }
+            }
+        }
+        let declaration = NodeDeclaration {
+            name: node_id, nodetype, branch, join, fanout, max_concurrent_elements, max_iterations
+        };
+        // A node declared again keeps its place in the order nodes were declared.
+        match self.declarations.iter().position(|declared| declared.name == declaration.name) {
+            Some(index) => self.declarations[index] = declaration,
+            None => self.declarations.push(declaration),
+        }
+    }
+
+    /// Removes everything an earlier declaration of a node set, so it can be declared again.
+    fn forget_node(&mut self, node_id: &String) {
+        self.nodes.remove(node_id);
+        self.join_nodes.remove(node_id);
+        self.gather_nodes.remove(node_id);
+        self.yield_nodes.remove(node_id);
+        self.max_concurrency.remove(node_id);
+        self.max_concurrent_elements.remove(node_id);
+        self.max_iterations.remove(node_id);
+        self.timeouts.remove(node_id);
+        self.retry_policies.remove(node_id);
+    }
+
+    /// Reads a node attribute that must be a positive integer, reporting any other value.
+    fn get_positive_integer_from_node_attributes(
+        &mut self,
+        node_id: &str,
+        attributes: &[Attribute],
+        key: &str
+    ) -> Option<usize> {
+        match get_positive_integer_attribute(attributes, key) {
+            Ok(value) => value,
+            Err(message) => {
+                let error = self.source_map.error_at_node_attribute(node_id, key, &message);
+                self.push_error(error);
+                None
             },
-            None => {}
         }
     }
 
-    fn process_edge(&mut self, edge: &GvEdge) {
-        match &edge.ty {
-            EdgeTy::Pair(src, dest) => self.process_edge_pair(src, dest, &edge.attributes),
-            EdgeTy::Chain(vertices) => self.process_edge_chain(vertices, &edge.attributes)
+    /// Reads a node attribute that must be a duration, reporting any other value.
+    fn get_duration_from_node_attributes(
+        &mut self,
+        node_id: &str,
+        attributes: &[Attribute],
+        key: &str
+    ) -> Option<Duration> {
+        match get_duration_attribute(attributes, key) {
+            Ok(value) => value,
+            Err(message) => {
+                let error = self.source_map.error_at_node_attribute(node_id, key, &message);
+                self.push_error(error);
+                None
+            },
         }
     }
 
-    fn process_edge_chain(&mut self, vertices: &Vec<Vertex>, attrs: &Vec<Attribute>) {
-        for i in 1..=vertices.len()-1 {
-            self.process_edge_pair(&vertices[i-1], &vertices[i], attrs);
+    /// Reads the `retry`, `retry_delay` and `backoff` attributes of a node, reporting a delay or
+    /// backoff without retries, and an exponential backoff without a delay to start from.
+    fn get_retry_policy_from_node_attributes(
+        &mut self,
+        node_id: &String,
+        attributes: &[Attribute]
+    ) -> Option<RetryPolicy> {
+        let retries = self.get_positive_integer_from_node_attributes(
+            node_id, attributes, NODE_RETRY_ATTR
+        );
+        let delay = self.get_duration_from_node_attributes(
+            node_id, attributes, NODE_RETRY_DELAY_ATTR
+        );
+        let backoff = attributes.iter()
+            .find(|attr| id_to_string(&attr.0) == NODE_BACKOFF_ATTR)
+            .map(|attr| id_to_string_value(&attr.1));
+        let exponential = match backoff.as_deref() {
+            None | Some(NODE_BACKOFF_FIXED_VAL) => false,
+            Some(NODE_BACKOFF_EXPONENTIAL_VAL) => true,
+            Some(backoff) => {
+                let message = format!(
+                    "unknown `{}` `{}`; supported backoffs are `{}` and `{}`",
+                    NODE_BACKOFF_ATTR, backoff, NODE_BACKOFF_FIXED_VAL, NODE_BACKOFF_EXPONENTIAL_VAL
+                );
+                let error = self.source_map.error_at_node_attribute(
+                    node_id, NODE_BACKOFF_ATTR, &message
+                );
+                self.push_error(error);
+                false
+            },
+        };
+        let unused = if retries.is_some() {
+            None
+        } else if delay.is_some() {
+            Some(NODE_RETRY_DELAY_ATTR)
+        } else if backoff.is_some() {
+            Some(NODE_BACKOFF_ATTR)
+        } else {
+            None
+        };
+        if let Some(key) = unused {
+            let message = format!(
+                "`{}` only applies to nodes with `{}`, which node `{}` doesn't have",
+                key, NODE_RETRY_ATTR, node_id
+            );
+            self.push_error(self.source_map.error_at_node_attribute(node_id, key, &message));
         }
+        if exponential && delay.is_none() {
+            let message = format!(
+                "`{}={}` needs a `{}` to start from",
+                NODE_BACKOFF_ATTR, NODE_BACKOFF_EXPONENTIAL_VAL, NODE_RETRY_DELAY_ATTR
+            );
+            let error = self.source_map.error_at_node_attribute(
+                node_id, NODE_BACKOFF_ATTR, &message
+            );
+            self.push_error(error);
+        }
+        Some(RetryPolicy { retries: retries?, delay, exponential })
     }
 
-    fn process_edge_pair(&mut self, src: &Vertex, dest: &Vertex, attrs: &Vec<Attribute>) {
-        let matcher_value = get_match_value_from_edge_attributes(attrs);
-        match (src, dest) {
-            (Vertex::N(src_id), Vertex::N(dest_id)) =>
-                self.add_edge(
-                    &id_to_string(&src_id.0),
-                    &id_to_string(&dest_id.0),
-                    &matcher_value,
-                ),
-            _ => {}
+    /// Declares a node first mentioned in an edge, which in DOT picks up the default node
+    /// attributes just like an explicit node statement.
+    ///
+    /// Without a default `type` the node is only declared once a node statement gives it one, but
+    /// it still keeps the defaults it was first mentioned with.
+    fn declare_implicit_node(&mut self, node_id: &String, defaults: &AttributeDefaults) {
+        if self.node_attributes.contains_key(node_id) {
+            return;
+        }
+        if get_nodetype_from_attributes(&defaults.node).is_none() {
+            self.node_attributes.insert(node_id.clone(), defaults.node.clone());
+            return;
         }
+        self.declare_node(node_id.clone(), &defaults.node);
     }
 
-    pub fn get_output_type(&self) -> TokenStream {
-        let mut output_type: Option<Ident> = None;
-        for val in self.nodes.values() {
-            if val.is_terminating_node() {
-                output_type = Some(val.get_nodetype_ident());
+    fn process_edge(&mut self, edge: &GvEdge, defaults: &AttributeDefaults) -> Vec<String> {
+        let attributes = defaults.edge_attributes(&edge.attributes);
+        let vertices = match &edge.ty {
+            EdgeTy::Pair(src, dest) => vec![src, dest],
+            EdgeTy::Chain(vertices) => vertices.iter().collect(),
+        };
+        let vertex_members: Vec<Vec<String>> = vertices.into_iter()
+            .map(|vertex| self.process_vertex(vertex, defaults))
+            .collect();
+        for i in 1..vertex_members.len() {
+            let sources = self.get_exit_nodes(&vertex_members[i-1]);
+            for src in sources.iter() {
+                for dest in vertex_members[i].iter() {
+                    self.add_edge(src, dest, &attributes);
+                }
             }
         }
+        vertex_members.into_iter().flatten().collect()
+    }
+
+    /// Resolves one end of an edge into the nodes it stands for: either a single node, or every
+    /// node in a subgraph.
+    fn process_vertex(&mut self, vertex: &Vertex, defaults: &AttributeDefaults) -> Vec<String> {
+        match vertex {
+            Vertex::N(node_id) => {
+                let name = id_to_string(&node_id.0);
+                self.declare_implicit_node(&name, defaults);
+                vec![name]
+            },
+            Vertex::S(subgraph) => self.process_subgraph(subgraph, defaults),
+        }
+    }
+
+    /// The nodes of a group that have no edges to other nodes of the same group, which are the
+    /// sources of edges leaving a subgraph.  Edges from a node back to itself don't count, so a
+    /// node with a loop still leads on to the nodes after it.
+    fn get_exit_nodes(&self, members: &[String]) -> Vec<String> {
+        members.iter()
+            .filter(|member| !self.edges.iter().any(|edge| {
+                &&edge.source == member
+                    && &&edge.destination != member
+                    && members.contains(&edge.destination)
+            }))
+            .cloned()
+            .collect()
+    }
+
+    /// The output type of the graph, which is the output type of the first node (in declaration
+    /// order) that can end the graph.  All other such nodes are checked against it by the
+    /// `ExecutableGraph`.
+    pub fn get_output_type(&self) -> TokenStream {
+        let output_type = self.get_ordered_nodes().into_iter()
+            .find_map(|node| node.get_terminal_output_type());
         match output_type {
-            Some(ident) => quote!{<#ident as conflagrate::NodeType>::ReturnType},
+            Some(output_type) => output_type,
             None => quote!{()}
         }
     }
 
-    fn get_start_node(&self) -> Option<&Nodes> {
-        self.nodes.get(&self.start_node)
+    /// The executable nodes in the order they were declared in the graph.
+    pub fn get_ordered_nodes(&self) -> Vec<&Nodes> {
+        let mut seen = HashSet::<&String>::new();
+        self.declarations.iter()
+            .filter(|declaration| seen.insert(&declaration.name))
+            .filter_map(|declaration| self.nodes.get(&declaration.name))
+            .collect()
     }
 
-    pub fn get_start_node_name(&self) -> &String {
-        match self.get_start_node() {
-            Some(node) => node.get_name(),
-            None => panic!("No starting node found!  Give one node the attribute 'start'.")
-        }
+    pub fn get_entry_points(&self) -> &Vec<EntryPoint> {
+        &self.entry_points
+    }
+
+    pub fn get_nodetype(&self, node: &String) -> TokenStream {
+        // Only typed nodes are made entry points, and edges are checked to be between typed nodes
+        // before code is generated.
+        let nodetype = self.nodes.get(node).unwrap().get_nodetype_ident();
+        quote!{#nodetype}
     }
 
-    pub fn get_start_node_nodetype(&self) -> TokenStream {
-        match self.get_start_node() {
-            Some(start_node) => {
-                let start_node_nodetype = start_node.get_nodetype_ident();
-                quote!{#start_node_nodetype}
+    /// Whether the node waits for the outputs of all of its predecessors before running.
+    pub fn is_join_node(&self, node: &String) -> bool {
+        self.join_nodes.contains(node)
+    }
+
+    /// Whether the node is one of the nodes racing each other after a `race` node.
+    pub fn is_race_destination(&self, node: &String) -> bool {
+        self.nodes.values().any(|source| match source.get_destinations() {
+            Branches::Race(destinations) => destinations.contains(node),
+            _ => false,
+        })
+    }
+
+    /// Whether the node is run by a `sequential` or `pipe` node.
+    pub fn is_sequential_destination(&self, node: &String) -> bool {
+        self.nodes.values().any(|source| match source.get_destinations() {
+            Branches::Sequential(destinations) | Branches::Pipe(destinations) => {
+                destinations.contains(node)
             },
-            None => panic!("No starting node found!  Give one node the attribute 'start'.")
+            _ => false,
+        })
+    }
+
+    /// The `map` function of the edge from `source` to `destination`, if it has one.
+    pub fn get_edge_map(&self, source: &String, destination: &String) -> Option<String> {
+        self.edges.iter()
+            .find(|edge| &edge.source == source && &edge.destination == destination)
+            .filter(|edge| !edge.map.is_empty())
+            .map(|edge| edge.map.clone())
+    }
+
+    /// How many times the edge from `source` to `destination` may be taken in one run, set on the
+    /// edge or as the default for all edges leaving `source`.
+    pub fn get_iteration_limit(&self, source: &String, destination: &String) -> Option<usize> {
+        let edge = self.edges.iter().find(|edge| {
+            &edge.source == source
+                && &edge.destination == destination
+                && !edge.on_exhausted
+                && !edge.on_timeout
+        })?;
+        edge.max_iterations.or_else(|| self.max_iterations.get(source).copied())
+    }
+
+    /// The node to go to in place of an edge from `source` that reached its iteration limit.
+    pub fn get_exhausted_destination(&self, source: &String) -> Option<String> {
+        self.edges.iter()
+            .find(|edge| &edge.source == source && edge.on_exhausted)
+            .map(|edge| edge.destination.clone())
+    }
+
+    /// How long the node may run before it times out, if it has a `timeout`.
+    pub fn get_timeout(&self, node: &String) -> Option<Duration> {
+        self.timeouts.get(node).copied()
+    }
+
+    /// The node to go to with the input of `source` if it times out.
+    pub fn get_timeout_destination(&self, source: &String) -> Option<String> {
+        self.edges.iter()
+            .find(|edge| &edge.source == source && edge.on_timeout)
+            .map(|edge| edge.destination.clone())
+    }
+
+    /// How the node is retried if it fails, if it has a `retry` attribute.
+    pub fn get_retry_policy(&self, node: &String) -> Option<RetryPolicy> {
+        self.retry_policies.get(node).cloned()
+    }
+
+    /// The path of the function to call before each retry of a node, if the graph has one.
+    pub fn get_on_retry(&self) -> Option<&String> {
+        self.on_retry.as_ref()
+    }
+
+    /// Whether the node or any of its edges has an iteration limit.
+    pub fn has_iteration_limit(&self, node: &String) -> bool {
+        self.max_iterations.contains_key(node)
+            || self.edges.iter().any(|edge| &edge.source == node && edge.max_iterations.is_some())
+    }
+
+    /// Whether the node receives the collected outputs of a node run by a `foreach` node.
+    pub fn is_gather_node(&self, node: &String) -> bool {
+        self.gather_nodes.contains(node)
+    }
+
+    /// Whether a loop running the node in a single task should yield to the runtime each time
+    /// around.
+    pub fn yields(&self, node: &String) -> bool {
+        self.yield_nodes.contains(node)
+    }
+
+    /// The gather node collecting the outputs of a node run by a `foreach` node, if it has one.
+    pub fn get_gather_node(&self, node: &String) -> Option<String> {
+        match self.nodes.get(node)?.get_destinations() {
+            Branches::Parallel(destinations) => destinations.into_iter()
+                .map(|destination| destination.node)
+                .find(|destination| self.is_gather_node(destination)),
+            _ => None,
+        }
+    }
+
+    /// The limit on how many invocations of the node may run at once, across every run of the
+    /// graph, if it has one.
+    pub fn get_max_concurrency(&self, node: &String) -> Option<usize> {
+        self.max_concurrency.get(node).copied()
+    }
+
+    /// The limit on how many elements of a `foreach` node's output may run at once, if it has one.
+    pub fn get_max_concurrent_elements(&self, node: &String) -> Option<usize> {
+        self.max_concurrent_elements.get(node).copied()
+    }
+
+    /// The executable nodes with edges into a node, in the order the edges were declared.  Each
+    /// fills the slot of a join node's input at its position in this list.
+    pub fn get_join_sources(&self, node: &String) -> Vec<String> {
+        let mut sources = Vec::<String>::new();
+        for edge in self.edges.iter().filter(|edge| &edge.destination == node) {
+            if self.nodes.contains_key(&edge.source) && !sources.contains(&edge.source) {
+                sources.push(edge.source.clone());
+            }
+        }
+        sources
+    }
+
+    /// The slot of a join node's input filled by the edge from `source`, or `None` if
+    /// `destination` isn't a join node.
+    pub fn get_join_slot(&self, source: &String, destination: &String) -> Option<usize> {
+        if !self.is_join_node(destination) {
+            return None;
+        }
+        self.get_join_sources(destination).iter().position(|join_source| join_source == source)
+    }
+
+    /// The parallel node whose branches a join node brings back together, if it has one: the
+    /// closest node spawning several trailing nodes that every path from an entry point to the
+    /// join node passes through.
+    pub fn get_join_fan_out(&self, node: &String) -> Option<String> {
+        if !self.is_join_node(node) {
+            return None;
+        }
+        let dominators = self.get_dominators();
+        let fan_outs: Vec<&String> = dominators.get(node)?.iter()
+            .filter(|dominator| *dominator != node && self.fans_out(dominator))
+            .collect();
+        // Every fan-out dominates the join node, so the closest is dominated by all the others.
+        fan_outs.iter()
+            .find(|fan_out| fan_outs.iter().all(|other| dominators[**fan_out].contains(*other)))
+            .map(|fan_out| (*fan_out).clone())
+    }
+
+    /// Whether the node's trailing nodes are brought back together by a join node, so that each
+    /// run of the node has to split off a new fan-out instance.
+    pub fn is_join_fan_out(&self, node: &String) -> bool {
+        self.join_nodes.iter().any(|join| self.get_join_fan_out(join).as_ref() == Some(node))
+    }
+
+    /// Whether the node spawns several of its trailing nodes in parallel.
+    fn fans_out(&self, node: &String) -> bool {
+        matches!(
+            self.nodes.get(node).map(Nodes::get_destinations),
+            Some(Branches::Parallel(destinations)) if destinations.len() > 1
+        )
+    }
+
+    /// For each node reachable from an entry point, the nodes that every path from an entry point
+    /// to it passes through, including itself.
+    fn get_dominators(&self) -> HashMap<String, HashSet<String>> {
+        let reachable = self.get_reachable_nodes();
+        let entry_nodes: HashSet<&String> = self.entry_points.iter()
+            .map(|entry_point| &entry_point.node)
+            .collect();
+        let mut dominators: HashMap<String, HashSet<String>> = reachable.iter()
+            .map(|name| if entry_nodes.contains(name) {
+                (name.clone(), HashSet::from([name.clone()]))
+            } else {
+                (name.clone(), reachable.clone())
+            })
+            .collect();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for name in reachable.iter().filter(|name| !entry_nodes.contains(name)) {
+                let mut common: Option<HashSet<String>> = None;
+                for edge in self.edges.iter().filter(|edge| &edge.destination == name) {
+                    if let Some(source_dominators) = dominators.get(&edge.source) {
+                        common = Some(match common {
+                            Some(common) => {
+                                common.intersection(source_dominators).cloned().collect()
+                            },
+                            None => source_dominators.clone(),
+                        });
+                    }
+                }
+                let mut name_dominators = common.unwrap_or_default();
+                name_dominators.insert(name.clone());
+                if name_dominators != dominators[name] {
+                    dominators.insert(name.clone(), name_dominators);
+                    changed = true;
+                }
+            }
         }
+        dominators
     }
 
     pub fn get_name(&self) -> Ident {
         format_ident!("{}", &self.name)
     }
 
+    /// The runtime the graph's `run` methods create, as set by the graph's own attributes.
+    pub fn get_runtime_settings(&self) -> &RuntimeSettings {
+        &self.runtime
+    }
+
+    /// The node's name prefixed with the names of the clusters it was declared in.
+    pub fn get_qualified_name(&self, node: &String) -> String {
+        self.qualified_names.get(node).cloned().unwrap_or_else(|| node.clone())
+    }
+
     pub fn get_nodes(&self) -> &HashMap<String, Nodes> {
         &self.nodes
     }
 
+    pub fn get_declarations(&self) -> &Vec<NodeDeclaration> {
+        &self.declarations
+    }
+
+    pub fn get_edges(&self) -> &Vec<EdgeDeclaration> {
+        &self.edges
+    }
+
+    pub fn get_source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Names of the nodes that can be reached by following edges from any entry point.
+    pub fn get_reachable_nodes(&self) -> HashSet<String> {
+        let mut reachable = HashSet::<String>::new();
+        let mut pending: Vec<String> = self.entry_points.iter()
+            .map(|entry_point| entry_point.node.clone())
+            .collect();
+        while let Some(name) = pending.pop() {
+            if !reachable.insert(name.clone()) {
+                continue;
+            }
+            for edge in self.edges.iter().filter(|edge| edge.source == name) {
+                if self.nodes.contains_key(&edge.destination) {
+                    pending.push(edge.destination.clone());
+                }
+            }
+        }
+        reachable
+    }
+
     pub fn into_source(self) -> String {
-        self.source
+        self.source_map.get_text().clone()
+    }
+}
+
+/// Converts the DOT parser's error message into an error pointing at the reported position.
+///
+/// The parser reports errors in the form:
+/// ```text
+///  --> 1:18
+///   |
+/// 1 | digraph G { a -> }
+///   |                  ^---
+///   |
+///   = expected vertex
+/// ```
+fn parse_error_to_syn_error(source_map: &SourceMap, error: &str) -> syn::Error {
+    let reason = error.lines()
+        .filter_map(|line| line.trim().strip_prefix("= "))
+        .next_back()
+        .unwrap_or("invalid DOT syntax");
+    let message = format!("unable to parse graph: {}", reason);
+    let position = error.lines()
+        .find_map(|line| line.trim().strip_prefix("--> "))
+        .and_then(|position| {
+            let (line, column) = position.split_once(':')?;
+            Some((line.trim().parse::<usize>().ok()?, column.trim().parse::<usize>().ok()?))
+        });
+    match position {
+        Some((line, column)) => source_map.error_at_position(line, column, &message),
+        None => source_map.error(&message),
     }
 }
 
+fn is_valid_ident(name: &str) -> bool {
+    syn::parse_str::<Ident>(name).is_ok()
+}
+
 fn get_graph_name(graph: &GvGraph) -> String {
     match graph {
         GvGraph::Graph {id, strict: _, stmts: _}
@@ -171,6 +1099,14 @@ fn get_graph_name(graph: &GvGraph) -> String {
     }
 }
 
+/// The name a cluster contributes to task names: the subgraph name without the "cluster" prefix,
+/// or `None` for subgraphs that aren't clusters.
+fn get_cluster_name(id: &Id) -> Option<String> {
+    let name = id_to_string(id);
+    let cluster = name.strip_prefix(CLUSTER_PREFIX)?.trim_start_matches('_');
+    if cluster.is_empty() { None } else { Some(String::from(cluster)) }
+}
+
 fn id_to_string(id: &Id) -> String {
     match id {
         Id::Html(val) | Id::Escaped(val) | Id::Plain(val) => val.clone(),
@@ -178,8 +1114,35 @@ fn id_to_string(id: &Id) -> String {
     }
 }
 
-fn get_nodetype_from_gv_node(node: &GvNode) -> Option<String> {
-    for attr in node.attributes.iter() {
+/// The text of a quoted DOT string, with the quotes removed and `\"` and `\\` escapes resolved.
+fn id_to_string_value(id: &Id) -> String {
+    let text = id_to_string(id);
+    let unquoted = unquote(&text);
+    if unquoted.len() == text.len() {
+        return text;
+    }
+    let mut value = String::with_capacity(unquoted.len());
+    let mut chars = unquoted.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(next @ ('"' | '\\'))) => {
+                value.push(next);
+                chars.next();
+            },
+            _ => value.push(c),
+        }
+    }
+    value
+}
+
+/// Combines a node's or edge's own attributes with the defaults in scope.  The lookup helpers
+/// below return the first matching attribute, so the element's own attributes come first.
+fn with_defaults(attrs: &[Attribute], defaults: &[Attribute]) -> Vec<Attribute> {
+    attrs.iter().chain(defaults.iter()).cloned().collect()
+}
+
+fn get_nodetype_from_attributes(attrs: &[Attribute]) -> Option<String> {
+    for attr in attrs.iter() {
         let attr_key = id_to_string(&attr.0);
         if attr_key == NODE_TYPE_ATTR {
             return Some(id_to_string(&attr.1))
@@ -188,15 +1151,40 @@ fn get_nodetype_from_gv_node(node: &GvNode) -> Option<String> {
     None
 }
 
-fn is_start_node(node: &GvNode) -> bool {
-    for attr in node.attributes.iter() {
+/// The attributes of the graph itself, given in `graph[...]` statements or as `key=value`
+/// statements.  Attributes of subgraphs don't apply.
+fn get_graph_attributes(stmts: &[Stmt]) -> Vec<Attribute> {
+    // Later statements win, as in DOT, and the lookup helpers return the first match.
+    stmts.iter().rev()
+        .flat_map(|statement| match statement {
+            Stmt::Attribute(attribute) => vec![attribute.clone()],
+            Stmt::GAttribute(GraphAttributes::Graph(attrs)) => {
+                attrs.iter().rev().cloned().collect()
+            },
+            _ => Vec::<Attribute>::new(),
+        })
+        .collect()
+}
+
+/// Reads the `start` attribute: `Some(None)` for the default entry point (`start=true`),
+/// `Some(Some(name))` for a named entry point, and `None` if the node isn't an entry point.
+fn get_entry_point_name(attrs: &[Attribute]) -> Option<Option<String>> {
+    for attr in attrs.iter() {
         let attr_key = id_to_string(&attr.0);
-        if attr_key == NODE_START_ATTR { return true; }
+        if attr_key == NODE_START_ATTR {
+            let value = id_to_string(&attr.1);
+            let value = unquote(&value);
+            return match value.to_lowercase().as_str() {
+                "true" | "" => Some(None),
+                "false" => None,
+                _ => Some(Some(String::from(value))),
+            };
+        }
     }
-    false
+    None
 }
 
-fn get_branch_value_from_node_attributes(attrs: &Vec<Attribute>) -> String {
+fn get_branch_value_from_node_attributes(attrs: &[Attribute]) -> String {
     for attr in attrs.iter() {
         let attr_key = id_to_string(&attr.0);
         if attr_key == NODE_BRANCH_ATTR {
@@ -206,12 +1194,406 @@ fn get_branch_value_from_node_attributes(attrs: &Vec<Attribute>) -> String {
     String::new()
 }
 
-fn get_match_value_from_edge_attributes(attributes: &Vec<Attribute>) -> String {
+fn get_join_value_from_node_attributes(attrs: &[Attribute]) -> String {
+    for attr in attrs.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == NODE_JOIN_ATTR {
+            return id_to_string(&attr.1).to_lowercase();
+        }
+    }
+    String::new()
+}
+
+fn get_fanout_from_node_attributes(attrs: &[Attribute]) -> bool {
+    for attr in attrs.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == NODE_FANOUT_ATTR {
+            return unquote(&id_to_string(&attr.1)).to_lowercase() == "true";
+        }
+    }
+    false
+}
+
+fn get_map_from_edge_attributes(attributes: &[Attribute]) -> String {
+    for attr in attributes.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == EDGE_MAP_ATTR {
+            return id_to_string_value(&attr.1)
+        }
+    }
+    String::new()
+}
+
+fn get_on_exhausted_from_edge_attributes(attributes: &[Attribute]) -> bool {
+    for attr in attributes.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == EDGE_ON_EXHAUSTED_ATTR {
+            return unquote(&id_to_string(&attr.1)).to_lowercase() == "true";
+        }
+    }
+    false
+}
+
+/// Reads an attribute that must be a positive integer, if it's given.
+fn get_positive_integer_attribute(
+    attributes: &[Attribute],
+    key: &str
+) -> Result<Option<usize>, String> {
+    let value = match attributes.iter().find(|attr| id_to_string(&attr.0) == key) {
+        Some(attr) => id_to_string_value(&attr.1),
+        None => return Ok(None),
+    };
+    match value.parse::<usize>() {
+        Ok(number) if number > 0 => Ok(Some(number)),
+        _ => Err(format!("`{}` must be a positive integer, found `{}`", key, value)),
+    }
+}
+
+/// Reads an attribute that must be a duration with a unit, like `500ms` or `30s`, if it's given.
+fn get_duration_attribute(
+    attributes: &[Attribute],
+    key: &str
+) -> Result<Option<Duration>, String> {
+    let value = match attributes.iter().find(|attr| id_to_string(&attr.0) == key) {
+        Some(attr) => id_to_string_value(&attr.1),
+        None => return Ok(None),
+    };
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let duration = match (number.parse::<u64>(), unit) {
+        (Ok(number), "ms") => Some(Duration::from_millis(number)),
+        (Ok(number), "s") => Some(Duration::from_secs(number)),
+        (Ok(number), "m") => number.checked_mul(60).map(Duration::from_secs),
+        (Ok(number), "h") => number.checked_mul(60 * 60).map(Duration::from_secs),
+        _ => None,
+    };
+    match duration {
+        Some(duration) if !duration.is_zero() => Ok(Some(duration)),
+        _ => Err(format!(
+            "`{}` must be a positive duration in `ms`, `s`, `m` or `h`, like `500ms`, found `{}`",
+            key, value
+        )),
+    }
+}
+
+fn get_gather_from_node_attributes(attrs: &[Attribute]) -> bool {
+    for attr in attrs.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == NODE_GATHER_ATTR {
+            return unquote(&id_to_string(&attr.1)).to_lowercase() == "true";
+        }
+    }
+    false
+}
+
+fn get_yield_from_node_attributes(attrs: &[Attribute]) -> bool {
+    for attr in attrs.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == NODE_YIELD_ATTR {
+            return unquote(&id_to_string(&attr.1)).to_lowercase() == "true";
+        }
+    }
+    false
+}
+
+fn get_match_value_from_edge_attributes(attributes: &[Attribute]) -> String {
     for attr in attributes.iter() {
         let attr_key = id_to_string(&attr.0);
         if attr_key == EDGE_VALUE_ATTR {
-            return id_to_string(&attr.1)
+            return id_to_string_value(&attr.1)
+        }
+    }
+    String::new()
+}
+
+fn get_guard_from_edge_attributes(attributes: &[Attribute]) -> String {
+    for attr in attributes.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == EDGE_WHEN_ATTR {
+            return id_to_string_value(&attr.1)
+        }
+    }
+    String::new()
+}
+
+fn get_pattern_from_edge_attributes(attributes: &[Attribute]) -> String {
+    for attr in attributes.iter() {
+        let attr_key = id_to_string(&attr.0);
+        if attr_key == EDGE_PATTERN_ATTR {
+            return id_to_string_value(&attr.1)
         }
     }
     String::new()
 }
+
+#[cfg(test)]
+impl DescriptiveGraph {
+    /// Builds a graph from DOT text as if it were read from the file `test.dot`, so errors carry
+    /// the line and column they're located at.
+    pub fn from_test_source(text: &str) -> Result<DescriptiveGraph, syn::Error> {
+        let source_map = SourceMap::from_file(
+            String::from(text), String::from("test.dot"), proc_macro2::Span::call_site()
+        );
+        DescriptiveGraph::from(source_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(key: &str, value: &str) -> Vec<Attribute> {
+        vec![Attribute(Id::Plain(String::from(key)), Id::Plain(String::from(value)))]
+    }
+
+    fn error_messages(text: &str) -> Vec<String> {
+        match DescriptiveGraph::from_test_source(text) {
+            Ok(_) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parses_durations_in_each_unit() {
+        let parse = |value| get_duration_attribute(&attributes("timeout", value), "timeout");
+        assert_eq!(parse("250ms"), Ok(Some(Duration::from_millis(250))));
+        assert_eq!(parse("30s"), Ok(Some(Duration::from_secs(30))));
+        assert_eq!(parse("2m"), Ok(Some(Duration::from_secs(120))));
+        assert_eq!(parse("1h"), Ok(Some(Duration::from_secs(3600))));
+        assert_eq!(parse("\"5s\""), Ok(Some(Duration::from_secs(5))));
+        assert_eq!(get_duration_attribute(&Vec::new(), "timeout"), Ok(None));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        let parse = |value| get_duration_attribute(&attributes("timeout", value), "timeout");
+        for value in ["0s", "5", "ms", "5d", "1.5s", "-5s", "99999999999999999999h"] {
+            assert!(parse(value).is_err(), "`{}` should be rejected", value);
+        }
+    }
+
+    #[test]
+    fn parses_positive_integers() {
+        let parse = |value| get_positive_integer_attribute(&attributes("retry", value), "retry");
+        assert_eq!(parse("3"), Ok(Some(3)));
+        assert_eq!(parse("\"3\""), Ok(Some(3)));
+        assert!(parse("0").is_err());
+        assert!(parse("-1").is_err());
+        assert!(parse("many").is_err());
+    }
+
+    #[test]
+    fn reads_retry_policies() {
+        let graph = DescriptiveGraph::from_test_source(
+            r#"digraph G {
+                a[type=A, start=true, retry=3];
+                b[type=B, retry=2, retry_delay="100ms", backoff=exponential];
+                a -> b;
+            }"#
+        ).unwrap();
+        let a = graph.get_retry_policy(&String::from("a")).unwrap();
+        assert_eq!((a.retries, a.delay, a.exponential), (3, None, false));
+        let b = graph.get_retry_policy(&String::from("b")).unwrap();
+        let delay = Some(Duration::from_millis(100));
+        assert_eq!((b.retries, b.delay, b.exponential), (2, delay, true));
+    }
+
+    #[test]
+    fn reports_retry_attributes_without_retry() {
+        let errors = error_messages(
+            r#"digraph G {
+                a[type=A, start=true, retry_delay="1s"];
+            }"#
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("test.dot:2:51: `retry_delay` only applies"),
+            "{}", errors[0]
+        );
+    }
+
+    #[test]
+    fn reports_exponential_backoff_without_delay() {
+        let errors = error_messages(
+            r#"digraph G {
+                a[type=A, start=true, retry=2, backoff=exponential];
+            }"#
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("test.dot:2:56: `backoff=exponential` needs"),
+            "{}", errors[0]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_backoff() {
+        let errors = error_messages(
+            r#"digraph G {
+                a[type=A, start=true, retry=2, backoff=linear];
+            }"#
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("unknown `backoff` `linear`"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn reads_on_retry_hook() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            graph[on_retry="hooks::log_retry"];
+            a[type=A, start=true, retry=2];
+        }"#).unwrap();
+        assert_eq!(graph.get_on_retry().map(String::as_str), Some("hooks::log_retry"));
+    }
+
+    #[test]
+    fn reports_on_retry_hook_that_isnt_a_path() {
+        let errors = error_messages(r#"digraph G {
+            on_retry="log retry";
+            a[type=A, start=true, retry=2];
+        }"#);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("test.dot:2:22: `on_retry` must be the path of a function"),
+            "{}", errors[0]
+        );
+    }
+
+    #[test]
+    fn reads_runtime_settings() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            graph[flavor=current_thread, max_blocking_threads=4];
+            thread_name="worker";
+            a[type=A, start=true];
+        }"#).unwrap();
+        let runtime = graph.get_runtime_settings();
+        assert!(runtime.current_thread);
+        assert_eq!(runtime.worker_threads, None);
+        assert_eq!(runtime.max_blocking_threads, Some(4));
+        assert_eq!(runtime.thread_name.as_deref(), Some("worker"));
+    }
+
+    #[test]
+    fn reports_unknown_flavor() {
+        let errors = error_messages(r#"digraph G {
+            flavor=fast;
+            a[type=A, start=true];
+        }"#);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("test.dot:2:20: unknown `flavor` `fast`"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn reports_worker_threads_with_current_thread_flavor() {
+        let errors = error_messages(r#"digraph G {
+            graph[flavor=current_thread, worker_threads=2];
+            a[type=A, start=true];
+        }"#);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].starts_with("test.dot:2:57: `worker_threads` only applies to the"),
+            "{}", errors[0]
+        );
+    }
+
+    #[test]
+    fn finds_the_fan_out_each_join_node_closes() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B];
+            c[type=C];
+            d[type=D];
+            e[type=E];
+            f[type=F, join=all];
+            g[type=G, join=all];
+            a -> b -> d -> f -> g;
+            b -> e -> f;
+            a -> c -> g;
+        }"#).unwrap();
+        let name = |name: &str| String::from(name);
+        assert_eq!(graph.get_join_fan_out(&name("f")), Some(name("b")));
+        assert_eq!(graph.get_join_fan_out(&name("g")), Some(name("a")));
+        assert!(graph.is_join_fan_out(&name("a")));
+        assert!(!graph.is_join_fan_out(&name("d")));
+    }
+
+    #[test]
+    fn finds_no_fan_out_for_inputs_from_separate_entry_points() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B, start=second];
+            c[type=C, join=all];
+            a -> c;
+            b -> c;
+        }"#).unwrap();
+        assert_eq!(graph.get_join_fan_out(&String::from("c")), None);
+    }
+
+    #[test]
+    fn node_declared_again_keeps_its_attributes_and_edges() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            node[type=T];
+            a -> b;
+            a[branch=matcher, start=true];
+        }"#).unwrap();
+        let a = String::from("a");
+        assert_eq!(graph.get_nodetype(&a).to_string(), "T");
+        assert!(matches!(
+            graph.get_nodes()[&a].get_destinations(),
+            Branches::Match(destinations) if destinations.len() == 1
+        ));
+        assert_eq!(graph.get_entry_points().len(), 1);
+        assert_eq!(graph.get_declarations().len(), 2);
+    }
+
+    #[test]
+    fn edges_leaving_a_node_declared_later_are_kept() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            a -> b;
+            a[type=A, start=true];
+            b[type=B];
+        }"#).unwrap();
+        assert!(matches!(
+            graph.get_nodes()[&String::from("a")].get_destinations(),
+            Branches::Parallel(destinations) if destinations.len() == 1
+        ));
+    }
+
+    #[test]
+    fn default_node_attributes_only_apply_when_a_node_is_first_mentioned() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            node[type=T];
+            a;
+            node[type=U];
+            a[start=true];
+        }"#).unwrap();
+        assert_eq!(graph.get_nodetype(&String::from("a")).to_string(), "T");
+    }
+
+    #[test]
+    fn reads_timeout_edge_written_before_the_timeout_attribute() {
+        let graph = DescriptiveGraph::from_test_source(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B];
+            c[type=C];
+            a -> b;
+            a -> c [value=timeout];
+            a[timeout="1s"];
+        }"#).unwrap();
+        let a = String::from("a");
+        assert_eq!(graph.get_timeout_destination(&a), Some(String::from("c")));
+        assert!(matches!(
+            graph.get_nodes()[&a].get_destinations(),
+            Branches::Parallel(destinations) if destinations.len() == 1
+        ));
+    }
+
+    #[test]
+    fn reports_an_error_once_when_a_node_is_declared_again() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, timeout=soon];
+            a[retry=2];
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+    }
+}
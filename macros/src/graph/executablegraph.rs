@@ -1,20 +1,38 @@
-use proc_macro2::{Ident, TokenStream};
-use quote::{quote, ToTokens};
-use std::collections::HashMap;
-use crate::graph::descriptivegraph::DescriptiveGraph;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use crate::graph::descriptivegraph::{DescriptiveGraph, EntryPoint, RuntimeSettings};
 use crate::graph::node::{Branches, Nodes};
-use crate::graph::task::{Task, TaskName};
+use crate::graph::sourcemap::respan;
+use crate::graph::task::{
+    ConcurrencyLimit, ContinueTask, JoinSlotTask, RaceTask, Task, TaskName
+};
 
 /// The execution organized and optimized representation of the control flow graph.
 ///
 /// The `ExecutableGraph` translates directly into compilable Rust code in the form of a public
 /// structure with a single `impl` block containing:
 /// * A `const SOURCE: &'static str` providing the original Graphviz graph definition text.
-/// * A public `run()` method that launches a tokio multi-threaded runtime and runs the graph.
+/// * A public `runtime_config()` method giving the tokio runtime configured by the graph's own
+///   attributes.
+/// * A public `run()` method that launches that runtime and runs the graph on it, a public
+///   `run_with()` method that does the same with a given `RuntimeConfig`, and a public `run_on()`
+///   method that runs the graph on an existing runtime through its `Handle`.
 /// * A public `run_graph()` async method that spawns the graph in an already-running tokio
-/// runtime and returns the output from the final executed node as its return value.
+///   runtime and returns the output from the final executed node as its return value, or a
+///   `GraphError` if the graph fails.
+/// * A public `start_graph()` method that spawns the graph in an already-running tokio runtime
+///   and returns a `GraphRunHandle` that can cancel the run or be awaited for its output.
+/// * A set of public `run_{name}()`, `run_with_{name}()`, `run_on_{name}()`, `run_graph_{name}()`,
+///   and `start_graph_{name}()` methods for every named entry point, which do the same starting
+///   from that entry point's node.
 /// * Private "task" methods each named "execute_{node_name}" that implement the nodes of the
-/// control flow graph.
+///   control flow graph.
+/// * Private methods named "join_{node_name}_{slot}" that collect the inputs of join nodes.
+/// * Private methods named "race_{node_name}" that run the nodes trailing a `race` node.
+/// * Private methods named "continue_{node_name}" that follow the edges of the nodes trailing a
+///   `sequential` or `pipe` node.
+/// * Private methods named "limit_{node_name}" that give the concurrency limits of nodes with a
+///   `max_concurrency` attribute.
 ///
 /// Note that the task methods may not correspond 1-to-1 with the nodes defined on the graph.
 /// The conversion process from the descriptive graph to the executable graph may make some
@@ -22,48 +40,116 @@ use crate::graph::task::{Task, TaskName};
 /// the same.
 pub struct ExecutableGraph {
     name: Ident,
-    run_method: RunMethod,
-    run_graph_method: RunGraphMethod,
+    runtime_config_method: RuntimeConfigMethod,
+    run_methods: Vec<RunMethod>,
+    run_graph_methods: Vec<RunGraphMethod>,
+    start_graph_methods: Vec<StartGraphMethod>,
     tasks: Vec<Task>,
+    join_slot_tasks: Vec<JoinSlotTask>,
+    race_tasks: Vec<RaceTask>,
+    continue_tasks: Vec<ContinueTask>,
+    concurrency_limits: Vec<ConcurrencyLimit>,
+    output_type_check: OutputTypeCheck,
     source: String,
 }
 impl ExecutableGraph {
     fn build_tasks(graph: &DescriptiveGraph) -> Vec<Task> {
         let graph_output_type = graph.get_output_type();
-        let graph_nodes_map = graph.get_nodes();
-        let mut tasks = Vec::<Task>::with_capacity(graph_nodes_map.len());
-        for (_, node) in graph_nodes_map {
+        let mut tasks = Vec::<Task>::with_capacity(graph.get_nodes().len());
+        for node in graph.get_ordered_nodes() {
+            // Nodes whose outputs are gathered are run directly by their `foreach` node.
+            if graph.get_gather_node(node.get_name()).is_some() {
+                continue;
+            }
             let mut task_nodes = Vec::<Nodes>::new();
-            Self::collect_nodes_for_task(&node, &mut task_nodes, graph_nodes_map);
-            tasks.push(Task::from_nodes(&task_nodes, &graph_output_type));
+            let loop_start = Self::collect_nodes_for_task(node, &mut task_nodes, graph);
+            tasks.push(Task::from_nodes(&task_nodes, loop_start, graph, &graph_output_type));
         }
         tasks
     }
 
+    fn build_join_slot_tasks(graph: &DescriptiveGraph) -> Vec<JoinSlotTask> {
+        let graph_output_type = graph.get_output_type();
+        graph.get_ordered_nodes().into_iter()
+            .flat_map(|node| JoinSlotTask::for_node(node, graph, &graph_output_type))
+            .collect()
+    }
+
+    fn build_race_tasks(graph: &DescriptiveGraph) -> Vec<RaceTask> {
+        let graph_output_type = graph.get_output_type();
+        graph.get_ordered_nodes().into_iter()
+            .filter_map(|node| RaceTask::for_node(node, graph, &graph_output_type))
+            .collect()
+    }
+
+    fn build_continue_tasks(graph: &DescriptiveGraph) -> Vec<ContinueTask> {
+        let graph_output_type = graph.get_output_type();
+        graph.get_ordered_nodes().into_iter()
+            .filter_map(|node| ContinueTask::for_node(node, graph, &graph_output_type))
+            .collect()
+    }
+
+    fn build_concurrency_limits(graph: &DescriptiveGraph) -> Vec<ConcurrencyLimit> {
+        graph.get_ordered_nodes().into_iter()
+            .filter_map(|node| ConcurrencyLimit::for_node(node.get_name(), graph))
+            .collect()
+    }
+
+    /// Collects the linear progression of nodes starting at `this_node` that can run in one task,
+    /// returning the index of the node the last one loops back to if the progression is a cycle.
     fn collect_nodes_for_task(
         this_node: &Nodes,
         nodes: &mut Vec<Nodes>,
-        graph_nodes_map: &HashMap<String, Nodes>
-    ) {
+        graph: &DescriptiveGraph
+    ) -> Option<usize> {
         nodes.push(this_node.clone());
-        // Look at the node type and its destinations to decide if we should recurse.
+        // Look at the node type and its destinations to decide if we should recurse.  Join nodes
+        // always run in their own task, since they have to wait for their other inputs, and
+        // guarded and limited edges have to be checked before the next node runs.
         if let Nodes::Node(_) = this_node {
             if let Branches::Parallel(destinations) = this_node.get_destinations() {
-                if destinations.len() == 1 {
-                    let next_node = graph_nodes_map.get(destinations.first().unwrap());
-                    Self::collect_nodes_for_task(next_node.unwrap(), nodes, graph_nodes_map);
+                if let [destination] = destinations.as_slice() {
+                    let source = this_node.get_name();
+                    if destination.guard.is_none()
+                        && !graph.is_join_node(&destination.node)
+                        && graph.get_iteration_limit(source, &destination.node).is_none()
+                    {
+                        // A node already in the task closes a cycle, which the task then runs as a
+                        // loop rather than recursing forever.
+                        let loop_start = nodes.iter()
+                            .position(|node| node.get_name() == &destination.node);
+                        if loop_start.is_some() {
+                            return loop_start;
+                        }
+                        let next_node = graph.get_nodes().get(&destination.node);
+                        return Self::collect_nodes_for_task(next_node.unwrap(), nodes, graph);
+                    }
                 }
             }
         }
+        None
     }
 }
 impl From<DescriptiveGraph> for ExecutableGraph {
     fn from(graph: DescriptiveGraph) -> Self {
         Self {
             name: graph.get_name(),
-            run_method: RunMethod::from(&graph),
-            run_graph_method: RunGraphMethod::from(&graph),
+            runtime_config_method: RuntimeConfigMethod::from(graph.get_runtime_settings()),
+            run_methods: graph.get_entry_points().iter()
+                .map(|entry_point| RunMethod::new(entry_point, &graph))
+                .collect(),
+            run_graph_methods: graph.get_entry_points().iter()
+                .map(|entry_point| RunGraphMethod::new(entry_point, &graph))
+                .collect(),
+            start_graph_methods: graph.get_entry_points().iter()
+                .map(|entry_point| StartGraphMethod::new(entry_point, &graph))
+                .collect(),
             tasks: Self::build_tasks(&graph),
+            join_slot_tasks: Self::build_join_slot_tasks(&graph),
+            race_tasks: Self::build_race_tasks(&graph),
+            continue_tasks: Self::build_continue_tasks(&graph),
+            concurrency_limits: Self::build_concurrency_limits(&graph),
+            output_type_check: OutputTypeCheck::from(&graph),
             source: graph.into_source(),
         }
     }
@@ -71,120 +157,364 @@ impl From<DescriptiveGraph> for ExecutableGraph {
 impl ToTokens for ExecutableGraph {
     fn to_tokens(&self, tokens: &mut TokenStream) {
         let graph_name = &self.name;
-        let run_method = &self.run_method;
-        let run_graph_method = &self.run_graph_method;
+        let runtime_config_method = &self.runtime_config_method;
+        let run_methods = &self.run_methods;
+        let run_graph_methods = &self.run_graph_methods;
+        let start_graph_methods = &self.start_graph_methods;
         let tasks = &self.tasks;
+        let join_slot_tasks = &self.join_slot_tasks;
+        let race_tasks = &self.race_tasks;
+        let continue_tasks = &self.continue_tasks;
+        let concurrency_limits = &self.concurrency_limits;
+        let output_type_check = &self.output_type_check;
         let source = &self.source;
         tokens.extend(quote! {
             pub struct #graph_name;
             impl #graph_name {
                 pub const SOURCE: &'static str = #source;
-                #run_method
-                #run_graph_method
+                #runtime_config_method
+                #(#run_methods)*
+                #(#run_graph_methods)*
+                #(#start_graph_methods)*
                 #(#tasks)*
+                #(#join_slot_tasks)*
+                #(#race_tasks)*
+                #(#continue_tasks)*
+                #(#concurrency_limits)*
             }
+            #output_type_check
         })
     }
 }
 
-/// Defines the `run()` method of an executable graph.
+/// Defines the `runtime_config()` method of an executable graph, giving the runtime configured by
+/// the graph's own attributes:
+/// ```no_compile
+/// pub fn runtime_config() -> conflagrate::RuntimeConfig {
+///     conflagrate::RuntimeConfig::{flavor}()
+///         .worker_threads({worker_threads})  // for each attribute given
+/// }
+/// ```
+struct RuntimeConfigMethod {
+    flavor: Ident,
+    settings: Vec<TokenStream>,
+}
+impl From<&RuntimeSettings> for RuntimeConfigMethod {
+    fn from(runtime: &RuntimeSettings) -> Self {
+        let mut settings = Vec::<TokenStream>::new();
+        if let Some(worker_threads) = runtime.worker_threads {
+            settings.push(quote! {.worker_threads(#worker_threads)});
+        }
+        if let Some(max_blocking_threads) = runtime.max_blocking_threads {
+            settings.push(quote! {.max_blocking_threads(#max_blocking_threads)});
+        }
+        if let Some(thread_name) = &runtime.thread_name {
+            settings.push(quote! {.thread_name(#thread_name)});
+        }
+        Self {
+            flavor: match runtime.current_thread {
+                true => format_ident!("current_thread"),
+                false => format_ident!("multi_thread"),
+            },
+            settings,
+        }
+    }
+}
+impl ToTokens for RuntimeConfigMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let flavor = &self.flavor;
+        let settings = &self.settings;
+        tokens.extend(quote! {
+            pub fn runtime_config() -> conflagrate::RuntimeConfig {
+                conflagrate::RuntimeConfig::#flavor() #(#settings)*
+            }
+        });
+    }
+}
+
+/// Defines the `run()`, `run_with()` and `run_on()` methods of an executable graph, or the
+/// `run_{name}()`, `run_with_{name}()` and `run_on_{name}()` methods of a named entry point.
 ///
-/// Generates a simple wrapper method for the `run_graph()` method that looks like the following:
+/// Generates simple wrapper methods for the `run_graph()` method that block on a runtime, which
+/// look like the following:
 /// ```no_compile
 /// pub fn run(
 ///     first_node_args: <{start_nodetype} as conflagrate::NodeType>::Args
-/// ) {
-///     let rt = tokio::runtime::Runtime::new().unwrap();
-///     match rt.block_on(async move {
+/// ) -> Result<{graph_output_type}, conflagrate::GraphError> {
+///     Self::run_with(Self::runtime_config(), first_node_args)
+/// }
+///
+/// pub fn run_with(
+///     config: conflagrate::RuntimeConfig,
+///     first_node_args: <{start_nodetype} as conflagrate::NodeType>::Args
+/// ) -> Result<{graph_output_type}, conflagrate::GraphError> {
+///     let rt = config.build().map_err(|error| conflagrate::GraphError::RuntimeFailed { error })?;
+///     rt.block_on(async move {
 ///         Self::run_graph(first_node_args, None).await
-///     }) {
-///         _ => {}
-///     };
+///     })
+/// }
+///
+/// pub fn run_on(
+///     handle: &tokio::runtime::Handle,
+///     first_node_args: <{start_nodetype} as conflagrate::NodeType>::Args
+/// ) -> Result<{graph_output_type}, conflagrate::GraphError> {
+///     handle.block_on(async move {
+///         Self::run_graph(first_node_args, None).await
+///     })
 /// }
 /// ```
 struct RunMethod {
+    method_name: Ident,
+    run_with_method_name: Ident,
+    run_on_method_name: Ident,
+    run_graph_method_name: Ident,
     start_nodetype: TokenStream,
+    graph_output_type: TokenStream,
 }
-impl From<&DescriptiveGraph> for RunMethod {
-    fn from(graph: &DescriptiveGraph) -> Self {
+impl RunMethod {
+    fn new(entry_point: &EntryPoint, graph: &DescriptiveGraph) -> Self {
         Self {
-            start_nodetype: graph.get_start_node_nodetype()
+            method_name: entry_point_method_name("run", entry_point),
+            run_with_method_name: entry_point_method_name("run_with", entry_point),
+            run_on_method_name: entry_point_method_name("run_on", entry_point),
+            run_graph_method_name: entry_point_method_name("run_graph", entry_point),
+            start_nodetype: graph.get_nodetype(&entry_point.node),
+            graph_output_type: graph.get_output_type(),
         }
     }
 }
 impl ToTokens for RunMethod {
     fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method_name = &self.method_name;
+        let run_with_method_name = &self.run_with_method_name;
+        let run_on_method_name = &self.run_on_method_name;
+        let run_graph_method_name = &self.run_graph_method_name;
         let start_nodetype = &self.start_nodetype;
+        let graph_output_type = &self.graph_output_type;
         tokens.extend(quote! {
-            pub fn run(
+            pub fn #method_name(
                 first_node_args: <#start_nodetype as conflagrate::NodeType>::Args
-            ) {
-                let rt = tokio::runtime::Runtime::new().unwrap();
-                match rt.block_on(async move {
-                    Self::run_graph(first_node_args, None).await
-                }) {
-                    _ => {}
-                };
+            ) -> Result<#graph_output_type, conflagrate::GraphError> {
+                Self::#run_with_method_name(Self::runtime_config(), first_node_args)
+            }
+
+            pub fn #run_with_method_name(
+                config: conflagrate::RuntimeConfig,
+                first_node_args: <#start_nodetype as conflagrate::NodeType>::Args
+            ) -> Result<#graph_output_type, conflagrate::GraphError> {
+                let rt = config.build().map_err(|error| conflagrate::GraphError::RuntimeFailed { error })?;
+                rt.block_on(async move {
+                    Self::#run_graph_method_name(first_node_args, None).await
+                })
+            }
+
+            pub fn #run_on_method_name(
+                handle: &tokio::runtime::Handle,
+                first_node_args: <#start_nodetype as conflagrate::NodeType>::Args
+            ) -> Result<#graph_output_type, conflagrate::GraphError> {
+                handle.block_on(async move {
+                    Self::#run_graph_method_name(first_node_args, None).await
+                })
             }
         });
     }
 }
 
-/// Defines the `run_graph()` async method on an executable graph.
+/// Defines the `run_graph()` async method on an executable graph, or the `run_graph_{name}()`
+/// method of a named entry point.
 ///
-/// Generates a method definition that looks roughly like the following:
+/// Generates a simple wrapper method for the `start_graph()` method that looks like the following:
 /// ```no_compile
 /// pub async fn run_graph(
 ///     first_node_args: <{start_nodetype} as conflagrate::NodeType>::Args,
 ///     dependency_cache: Option<std::sync::Arc<conflagrate::DependencyCache>>
-/// ) -> Result<{graph_output_type}, tokio::sync::oneshot::error::RecvError> {
+/// ) -> Result<{graph_output_type}, conflagrate::GraphError> {
+///     Self::start_graph(first_node_args, dependency_cache).await
+/// }
+/// ```
+struct RunGraphMethod {
+    method_name: Ident,
+    start_graph_method_name: Ident,
+    start_nodetype: TokenStream,
+    graph_output_type: TokenStream,
+}
+impl RunGraphMethod {
+    fn new(entry_point: &EntryPoint, graph: &DescriptiveGraph) -> Self {
+        Self {
+            method_name: entry_point_method_name("run_graph", entry_point),
+            start_graph_method_name: entry_point_method_name("start_graph", entry_point),
+            start_nodetype: graph.get_nodetype(&entry_point.node),
+            graph_output_type: graph.get_output_type(),
+        }
+    }
+}
+impl ToTokens for RunGraphMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method_name = &self.method_name;
+        let start_graph_method_name = &self.start_graph_method_name;
+        let start_nodetype = &self.start_nodetype;
+        let graph_output_type = &self.graph_output_type;
+        tokens.extend(quote! {
+            pub async fn #method_name(
+                first_node_args: <#start_nodetype as conflagrate::NodeType>::Args,
+                dependency_cache: Option<std::sync::Arc<conflagrate::DependencyCache>>
+            ) -> Result<#graph_output_type, conflagrate::GraphError> {
+                Self::#start_graph_method_name(first_node_args, dependency_cache).await
+            }
+        })
+    }
+}
+
+/// Defines the `start_graph()` method on an executable graph, or the `start_graph_{name}()`
+/// method of a named entry point, which starts a run on the current tokio runtime.
+///
+/// Generates a method definition that looks roughly like the following:
+/// ```no_compile
+/// pub fn start_graph(
+///     first_node_args: <{start_nodetype} as conflagrate::NodeType>::Args,
+///     dependency_cache: Option<std::sync::Arc<conflagrate::DependencyCache>>
+/// ) -> conflagrate::GraphRunHandle<{graph_output_type}> {
 ///     let (receiver, raw_branch_tracker) = conflagrate::BranchTracker::<{graph_output_type}>::new();
 ///     let branch_tracker = std::sync::Arc::new(tokio::sync::Mutex::new(raw_branch_tracker));
 ///     let deps = match dependency_cache {
 ///         Some(deps) => deps,
 ///         None => std::sync::Arc::new(conflagrate::DependencyCache::new()),
 ///     };
-///     tokio::spawn(async move {
-///         Self::execute_{start_node_name}(branch_tracker, first_node_args, deps).await;
+///     let deps = std::sync::Arc::new(conflagrate::DependencyCache::for_run(deps));
+///     let handle = conflagrate::GraphRunHandle::new(receiver, &deps);
+///     deps.cancellation().spawn(async move {
+///         let fan_out = conflagrate::FanOut::default();
+///         Self::execute_{start_node_name}(branch_tracker, first_node_args, deps, fan_out).await;
 ///     });
-///     receiver.await
+///     handle
 /// }
 /// ```
-struct RunGraphMethod {
+struct StartGraphMethod {
+    method_name: Ident,
     start_nodetype: TokenStream,
     graph_output_type: TokenStream,
     start_node_name: TaskName,
 }
-impl From<&DescriptiveGraph> for RunGraphMethod {
-    fn from(graph: &DescriptiveGraph) -> Self {
+impl StartGraphMethod {
+    fn new(entry_point: &EntryPoint, graph: &DescriptiveGraph) -> Self {
         Self {
-            start_nodetype: graph.get_start_node_nodetype(),
+            method_name: entry_point_method_name("start_graph", entry_point),
+            start_nodetype: graph.get_nodetype(&entry_point.node),
             graph_output_type: graph.get_output_type(),
-            start_node_name: TaskName::from(graph.get_start_node_name())
+            start_node_name: TaskName::for_node(&entry_point.node, graph)
         }
     }
 }
-impl ToTokens for RunGraphMethod {
+impl ToTokens for StartGraphMethod {
     fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method_name = &self.method_name;
         let start_nodetype = &self.start_nodetype;
         let graph_output_type = &self.graph_output_type;
         let execute_start_node = &self.start_node_name;
         tokens.extend(quote! {
-            pub async fn run_graph(
+            pub fn #method_name(
                 first_node_args: <#start_nodetype as conflagrate::NodeType>::Args,
                 dependency_cache: Option<std::sync::Arc<conflagrate::DependencyCache>>
-            ) -> Result<#graph_output_type, tokio::sync::oneshot::error::RecvError> {
+            ) -> conflagrate::GraphRunHandle<#graph_output_type> {
                 let (receiver, raw_branch_tracker) = conflagrate::BranchTracker::<#graph_output_type>::new();
                 let branch_tracker = std::sync::Arc::new(tokio::sync::Mutex::new(raw_branch_tracker));
                 let deps = match dependency_cache {
                     Some(deps) => deps,
                     None => std::sync::Arc::new(conflagrate::DependencyCache::new()),
                 };
-                tokio::spawn(async move {
-                    Self::#execute_start_node(branch_tracker, first_node_args, deps).await;
+                let deps = std::sync::Arc::new(conflagrate::DependencyCache::for_run(deps));
+                let handle = conflagrate::GraphRunHandle::new(receiver, &deps);
+                deps.cancellation().spawn(async move {
+                    let fan_out = conflagrate::FanOut::default();
+                    Self::#execute_start_node(branch_tracker, first_node_args, deps, fan_out).await;
                 });
-                receiver.await
+                handle
+            }
+        })
+    }
+}
+
+/// The name of a run method for an entry point, like `run_graph` for the default entry point or
+/// `run_graph_{name}` for a named one.
+fn entry_point_method_name(prefix: &str, entry_point: &EntryPoint) -> Ident {
+    format_ident!("{}", entry_point.method_name(prefix))
+}
+
+/// Checks that every node that can end the graph ends it with the same type of output.
+///
+/// The graph's output type comes from the first such node declared in the graph.  For every
+/// other such node, a compile-time assertion is generated at the node's location in the graph
+/// definition, so a node returning the wrong type is reported where it is declared:
+/// ```no_compile
+/// const _: () = {
+///     #[diagnostic::on_unimplemented(/* ... */)]
+///     trait GraphOutput<Output> {}
+///     impl<Output> GraphOutput<Output> for Output {}
+///     fn check_output<Terminal: GraphOutput<Output>, Output>() {}
+///     fn check_outputs() {
+///         check_output::<{terminal_output_type}, {graph_output_type}>();
+///         // ...
+///     }
+/// };
+/// ```
+struct OutputTypeCheck {
+    first_node: String,
+    graph_output_type: TokenStream,
+    terminal_nodes: Vec<(Span, TokenStream)>,
+}
+impl From<&DescriptiveGraph> for OutputTypeCheck {
+    fn from(graph: &DescriptiveGraph) -> Self {
+        let mut terminal_nodes = graph.get_ordered_nodes().into_iter()
+            .filter_map(|node| {
+                let output_type = node.get_terminal_output_type()?;
+                Some((node.get_name().clone(), output_type))
+            });
+        let first_node = match terminal_nodes.next() {
+            Some((name, _)) => name,
+            None => String::new(),
+        };
+        let source_map = graph.get_source_map();
+        Self {
+            first_node,
+            graph_output_type: graph.get_output_type(),
+            terminal_nodes: terminal_nodes
+                .map(|(name, output_type)| (source_map.get_node_span(&name), output_type))
+                .collect(),
+        }
+    }
+}
+impl ToTokens for OutputTypeCheck {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        if self.terminal_nodes.is_empty() {
+            return;
+        }
+        let graph_output_type = &self.graph_output_type;
+        let message = format!(
+            "a node that ends the graph returns `{{Self}}`, but the graph's output is `{{Output}}` \
+            (from node `{}`)",
+            self.first_node
+        );
+        let label = format!(
+            "every node that can end the graph must output the same type as `{}`",
+            self.first_node
+        );
+        let checks = self.terminal_nodes.iter().map(|(span, output_type)| {
+            let output_type = respan(output_type, *span);
+            quote_spanned! {*span=>
+                check_output::<#output_type, #graph_output_type>();
             }
+        });
+        tokens.extend(quote! {
+            const _: () = {
+                #[diagnostic::on_unimplemented(message = #message, label = #label)]
+                trait GraphOutput<Output> {}
+                impl<Output> GraphOutput<Output> for Output {}
+                fn check_output<Terminal: GraphOutput<Output>, Output>() {}
+                #[allow(dead_code)]
+                fn check_outputs() {
+                    #(#checks)*
+                }
+            };
         })
     }
 }
@@ -1,33 +1,54 @@
-use std::collections::HashMap;
-use proc_macro2::Ident;
-use quote::format_ident;
-
-const NODE_BRANCH_MATCHER_VAL: &'static str = "matcher";
-const NODE_BRANCH_RESULT_MATCHER_VAL: &'static str = "resultmatcher";
-
-const RESULT_MATCHER_OK_VAL: &'static str = "ok";
-const RESULT_MATCHER_ERR_VAL: &'static str = "err";
+use proc_macro2::{Ident, TokenStream};
+use crate::graph::descriptivegraph::NODE_FANOUT_ATTR;
+use quote::{format_ident, quote};
+
+const NODE_BRANCH_PARALLEL_VAL: &str = "parallel";
+const NODE_BRANCH_MATCHER_VAL: &str = "matcher";
+const NODE_BRANCH_RESULT_MATCHER_VAL: &str = "resultmatcher";
+const NODE_BRANCH_ENUM_MATCHER_VAL: &str = "enummatcher";
+const NODE_BRANCH_OPTION_MATCHER_VAL: &str = "optionmatcher";
+const NODE_BRANCH_RACE_VAL: &str = "race";
+pub const NODE_BRANCH_FOREACH_VAL: &str = "foreach";
+const NODE_BRANCH_SEQUENTIAL_VAL: &str = "sequential";
+const NODE_BRANCH_PIPE_VAL: &str = "pipe";
+
+const RESULT_MATCHER_OK_VAL: &str = "ok";
+const RESULT_MATCHER_ERR_VAL: &str = "err";
+
+const OPTION_MATCHER_SOME_VAL: &str = "some";
+const OPTION_MATCHER_NONE_VAL: &str = "none";
+
+/// An edge leaving a parallel node.  An edge with a `when` guard is only followed if the guard,
+/// a path to a `fn(&T) -> bool` given the node's output, returns true.
+#[derive(Clone)]
+pub struct GuardedDestination {
+    pub node: String,
+    pub guard: Option<String>,
+}
 
 #[derive(Clone)]
 pub struct Node {
     name: String,
     nodetype: String,
-    destinations: Vec<String>,
+    destinations: Vec<GuardedDestination>,
 }
 impl Node {
-    fn new(name: &String, nodetype: &String) -> Node {
+    fn new(name: &str, nodetype: &str) -> Node {
         Node {
-            name: name.clone(),
-            destinations: Vec::<String>::new(),
-            nodetype: nodetype.clone(),
+            name: name.to_owned(),
+            destinations: Vec::<GuardedDestination>::new(),
+            nodetype: nodetype.to_owned(),
         }
     }
 
-    fn add_destination(&mut self, destination: &String) {
-        self.destinations.push(destination.clone());
+    fn add_destination(&mut self, guard: &str, destination: &str) {
+        self.destinations.push(GuardedDestination {
+            node: destination.to_owned(),
+            guard: if guard.is_empty() { None } else { Some(guard.to_owned()) },
+        });
     }
 
-    fn get_destinations(&self) -> Vec<String> {
+    fn get_destinations(&self) -> Vec<GuardedDestination> {
         self.destinations.clone()
     }
 
@@ -39,31 +60,149 @@ impl Node {
         &self.nodetype
     }
 
-    fn is_terminating_node(&self) -> bool {
-        self.destinations.is_empty()
+    /// A node whose edges all have guards ends the graph with its output if none of them pass.
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if self.destinations.iter().any(|destination| destination.guard.is_none()) {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{<#nodetype as conflagrate::NodeType>::ReturnType})
+    }
+}
+
+const MATCH_PATTERN_GLOB_VAL: &str = "glob";
+const MATCH_PATTERN_REGEX_VAL: &str = "regex";
+const MATCH_PATTERN_RANGE_VAL: &str = "range";
+
+/// How a `matcher` node compares its key to the `value` of an edge, set by the edge's `pattern`
+/// attribute.
+#[derive(Clone, PartialEq)]
+pub enum MatchPattern {
+    Exact,
+    Glob,
+    Regex,
+    Range,
+}
+
+/// The `value` of an edge leaving a `matcher` node and how to match it.
+#[derive(Clone, PartialEq)]
+pub struct MatchValue {
+    pub value: String,
+    pub pattern: MatchPattern,
+}
+impl MatchValue {
+    fn new(value: &String, pattern: &String) -> Result<Self, String> {
+        let pattern = match pattern.to_lowercase().as_str() {
+            "" => MatchPattern::Exact,
+            MATCH_PATTERN_GLOB_VAL => MatchPattern::Glob,
+            MATCH_PATTERN_REGEX_VAL => MatchPattern::Regex,
+            MATCH_PATTERN_RANGE_VAL => MatchPattern::Range,
+            _ => return Err(format!(
+                "unknown `pattern` `{}`; supported patterns are `{}`, `{}` and `{}`",
+                pattern, MATCH_PATTERN_GLOB_VAL, MATCH_PATTERN_REGEX_VAL, MATCH_PATTERN_RANGE_VAL
+            )),
+        };
+        if value.is_empty() && pattern != MatchPattern::Exact {
+            return Err(String::from("an edge with a `pattern` also needs a `value` to match"));
+        }
+        match pattern {
+            MatchPattern::Regex => if let Err(e) = regex::Regex::new(value) {
+                return Err(format!("invalid regex `{}`: {}", value, e));
+            },
+            MatchPattern::Range => if parse_range(value).is_none() {
+                return Err(format!(
+                    "invalid range `{}`; expected a range of literals like `200..300`", value
+                ));
+            },
+            MatchPattern::Exact | MatchPattern::Glob => {},
+        }
+        Ok(Self { value: value.clone(), pattern })
+    }
+
+    pub fn is_default(&self) -> bool {
+        self.value.is_empty()
     }
 }
 
+/// Parses the `value` of a `pattern=range` edge, which must be a Rust range of literals.
+pub fn parse_range(value: &str) -> Option<syn::ExprRange> {
+    let range = syn::parse_str::<syn::ExprRange>(value).ok()?;
+    let is_literal = |bound: &Option<Box<syn::Expr>>| match bound.as_deref() {
+        None | Some(syn::Expr::Lit(_)) => true,
+        Some(syn::Expr::Unary(unary)) => {
+            matches!(unary.op, syn::UnOp::Neg(_)) && matches!(*unary.expr, syn::Expr::Lit(_))
+        },
+        _ => false,
+    };
+    if is_literal(&range.from) && is_literal(&range.to) {
+        Some(range)
+    } else {
+        None
+    }
+}
+
+/// The destinations of a node that routes on edge values, grouped by value in the order the values
+/// first appear.  The empty value holds the default edges.
+pub type ValueDestinations<V = String> = Vec<(V, Vec<String>)>;
+
+/// Adds an edge to the destinations of a node routing on edge values.  More than one edge with the
+/// same value is a mistake unless the node fans out, in which case they run in parallel.
+fn add_value_destination<V: PartialEq>(
+    destinations: &mut ValueDestinations<V>,
+    value: V,
+    value_text: &String,
+    destination: &str,
+    fanout: bool,
+    node_description: &str
+) -> Result<(), String> {
+    match destinations.iter_mut().find(|(existing_value, _)| existing_value == &value) {
+        Some((_, existing)) if fanout => existing.push(destination.to_owned()),
+        Some((_, existing)) => {
+            let routes = match value_text.as_str() {
+                "" => String::from("already has a default edge"),
+                _ => format!("already routes `{}`", value_text),
+            };
+            return Err(format!(
+                "{} {} to `{}`; add `{}=true` to the node to follow both edges in parallel",
+                node_description, routes, existing.first().unwrap(), NODE_FANOUT_ATTR
+            ));
+        },
+        None => destinations.push((value, vec![destination.to_owned()])),
+    }
+    Ok(())
+}
+
 #[derive(Clone)]
 pub struct MatcherNode {
     name: String,
     nodetype: String,
-    destinations: HashMap<String, String>,
+    destinations: ValueDestinations<MatchValue>,
+    fanout: bool,
 }
 impl MatcherNode {
-    fn new(name: &String, nodetype: &String) -> MatcherNode {
+    fn new(name: &str, nodetype: &str, fanout: bool) -> MatcherNode {
         MatcherNode {
-            name: name.clone(),
-            nodetype: nodetype.clone(),
-            destinations: HashMap::<String, String>::new(),
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: ValueDestinations::<MatchValue>::new(),
+            fanout,
         }
     }
 
-    fn add_destination(&mut self, value: &String, destination: &String) {
-        self.destinations.insert(value.clone(), destination.clone());
+    fn add_destination(
+        &mut self,
+        value: &String,
+        pattern: &String,
+        destination: &str
+    ) -> Result<(), String> {
+        let match_value = MatchValue::new(value, pattern)?;
+        let node_description = format!("matcher node `{}`", self.name);
+        add_value_destination(
+            &mut self.destinations, match_value, value, destination, self.fanout, &node_description
+        )
     }
 
-    fn get_destinations(&self) -> HashMap<String, String> {
+    fn get_destinations(&self) -> ValueDestinations<MatchValue> {
         self.destinations.clone()
     }
 
@@ -75,8 +214,16 @@ impl MatcherNode {
         &self.nodetype
     }
 
-    fn is_terminating_node(&self) -> bool {
-        self.destinations.is_empty()
+    /// Without a default edge, unmatched values end the graph with the second element of the
+    /// node's output.
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if self.destinations.iter().any(|(value, _)| value.is_default()) {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{
+            <<#nodetype as conflagrate::NodeType>::ReturnType as conflagrate::MatcherOutput>::Output
+        })
     }
 }
 
@@ -117,21 +264,25 @@ pub struct ResultMatcherNode {
     destinations: ResultDestinations,
 }
 impl ResultMatcherNode {
-    fn new(name: &String, nodetype: &String) -> Self {
+    fn new(name: &str, nodetype: &str) -> Self {
         Self {
-            name: name.clone(),
-            nodetype: nodetype.clone(),
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
             destinations: ResultDestinations::new(),
         }
     }
 
-    fn add_destination(&mut self, value: &String, destination: &String) {
+    fn add_destination(&mut self, value: &String, destination: &str) -> Result<(), String> {
         let value_lower = value.to_lowercase();
         match value_lower.as_str() {
-            RESULT_MATCHER_OK_VAL => self.destinations.ok.push(destination.clone()),
-            RESULT_MATCHER_ERR_VAL => self.destinations.err.push(destination.clone()),
-            _ => panic!("Result matcher node only supports ok and err edge values")
+            RESULT_MATCHER_OK_VAL => self.destinations.ok.push(destination.to_owned()),
+            RESULT_MATCHER_ERR_VAL => self.destinations.err.push(destination.to_owned()),
+            _ => return Err(format!(
+                "result matcher node `{}` only supports `{}` and `{}` edge values, found `{}`",
+                self.name, RESULT_MATCHER_OK_VAL, RESULT_MATCHER_ERR_VAL, value
+            )),
         }
+        Ok(())
     }
 
     fn get_destinations(&self) -> ResultDestinations {
@@ -146,15 +297,295 @@ impl ResultMatcherNode {
         &self.nodetype
     }
 
-    fn is_terminating_node(&self) -> bool {
-        self.destinations.ok.is_empty() && self.destinations.err.is_empty()
+    /// A variant without any edges ends the graph with the variant's contents, or with the whole
+    /// `Result` if the node has no edges at all.
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        let nodetype = format_ident!("{}", self.nodetype);
+        let return_type = quote!{<#nodetype as conflagrate::NodeType>::ReturnType};
+        match (self.destinations.ok.is_empty(), self.destinations.err.is_empty()) {
+            (true, true) => Some(return_type),
+            (true, false) => Some(quote!{<#return_type as conflagrate::ResultOutput>::Ok}),
+            (false, true) => Some(quote!{<#return_type as conflagrate::ResultOutput>::Err}),
+            (false, false) => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct OptionDestinations {
+    some: Vec<String>,
+    none: Vec<String>,
+}
+impl OptionDestinations {
+    pub fn new() -> Self {
+        Self {
+            some: Vec::<String>::new(),
+            none: Vec::<String>::new(),
+        }
+    }
+
+    pub fn get_some_nodes(&self) -> Vec<String> {
+        self.some.clone()
+    }
+
+    pub fn get_none_nodes(&self) -> Vec<String> {
+        self.none.clone()
+    }
+
+    pub fn len(&self) -> usize {
+        self.some.len() + self.none.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Clone)]
+pub struct OptionMatcherNode {
+    name: String,
+    nodetype: String,
+    destinations: OptionDestinations,
+}
+impl OptionMatcherNode {
+    fn new(name: &str, nodetype: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: OptionDestinations::new(),
+        }
+    }
+
+    fn add_destination(&mut self, value: &String, destination: &str) -> Result<(), String> {
+        let value_lower = value.to_lowercase();
+        match value_lower.as_str() {
+            OPTION_MATCHER_SOME_VAL => self.destinations.some.push(destination.to_owned()),
+            OPTION_MATCHER_NONE_VAL => self.destinations.none.push(destination.to_owned()),
+            _ => return Err(format!(
+                "option matcher node `{}` only supports `{}` and `{}` edge values, found `{}`",
+                self.name, OPTION_MATCHER_SOME_VAL, OPTION_MATCHER_NONE_VAL, value
+            )),
+        }
+        Ok(())
+    }
+
+    fn get_destinations(&self) -> OptionDestinations {
+        self.destinations.clone()
+    }
+
+    fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    fn get_nodetype(&self) -> &String {
+        &self.nodetype
+    }
+
+    /// A variant without any edges ends the graph with the contents of `Some`, or with `()` for
+    /// `None`.  A node with no edges at all ends the graph with the whole `Option`.
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        let nodetype = format_ident!("{}", self.nodetype);
+        let return_type = quote!{<#nodetype as conflagrate::NodeType>::ReturnType};
+        match (self.destinations.some.is_empty(), self.destinations.none.is_empty()) {
+            (true, true) => Some(return_type),
+            (true, false) => Some(quote!{<#return_type as conflagrate::OptionOutput>::Some}),
+            (false, true) => Some(quote!{()}),
+            (false, false) => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct EnumMatcherNode {
+    name: String,
+    nodetype: String,
+    destinations: ValueDestinations,
+    fanout: bool,
+}
+impl EnumMatcherNode {
+    fn new(name: &str, nodetype: &str, fanout: bool) -> Self {
+        Self {
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: ValueDestinations::new(),
+            fanout,
+        }
+    }
+
+    fn add_destination(&mut self, value: &String, destination: &str) -> Result<(), String> {
+        if !value.is_empty() && syn::parse_str::<Ident>(value).is_err() {
+            return Err(format!(
+                "enum matcher node `{}` edge values must name an enum variant, found `{}`",
+                self.name, value
+            ));
+        }
+        let node_description = format!("enum matcher node `{}`", self.name);
+        add_value_destination(
+            &mut self.destinations, value.clone(), value, destination, self.fanout,
+            &node_description
+        )
+    }
+
+    fn get_destinations(&self) -> ValueDestinations {
+        self.destinations.clone()
+    }
+
+    fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    fn get_nodetype(&self) -> &String {
+        &self.nodetype
+    }
+
+    /// With any edges, every variant is either routed or taken by the default edge (which the
+    /// compiler checks), so only a node without edges ends the graph, with its whole output.
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if !self.destinations.is_empty() {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{<#nodetype as conflagrate::NodeType>::ReturnType})
+    }
+}
+
+/// A node whose trailing nodes race each other: they all start in parallel, and only the first to
+/// finish continues along its own edges.
+#[derive(Clone)]
+pub struct RaceNode {
+    name: String,
+    nodetype: String,
+    destinations: Vec<String>,
+}
+impl RaceNode {
+    fn new(name: &str, nodetype: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: Vec::<String>::new(),
+        }
+    }
+
+    fn add_destination(&mut self, destination: &str) {
+        self.destinations.push(destination.to_owned());
+    }
+
+    fn get_destinations(&self) -> Vec<String> {
+        self.destinations.clone()
+    }
+
+    fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    fn get_nodetype(&self) -> &String {
+        &self.nodetype
+    }
+
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if !self.destinations.is_empty() {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{<#nodetype as conflagrate::NodeType>::ReturnType})
+    }
+}
+
+/// A node returning a collection, whose trailing node runs once for each element.
+#[derive(Clone)]
+pub struct ForEachNode {
+    name: String,
+    nodetype: String,
+    destinations: Vec<String>,
+}
+impl ForEachNode {
+    fn new(name: &str, nodetype: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: Vec::<String>::new(),
+        }
+    }
+
+    fn add_destination(&mut self, destination: &str) {
+        self.destinations.push(destination.to_owned());
+    }
+
+    fn get_destinations(&self) -> Vec<String> {
+        self.destinations.clone()
+    }
+
+    fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    fn get_nodetype(&self) -> &String {
+        &self.nodetype
+    }
+
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if !self.destinations.is_empty() {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{<#nodetype as conflagrate::NodeType>::ReturnType})
+    }
+}
+
+/// A node whose trailing nodes run one after another, in the order their edges are declared.  In
+/// a pipe, each trailing node takes the output of the one before it instead of the node's own.
+#[derive(Clone)]
+pub struct SequentialNode {
+    name: String,
+    nodetype: String,
+    destinations: Vec<String>,
+    pipe: bool,
+}
+impl SequentialNode {
+    fn new(name: &str, nodetype: &str, pipe: bool) -> Self {
+        Self {
+            name: name.to_owned(),
+            nodetype: nodetype.to_owned(),
+            destinations: Vec::<String>::new(),
+            pipe,
+        }
+    }
+
+    fn add_destination(&mut self, destination: &str) {
+        self.destinations.push(destination.to_owned());
+    }
+
+    fn get_destinations(&self) -> Vec<String> {
+        self.destinations.clone()
+    }
+
+    fn get_name(&self) -> &String {
+        &self.name
+    }
+
+    fn get_nodetype(&self) -> &String {
+        &self.nodetype
+    }
+
+    fn get_terminal_output_type(&self) -> Option<TokenStream> {
+        if !self.destinations.is_empty() {
+            return None;
+        }
+        let nodetype = format_ident!("{}", self.nodetype);
+        Some(quote!{<#nodetype as conflagrate::NodeType>::ReturnType})
     }
 }
 
 pub enum Branches {
-    Parallel(Vec<String>),
-    Match(HashMap<String, String>),
+    Parallel(Vec<GuardedDestination>),
+    Match(ValueDestinations<MatchValue>),
     ResultMatch(ResultDestinations),
+    OptionMatch(OptionDestinations),
+    EnumMatch(ValueDestinations),
+    Race(Vec<String>),
+    ForEach(Vec<String>),
+    Sequential(Vec<String>),
+    Pipe(Vec<String>),
 }
 
 #[derive(Clone)]
@@ -162,13 +593,63 @@ pub enum Nodes {
     Node(Node),
     MatcherNode(MatcherNode),
     ResultMatcherNode(ResultMatcherNode),
+    OptionMatcherNode(OptionMatcherNode),
+    EnumMatcherNode(EnumMatcherNode),
+    RaceNode(RaceNode),
+    ForEachNode(ForEachNode),
+    SequentialNode(SequentialNode),
 }
 impl Nodes {
-    pub fn is_terminating_node(&self) -> bool {
+    /// The type of the value the node ends the graph with, if any of its outputs can end the graph.
+    pub fn get_terminal_output_type(&self) -> Option<TokenStream> {
         match self {
-            Self::Node(node) => node.is_terminating_node(),
-            Self::MatcherNode(node) => node.is_terminating_node(),
-            Self::ResultMatcherNode(node) => node.is_terminating_node(),
+            Self::Node(node) => node.get_terminal_output_type(),
+            Self::MatcherNode(node) => node.get_terminal_output_type(),
+            Self::ResultMatcherNode(node) => node.get_terminal_output_type(),
+            Self::OptionMatcherNode(node) => node.get_terminal_output_type(),
+            Self::EnumMatcherNode(node) => node.get_terminal_output_type(),
+            Self::RaceNode(node) => node.get_terminal_output_type(),
+            Self::ForEachNode(node) => node.get_terminal_output_type(),
+            Self::SequentialNode(node) => node.get_terminal_output_type(),
+        }
+    }
+
+    /// Whether the node's outgoing edges can be given a `pattern` attribute.
+    pub fn uses_edge_patterns(&self) -> bool {
+        matches!(self, Self::MatcherNode(_))
+    }
+
+    /// Whether the node's outgoing edges can be given a `when` guard.
+    pub fn uses_edge_guards(&self) -> bool {
+        matches!(self, Self::Node(_))
+    }
+
+    /// Whether the node's trailing nodes race each other.
+    pub fn is_race(&self) -> bool {
+        matches!(self, Self::RaceNode(_))
+    }
+
+    /// Whether the node runs its trailing nodes one after another, as `sequential` or `pipe`.
+    pub fn is_sequential(&self) -> bool {
+        matches!(self, Self::SequentialNode(_))
+    }
+
+    /// Whether the node's trailing node runs once for each element of its output.
+    pub fn is_foreach(&self) -> bool {
+        matches!(self, Self::ForEachNode(_))
+    }
+
+    /// Whether the node's outgoing edges are chosen by their `value` attribute.
+    pub fn uses_edge_values(&self) -> bool {
+        match self {
+            Self::Node(_) => false,
+            Self::MatcherNode(_) => true,
+            Self::ResultMatcherNode(_) => true,
+            Self::OptionMatcherNode(_) => true,
+            Self::EnumMatcherNode(_) => true,
+            Self::RaceNode(_) => false,
+            Self::ForEachNode(_) => false,
+            Self::SequentialNode(_) => false,
         }
     }
 
@@ -177,15 +658,32 @@ impl Nodes {
             Self::Node(_) => false,
             Self::MatcherNode(_) => true,
             Self::ResultMatcherNode(_) => false,
+            Self::OptionMatcherNode(_) => false,
+            Self::EnumMatcherNode(_) => false,
+            Self::RaceNode(_) => false,
+            Self::ForEachNode(_) => false,
+            Self::SequentialNode(_) => false,
         }
     }
 
-    pub fn add_destination(&mut self, value: &String, destination: &String) {
+    pub fn add_destination(
+        &mut self,
+        value: &String,
+        pattern: &String,
+        guard: &str,
+        destination: &str
+    ) -> Result<(), String> {
         match self {
-            Self::Node(node) => node.add_destination(destination),
-            Self::MatcherNode(node) => node.add_destination(value, destination),
-            Self::ResultMatcherNode(node) => node.add_destination(value, destination),
+            Self::Node(node) => node.add_destination(guard, destination),
+            Self::MatcherNode(node) => return node.add_destination(value, pattern, destination),
+            Self::ResultMatcherNode(node) => return node.add_destination(value, destination),
+            Self::OptionMatcherNode(node) => return node.add_destination(value, destination),
+            Self::EnumMatcherNode(node) => return node.add_destination(value, destination),
+            Self::RaceNode(node) => node.add_destination(destination),
+            Self::ForEachNode(node) => node.add_destination(destination),
+            Self::SequentialNode(node) => node.add_destination(destination),
         }
+        Ok(())
     }
 
     pub fn get_nodetype_ident(&self) -> Ident {
@@ -193,16 +691,51 @@ impl Nodes {
             Self::Node(node) => format_ident!("{}", node.get_nodetype()),
             Self::MatcherNode(node) => format_ident!("{}", node.get_nodetype()),
             Self::ResultMatcherNode(node) => format_ident!("{}", node.get_nodetype()),
+            Self::OptionMatcherNode(node) => format_ident!("{}", node.get_nodetype()),
+            Self::EnumMatcherNode(node) => format_ident!("{}", node.get_nodetype()),
+            Self::RaceNode(node) => format_ident!("{}", node.get_nodetype()),
+            Self::ForEachNode(node) => format_ident!("{}", node.get_nodetype()),
+            Self::SequentialNode(node) => format_ident!("{}", node.get_nodetype()),
         }
     }
 
-    pub fn new_node(name: &String, nodetype: &String, branch: &String) -> Self {
-        match branch.as_str() {
-            NODE_BRANCH_MATCHER_VAL => Self::MatcherNode(MatcherNode::new(&name, &nodetype)),
+    pub fn is_known_branch(branch: &str) -> bool {
+        matches!(
+            branch,
+            "" | NODE_BRANCH_PARALLEL_VAL | NODE_BRANCH_MATCHER_VAL | NODE_BRANCH_RESULT_MATCHER_VAL
+                | NODE_BRANCH_OPTION_MATCHER_VAL | NODE_BRANCH_ENUM_MATCHER_VAL
+                | NODE_BRANCH_RACE_VAL | NODE_BRANCH_FOREACH_VAL | NODE_BRANCH_SEQUENTIAL_VAL
+                | NODE_BRANCH_PIPE_VAL
+        )
+    }
+
+    /// Whether the node can be given the `fanout` attribute, which only applies to nodes that
+    /// route on edge values one edge at a time.
+    pub fn supports_fanout(branch: &str) -> bool {
+        matches!(branch, NODE_BRANCH_MATCHER_VAL | NODE_BRANCH_ENUM_MATCHER_VAL)
+    }
+
+    pub fn new_node(name: &str, nodetype: &str, branch: &str, fanout: bool) -> Self {
+        match branch {
+            NODE_BRANCH_MATCHER_VAL => Self::MatcherNode(MatcherNode::new(name, nodetype, fanout)),
             NODE_BRANCH_RESULT_MATCHER_VAL => Self::ResultMatcherNode(
-                ResultMatcherNode::new(&name, &nodetype)
+                ResultMatcherNode::new(name, nodetype)
+            ),
+            NODE_BRANCH_OPTION_MATCHER_VAL => Self::OptionMatcherNode(
+                OptionMatcherNode::new(name, nodetype)
+            ),
+            NODE_BRANCH_ENUM_MATCHER_VAL => Self::EnumMatcherNode(
+                EnumMatcherNode::new(name, nodetype, fanout)
+            ),
+            NODE_BRANCH_RACE_VAL => Self::RaceNode(RaceNode::new(name, nodetype)),
+            NODE_BRANCH_FOREACH_VAL => Self::ForEachNode(ForEachNode::new(name, nodetype)),
+            NODE_BRANCH_SEQUENTIAL_VAL => Self::SequentialNode(
+                SequentialNode::new(name, nodetype, false)
+            ),
+            NODE_BRANCH_PIPE_VAL => Self::SequentialNode(
+                SequentialNode::new(name, nodetype, true)
             ),
-            _ => Self::Node(Node::new(&name, &nodetype))
+            _ => Self::Node(Node::new(name, nodetype))
         }
     }
 
@@ -211,6 +744,11 @@ impl Nodes {
             Self::Node(node) => node.get_name(),
             Self::MatcherNode(node) => node.get_name(),
             Self::ResultMatcherNode(node) => node.get_name(),
+            Self::OptionMatcherNode(node) => node.get_name(),
+            Self::EnumMatcherNode(node) => node.get_name(),
+            Self::RaceNode(node) => node.get_name(),
+            Self::ForEachNode(node) => node.get_name(),
+            Self::SequentialNode(node) => node.get_name(),
         }
     }
 
@@ -218,7 +756,13 @@ impl Nodes {
         match self {
             Self::Node(node) => Branches::Parallel(node.get_destinations()),
             Self::MatcherNode(node) => Branches::Match(node.get_destinations()),
-            Self::ResultMatcherNode(node) => Branches::ResultMatch(node.get_destinations())
+            Self::ResultMatcherNode(node) => Branches::ResultMatch(node.get_destinations()),
+            Self::OptionMatcherNode(node) => Branches::OptionMatch(node.get_destinations()),
+            Self::EnumMatcherNode(node) => Branches::EnumMatch(node.get_destinations()),
+            Self::RaceNode(node) => Branches::Race(node.get_destinations()),
+            Self::ForEachNode(node) => Branches::ForEach(node.get_destinations()),
+            Self::SequentialNode(node) if node.pipe => Branches::Pipe(node.get_destinations()),
+            Self::SequentialNode(node) => Branches::Sequential(node.get_destinations()),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,361 @@
+use proc_macro2::{Delimiter, Group, Span, Spacing, TokenStream, TokenTree};
+
+const EDGE_OPERATORS: [&str; 2] = ["->", "--"];
+
+/// A single word, string, or symbol of the graph source along with where it came from.
+struct Lexeme {
+    text: String,
+    offset: usize,
+    span: Span,
+}
+
+/// The raw DOT source of a graph, plus the information needed to point diagnostics at it.
+///
+/// Graphs written inline in the `graph` macro are rebuilt into DOT text from the macro's tokens,
+/// keeping the span of every token so errors land on the offending node, edge or attribute in the
+/// user's code.  Graphs read from a file by the `graph_file` macro have no spans of their own, so
+/// errors point at the file path in the macro invocation and report the line and column within
+/// the file in the message.
+pub struct SourceMap {
+    text: String,
+    lexemes: Vec<Lexeme>,
+    file: Option<(String, Span)>,
+}
+impl SourceMap {
+    pub fn from_tokens(tokens: TokenStream) -> Self {
+        let mut source_map = Self {
+            text: String::new(),
+            lexemes: Vec::<Lexeme>::new(),
+            file: None,
+        };
+        source_map.push_tokens(tokens);
+        source_map
+    }
+
+    pub fn from_file(text: String, path: String, path_span: Span) -> Self {
+        let lexemes = lex_dot_source(&text, path_span);
+        Self {
+            text,
+            lexemes,
+            file: Some((path, path_span)),
+        }
+    }
+
+    fn push_tokens(&mut self, tokens: TokenStream) {
+        let mut joint_punct = false;
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Brace => ("{", "}"),
+                        Delimiter::Bracket => ("[", "]"),
+                        Delimiter::Parenthesis => ("(", ")"),
+                        Delimiter::None => ("", ""),
+                    };
+                    self.push_lexeme(open, group.span_open(), false);
+                    self.push_tokens(group.stream());
+                    self.push_lexeme(close, group.span_close(), false);
+                    joint_punct = false;
+                },
+                TokenTree::Punct(punct) => {
+                    let text = punct.as_char().to_string();
+                    self.push_lexeme(&text, punct.span(), joint_punct);
+                    joint_punct = punct.spacing() == Spacing::Joint;
+                },
+                TokenTree::Ident(ident) => {
+                    self.push_lexeme(&ident.to_string(), ident.span(), false);
+                    joint_punct = false;
+                },
+                TokenTree::Literal(literal) => {
+                    self.push_lexeme(&literal.to_string(), literal.span(), false);
+                    joint_punct = false;
+                },
+            }
+        }
+    }
+
+    /// Appends a token to the source text, gluing multi-character operators like `->` back
+    /// together.
+    fn push_lexeme(&mut self, text: &str, span: Span, continues_previous: bool) {
+        if text.is_empty() {
+            return;
+        }
+        if continues_previous {
+            if let Some(previous) = self.lexemes.last_mut() {
+                previous.text.push_str(text);
+                self.text.push_str(text);
+                return;
+            }
+        }
+        if !self.text.is_empty() {
+            self.text.push(' ');
+        }
+        self.lexemes.push(Lexeme {
+            text: String::from(text),
+            offset: self.text.len(),
+            span,
+        });
+        self.text.push_str(text);
+    }
+
+    pub fn get_text(&self) -> &String {
+        &self.text
+    }
+
+    /// The location of the first mention of a node, for generated code that should report errors
+    /// there.
+    pub fn get_node_span(&self, node: &str) -> Span {
+        match (self.find_node(node), &self.file) {
+            (_, Some((_, span))) => *span,
+            (Some(index), None) => self.lexemes[index].span,
+            (None, None) => Span::call_site(),
+        }
+    }
+
+    /// Creates an error for a problem that can't be attributed to any part of the graph.
+    pub fn error(&self, message: &str) -> syn::Error {
+        match &self.file {
+            Some((path, span)) => syn::Error::new(*span, format!("{}: {}", path, message)),
+            None => syn::Error::new(Span::call_site(), message),
+        }
+    }
+
+    /// Creates an error located at a (1-based) line and column of the source text, as reported by
+    /// the DOT parser.
+    pub fn error_at_position(&self, line: usize, column: usize, message: &str) -> syn::Error {
+        let line_offset: usize = self.text.split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum();
+        let offset = self.text[line_offset..].char_indices()
+            .nth(column.saturating_sub(1))
+            .map(|(offset, _)| line_offset + offset)
+            .unwrap_or(self.text.len());
+        let index = self.lexemes.iter()
+            .position(|lexeme| lexeme.offset + lexeme.text.len() > offset)
+            .or_else(|| self.lexemes.len().checked_sub(1));
+        match index {
+            Some(index) => self.error_at_lexeme(index, message),
+            None => self.error(message),
+        }
+    }
+
+    /// Creates an error located at the first mention of a node.
+    pub fn error_at_node(&self, node: &str, message: &str) -> syn::Error {
+        match self.find_node(node) {
+            Some(index) => self.error_at_lexeme(index, message),
+            None => self.error(message),
+        }
+    }
+
+    /// Creates an error located at the destination of an edge.
+    pub fn error_at_edge(&self, source: &str, destination: &str, message: &str) -> syn::Error {
+        match self.find_edge(source, destination) {
+            Some(index) => self.error_at_lexeme(index + 2, message),
+            None => self.error_at_node(destination, message),
+        }
+    }
+
+    /// Creates an error located at the value of an attribute given to a node.
+    pub fn error_at_node_attribute(&self, node: &str, key: &str, message: &str) -> syn::Error {
+        let attribute = self.lexemes.iter().enumerate()
+            .filter(|(_, lexeme)| unquote(&lexeme.text) == node)
+            .find_map(|(index, _)| self.find_attribute_in_list(index + 1, key));
+        match attribute {
+            Some(index) => self.error_at_lexeme(index, message),
+            None => self.error_at_node(node, message),
+        }
+    }
+
+    /// Creates an error located at the value of an attribute given to an edge.
+    pub fn error_at_edge_attribute(
+        &self,
+        source: &str,
+        destination: &str,
+        key: &str,
+        message: &str
+    ) -> syn::Error {
+        match self.find_edge_attribute(source, destination, key) {
+            Some(index) => self.error_at_lexeme(index, message),
+            None => self.error_at_edge(source, destination, message),
+        }
+    }
+
+    /// The location of the value of an attribute given to an edge, for generated code that should
+    /// report errors there.
+    pub fn get_edge_attribute_span(&self, source: &str, destination: &str, key: &str) -> Span {
+        match (self.find_edge_attribute(source, destination, key), &self.file) {
+            (_, Some((_, span))) => *span,
+            (Some(index), None) => self.lexemes[index].span,
+            (None, None) => self.get_node_span(destination),
+        }
+    }
+
+    /// Creates an error located at the value of an attribute given to the graph itself, in a
+    /// `graph[...]` statement or a `key=value` statement.
+    pub fn error_at_graph_attribute(&self, key: &str, message: &str) -> syn::Error {
+        match self.find_graph_attribute(key) {
+            Some(index) => self.error_at_lexeme(index, message),
+            None => self.error(message),
+        }
+    }
+
+    /// The location of the value of an attribute given to the graph itself, for generated code
+    /// that should report errors there.
+    pub fn get_graph_attribute_span(&self, key: &str) -> Span {
+        match (self.find_graph_attribute(key), &self.file) {
+            (_, Some((_, span))) => *span,
+            (Some(index), None) => self.lexemes[index].span,
+            (None, None) => Span::call_site(),
+        }
+    }
+
+    fn error_at_lexeme(&self, index: usize, message: &str) -> syn::Error {
+        let lexeme = &self.lexemes[index];
+        match &self.file {
+            Some((path, _)) => {
+                let (line, column) = line_and_column(&self.text, lexeme.offset);
+                syn::Error::new(lexeme.span, format!("{}:{}:{}: {}", path, line, column, message))
+            },
+            None => syn::Error::new(lexeme.span, message),
+        }
+    }
+
+    fn find_node(&self, node: &str) -> Option<usize> {
+        // Skip over attribute values that happen to share the node's name.
+        self.lexemes.iter().enumerate().position(|(index, lexeme)| {
+            unquote(&lexeme.text) == node
+                && (index == 0 || self.lexemes[index - 1].text != "=")
+        })
+    }
+
+    fn find_edge(&self, source: &str, destination: &str) -> Option<usize> {
+        self.lexemes.windows(3).position(|window| {
+            unquote(&window[0].text) == source
+                && EDGE_OPERATORS.contains(&window[1].text.as_str())
+                && unquote(&window[2].text) == destination
+        })
+    }
+
+    fn find_edge_attribute(&self, source: &str, destination: &str, key: &str) -> Option<usize> {
+        self.find_edge(source, destination).and_then(|index| {
+            let mut list_start = index + 2;
+            while list_start < self.lexemes.len()
+                && self.lexemes[list_start].text != "["
+                && self.lexemes[list_start].text != ";"
+                && self.lexemes[list_start].text != "}" {
+                list_start += 1;
+            }
+            self.find_attribute_in_list(list_start, key)
+        })
+    }
+
+    fn find_graph_attribute(&self, key: &str) -> Option<usize> {
+        self.lexemes.iter().enumerate()
+            .filter(|(_, lexeme)| lexeme.text == "graph")
+            .find_map(|(index, _)| self.find_attribute_in_list(index + 1, key))
+            .or_else(|| self.lexemes.windows(4).position(|window| {
+                (window[0].text == "{" || window[0].text == ";")
+                    && unquote(&window[1].text) == key
+                    && window[2].text == "="
+            }).map(|index| index + 3))
+    }
+
+    /// Looks for `key = value` in the attribute list opening at `list_start`, returning the index
+    /// of the value.
+    fn find_attribute_in_list(&self, list_start: usize, key: &str) -> Option<usize> {
+        if self.lexemes.get(list_start)?.text != "[" {
+            return None;
+        }
+        let mut index = list_start + 1;
+        while index + 2 < self.lexemes.len() && self.lexemes[index].text != "]" {
+            if unquote(&self.lexemes[index].text) == key && self.lexemes[index + 1].text == "=" {
+                return Some(index + 2);
+            }
+            index += 1;
+        }
+        None
+    }
+}
+
+/// Strips the quotes surrounding a DOT string, if it has any.
+pub fn unquote(text: &str) -> &str {
+    if text.len() >= 2 && text.starts_with('"') && text.ends_with('"') {
+        &text[1..text.len() - 1]
+    } else {
+        text
+    }
+}
+
+/// Moves every token of the stream to the given location, so that errors in it are reported there.
+pub fn respan(tokens: &TokenStream, span: Span) -> TokenStream {
+    tokens.clone().into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut new_group = Group::new(group.delimiter(), respan(&group.stream(), span));
+                new_group.set_span(span);
+                token = TokenTree::Group(new_group);
+            }
+            token.set_span(span);
+            token
+        })
+        .collect()
+}
+
+fn line_and_column(text: &str, offset: usize) -> (usize, usize) {
+    let preceding = &text[..offset.min(text.len())];
+    let line = preceding.matches('\n').count() + 1;
+    let column = preceding.chars().rev().take_while(|c| *c != '\n').count() + 1;
+    (line, column)
+}
+
+/// Splits DOT source read from a file into identifiers, strings and symbols.
+fn lex_dot_source(text: &str, span: Span) -> Vec<Lexeme> {
+    let mut lexemes = Vec::<Lexeme>::new();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        } else if c == '/' && next == Some('/') || c == '#' {
+            while i < chars.len() && chars[i].1 != '\n' { i += 1; }
+            continue;
+        } else if c == '/' && next == Some('*') {
+            i += 2;
+            while i + 1 < chars.len() && !(chars[i].1 == '*' && chars[i + 1].1 == '/') {
+                i += 1;
+            }
+            i += 2;
+            continue;
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i].1 != '"' {
+                if chars[i].1 == '\\' { i += 1; }
+                i += 1;
+            }
+            i += 1;
+        } else if is_id_char(c) {
+            while i < chars.len() && is_id_char(chars[i].1) {
+                i += 1;
+            }
+        } else if c == '-' && (next == Some('>') || next == Some('-')) {
+            i += 2;
+        } else {
+            i += 1;
+        }
+        let end = chars.get(i).map(|(offset, _)| *offset).unwrap_or(text.len());
+        lexemes.push(Lexeme {
+            text: String::from(&text[offset..end]),
+            offset,
+            span,
+        });
+    }
+    lexemes
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
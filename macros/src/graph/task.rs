@@ -1,8 +1,15 @@
-use std::collections::HashMap;
-use proc_macro2::{Ident, TokenStream};
-use quote::{format_ident, quote, ToTokens};
-use crate::graph::node::{Branches, Nodes, ResultDestinations};
-use crate::graph::task::MatchCase::{DefaultCase, RegularCase};
+use std::ops::Range;
+use std::time::Duration;
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use crate::graph::descriptivegraph::{
+    DescriptiveGraph, RetryPolicy, EDGE_MAP_ATTR, EDGE_VALUE_ATTR, EDGE_WHEN_ATTR,
+    GRAPH_ON_RETRY_ATTR
+};
+use crate::graph::node::{
+    parse_range, Branches, GuardedDestination, MatchPattern, MatchValue, Nodes, ValueDestinations
+};
+use crate::graph::sourcemap::respan;
 
 const DEFAULT_MATCH_VALUE: &str = "";
 
@@ -12,19 +19,29 @@ const DEFAULT_MATCH_VALUE: &str = "";
 /// task(s) in the graph.
 ///
 /// In most cases a task will execute one node, however for optimization it may execute several
-/// nodes if they occur in a single, linear progression with no branching (e.g. A->B->C).
+/// nodes if they occur in a single, linear progression with no branching (e.g. A->B->C).  If the
+/// progression leads back to one of its own nodes (e.g. A->B->C->B), the task runs the cycle as a
+/// loop that never spawns another task.
 pub struct Task {
     name: TaskName,
     invocation: Invocation,
-    spawn: Spawn,
+    spawn: Option<Spawn>,
     graph_output_type: TokenStream,
 }
 impl Task {
-    pub fn from_nodes(nodes: &[Nodes], graph_output_type: &TokenStream) -> Self {
+    pub fn from_nodes(
+        nodes: &[Nodes],
+        loop_start: Option<usize>,
+        graph: &DescriptiveGraph,
+        graph_output_type: &TokenStream
+    ) -> Self {
         Self {
-            name: TaskName::from(nodes.get(0).unwrap().get_name()),
-            invocation: Invocation(Vec::from(nodes)),
-            spawn: Spawn::from_nodes(nodes),
+            name: TaskName::for_node(nodes.first().unwrap().get_name(), graph),
+            invocation: Invocation::new(nodes, loop_start, graph),
+            spawn: match loop_start {
+                Some(_) => None,
+                None => Some(Spawn::from_nodes(nodes, graph)),
+            },
             graph_output_type: graph_output_type.clone()
         }
     }
@@ -42,18 +59,329 @@ impl ToTokens for Task {
             async fn #execute_node(
                 branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<#graph_output_type>>>,
                 node_args: <#first_node_type as conflagrate::NodeType>::Args,
-                deps: std::sync::Arc<conflagrate::DependencyCache>
+                deps: std::sync::Arc<conflagrate::DependencyCache>,
+                fan_out: conflagrate::FanOut
+            ) {
+                #invocation
+                #spawn
+            }
+        })
+    }
+}
+
+/// The entry point of one incoming edge of a join node, named "join_{nodename}_{slot}".
+///
+/// Predecessors spawn the slot function of their edge instead of the join node's task.  The slot
+/// buffers its input in the branch tracker and ends its branch, except for the input completing
+/// a full set, which runs the join node's task with all of them.  Inputs are only joined with
+/// others from the same instance of the join node's fan-out, the closest parallel node that all of
+/// its inputs descend from:
+/// ```no_compile
+/// async fn join_{node_name}_{slot}(
+///     branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<{graph_output_type}>>>,
+///     node_args: <<{node_type} as conflagrate::NodeType>::Args as conflagrate::JoinSlot<{slot}>>::Input,
+///     deps: std::sync::Arc<conflagrate::DependencyCache>,
+///     fan_out: conflagrate::FanOut
+/// ) {
+///     let instance = fan_out.instance("{fan_out_node_name}");
+///     let slots = branchtracker.lock().await
+///         .join("{node_name}", instance, {slot}, {num_slots}, Box::new(node_args));
+///     match slots {
+///         Some(slots) => {
+///             let node_args = <<{node_type} as conflagrate::NodeType>::Args as conflagrate::JoinArgs>::from_slots(slots);
+///             Self::execute_{node_name}(branchtracker, node_args, deps, fan_out).await;
+///         },
+///         None => branchtracker.lock().await.drop_branch(),
+///     }
+/// }
+/// ```
+///
+/// Without a fan-out, the instance is `None` and all of the join node's inputs are joined in the
+/// order they arrive.
+pub struct JoinSlotTask {
+    name: TaskName,
+    join_task: TaskName,
+    join_key: String,
+    fan_out_key: Option<String>,
+    nodetype: Ident,
+    slot: usize,
+    num_slots: usize,
+    graph_output_type: TokenStream,
+    span: Span,
+}
+impl JoinSlotTask {
+    pub fn for_node(
+        node: &Nodes,
+        graph: &DescriptiveGraph,
+        graph_output_type: &TokenStream
+    ) -> Vec<Self> {
+        let name = node.get_name();
+        if !graph.is_join_node(name) {
+            return Vec::new();
+        }
+        let num_slots = graph.get_join_sources(name).len();
+        (0..num_slots).map(|slot| Self {
+            name: TaskName::for_join_slot(name, slot, graph),
+            join_task: TaskName::for_node(name, graph),
+            join_key: graph.get_qualified_name(name),
+            fan_out_key: graph.get_join_fan_out(name)
+                .map(|fan_out| graph.get_qualified_name(&fan_out)),
+            nodetype: node.get_nodetype_ident(),
+            slot,
+            num_slots,
+            graph_output_type: graph_output_type.clone(),
+            span: graph.get_source_map().get_node_span(name),
+        }).collect()
+    }
+}
+impl ToTokens for JoinSlotTask {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let join_slot = &self.name;
+        let execute_node = &self.join_task;
+        let join_key = &self.join_key;
+        let instance = match &self.fan_out_key {
+            Some(fan_out_key) => quote! {fan_out.instance(#fan_out_key)},
+            None => quote! {None},
+        };
+        let node_type = &self.nodetype;
+        let slot = self.slot;
+        let num_slots = self.num_slots;
+        let graph_output_type = &self.graph_output_type;
+        // Report join inputs that don't fit the node's arguments at the join node.
+        let slot_input = respan(&quote! {
+            <<#node_type as conflagrate::NodeType>::Args as conflagrate::JoinSlot<#slot>>::Input
+        }, self.span);
+        tokens.extend(quote! {
+            async fn #join_slot(
+                branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<#graph_output_type>>>,
+                node_args: #slot_input,
+                deps: std::sync::Arc<conflagrate::DependencyCache>,
+                fan_out: conflagrate::FanOut
+            ) {
+                let instance = #instance;
+                let slots = branchtracker.lock().await
+                    .join(#join_key, instance, #slot, #num_slots, Box::new(node_args));
+                match slots {
+                    Some(slots) => {
+                        let node_args = <<#node_type as conflagrate::NodeType>::Args as conflagrate::JoinArgs>::from_slots(slots);
+                        Self::#execute_node(branchtracker, node_args, deps, fan_out).await;
+                    },
+                    None => branchtracker.lock().await.drop_branch(),
+                }
+            }
+        })
+    }
+}
+
+/// The task of a node racing the other trailing nodes of a `race` node, named "race_{nodename}".
+///
+/// Runs only the node itself, and then continues along the node's edges if it finished first.
+/// Otherwise another node has already won the race and the task ends without touching the branch
+/// tracker, since the race as a whole carries the `race` node's branch.  A node that times out
+/// also has to win the race before it follows its `value=timeout` edge:
+/// ```no_compile
+/// #[async_recursion::async_recursion]
+/// async fn race_{node_name}(
+///     branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<{graph_output_type}>>>,
+///     node_args: <{node_type} as conflagrate::NodeType>::Args,
+///     deps: std::sync::Arc<conflagrate::DependencyCache>,
+///     fan_out: conflagrate::FanOut,
+///     race: std::sync::Arc<conflagrate::Race>,
+///     index: usize
+/// ) {
+///     let output = <{node_type} as conflagrate::NodeType>::run(node_args, &deps).await;
+///     if !race.try_win(index) {
+///         return;
+///     }
+///     // spawn the node's trailing nodes
+/// }
+/// ```
+pub struct RaceTask {
+    name: TaskName,
+    invocation: Invocation,
+    spawn: Spawn,
+    graph_output_type: TokenStream,
+}
+impl RaceTask {
+    pub fn for_node(
+        node: &Nodes,
+        graph: &DescriptiveGraph,
+        graph_output_type: &TokenStream
+    ) -> Option<Self> {
+        if !graph.is_race_destination(node.get_name()) {
+            return None;
+        }
+        let nodes = [node.clone()];
+        Some(Self {
+            name: TaskName::for_race(node.get_name(), graph),
+            invocation: Invocation::for_racer(&nodes, graph),
+            spawn: Spawn::from_nodes(&nodes, graph),
+            graph_output_type: graph_output_type.clone(),
+        })
+    }
+}
+impl ToTokens for RaceTask {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let race_node = &self.name;
+        let graph_output_type = &self.graph_output_type;
+        let node_type = self.invocation.get_nodetype();
+        let invocation = &self.invocation;
+        let spawn = &self.spawn;
+        tokens.extend(quote! {
+            #[async_recursion::async_recursion]
+            async fn #race_node(
+                branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<#graph_output_type>>>,
+                node_args: <#node_type as conflagrate::NodeType>::Args,
+                deps: std::sync::Arc<conflagrate::DependencyCache>,
+                fan_out: conflagrate::FanOut,
+                race: std::sync::Arc<conflagrate::Race>,
+                index: usize
             ) {
                 #invocation
+                if !race.try_win(index) {
+                    return;
+                }
+                #spawn
+            }
+        })
+    }
+}
+
+/// The second half of a node run by a `sequential` or `pipe` node, named "continue_{nodename}".
+///
+/// The sequential node runs the node itself, so that the next one only starts once it's done, and
+/// then hands the node's output to this function to follow the node's own edges:
+/// ```no_compile
+/// #[async_recursion::async_recursion]
+/// async fn continue_{node_name}(
+///     branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<{graph_output_type}>>>,
+///     output: <{node_type} as conflagrate::NodeType>::ReturnType,
+///     deps: std::sync::Arc<conflagrate::DependencyCache>,
+///     fan_out: conflagrate::FanOut
+/// ) {
+///     let (value, output) = output;  // only for matcher nodes
+///     // spawn the node's trailing nodes
+/// }
+/// ```
+pub struct ContinueTask {
+    name: TaskName,
+    nodetype: Ident,
+    returns_matcher_value: bool,
+    spawn: Spawn,
+    graph_output_type: TokenStream,
+}
+impl ContinueTask {
+    pub fn for_node(
+        node: &Nodes,
+        graph: &DescriptiveGraph,
+        graph_output_type: &TokenStream
+    ) -> Option<Self> {
+        if !graph.is_sequential_destination(node.get_name()) {
+            return None;
+        }
+        Some(Self {
+            name: TaskName::for_continuation(node.get_name(), graph),
+            nodetype: node.get_nodetype_ident(),
+            returns_matcher_value: node.node_returns_matcher_value(),
+            spawn: Spawn::from_nodes(std::slice::from_ref(node), graph),
+            graph_output_type: graph_output_type.clone(),
+        })
+    }
+}
+impl ToTokens for ContinueTask {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let continue_node = &self.name;
+        let graph_output_type = &self.graph_output_type;
+        let node_type = &self.nodetype;
+        let split_output = if self.returns_matcher_value {
+            quote! {let (value, output) = output;}
+        } else {
+            TokenStream::new()
+        };
+        let spawn = &self.spawn;
+        tokens.extend(quote! {
+            #[async_recursion::async_recursion]
+            async fn #continue_node(
+                branchtracker: std::sync::Arc<tokio::sync::Mutex<conflagrate::BranchTracker<#graph_output_type>>>,
+                output: <#node_type as conflagrate::NodeType>::ReturnType,
+                deps: std::sync::Arc<conflagrate::DependencyCache>,
+                fan_out: conflagrate::FanOut
+            ) {
+                #split_output
                 #spawn
             }
         })
     }
 }
 
+/// The limit on how many invocations of a node may run at once, set by the node's `max_concurrency`
+/// attribute and shared by every run of the graph.
+///
+/// Each limited node gets a function returning its limit, named "limit_{nodename}", which every
+/// task invoking the node takes a permit from:
+/// ```no_compile
+/// fn limit_{node_name}() -> &'static conflagrate::ConcurrencyLimit {
+///     static LIMIT: conflagrate::ConcurrencyLimit =
+///         conflagrate::ConcurrencyLimit::new({max_concurrency});
+///     &LIMIT
+/// }
+/// ```
+pub struct ConcurrencyLimit {
+    name: Ident,
+    max_concurrency: usize,
+}
+impl ConcurrencyLimit {
+    pub fn for_node(node: &String, graph: &DescriptiveGraph) -> Option<Self> {
+        Some(Self {
+            name: format_ident!("limit_{}", graph.get_qualified_name(node)),
+            max_concurrency: graph.get_max_concurrency(node)?,
+        })
+    }
+}
+impl ToTokens for ConcurrencyLimit {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = &self.name;
+        let max_concurrency = self.max_concurrency;
+        tokens.extend(quote! {
+            fn #name() -> &'static conflagrate::ConcurrencyLimit {
+                static LIMIT: conflagrate::ConcurrencyLimit =
+                    conflagrate::ConcurrencyLimit::new(#max_concurrency);
+                &LIMIT
+            }
+        })
+    }
+}
+
 /// Name of the function executing the task, typically "execute_{nodename}" of the first node in
-/// the task.
+/// the task.  Nodes declared inside clusters have the cluster names prefixed to the node name.
+#[derive(Clone)]
 pub struct TaskName(Ident);
+impl TaskName {
+    pub fn for_node(name: &String, graph: &DescriptiveGraph) -> Self {
+        Self::from(&graph.get_qualified_name(name))
+    }
+
+    /// The function to spawn to follow an edge, which is the destination's task unless the
+    /// destination is a join node, in which case the edge's output first goes to its join slot.
+    pub fn for_edge(source: &String, destination: &String, graph: &DescriptiveGraph) -> Self {
+        match graph.get_join_slot(source, destination) {
+            Some(slot) => Self::for_join_slot(destination, slot, graph),
+            None => Self::for_node(destination, graph),
+        }
+    }
+
+    fn for_join_slot(name: &String, slot: usize, graph: &DescriptiveGraph) -> Self {
+        Self(format_ident!("join_{}_{}", graph.get_qualified_name(name), slot))
+    }
+
+    fn for_race(name: &String, graph: &DescriptiveGraph) -> Self {
+        Self(format_ident!("race_{}", graph.get_qualified_name(name)))
+    }
+
+    fn for_continuation(name: &String, graph: &DescriptiveGraph) -> Self {
+        Self(format_ident!("continue_{}", graph.get_qualified_name(name)))
+    }
+}
 impl From<&String> for TaskName {
     fn from(name: &String) -> Self {
         Self(format_ident!("execute_{}", name))
@@ -66,6 +394,170 @@ impl ToTokens for TaskName {
     }
 }
 
+/// The function given by an edge's `map` attribute, which converts the output of the edge's
+/// source into the input of its destination.
+#[derive(Clone)]
+struct EdgeMap {
+    path: syn::Path,
+    span: Span,
+}
+impl EdgeMap {
+    fn for_edge(source: &String, destination: &String, graph: &DescriptiveGraph) -> Option<Self> {
+        let map = graph.get_edge_map(source, destination)?;
+        Some(Self {
+            // The map was checked to be a path when the edge was added.
+            path: syn::parse_str::<syn::Path>(&map).unwrap(),
+            span: graph.get_source_map().get_edge_attribute_span(source, destination, EDGE_MAP_ATTR),
+        })
+    }
+
+    /// Calls the function on a value, reporting missing or mismatched functions at the edge's
+    /// `map` attribute.
+    fn apply(&self, value: &TokenStream) -> TokenStream {
+        let path = &self.path;
+        respan(&quote! {#path(#value)}, self.span)
+    }
+}
+
+/// The number of times an edge may be taken in one run of the graph, set by its `max_iterations`
+/// attribute, and the task to spawn in its place after that.
+#[derive(Clone)]
+struct IterationLimit {
+    /// The key the branch-tracker counts the edge under, which is also how the edge is named if
+    /// the graph fails.
+    edge: String,
+    max_iterations: usize,
+    /// The task of the source's `on_exhausted` edge, if it has one, or else the graph fails.
+    on_exhausted: Option<Box<NextTask>>,
+}
+impl IterationLimit {
+    fn for_edge(source: &String, destination: &String, graph: &DescriptiveGraph) -> Option<Self> {
+        let max_iterations = graph.get_iteration_limit(source, destination)?;
+        let on_exhausted = graph.get_exhausted_destination(source).map(|exhausted| {
+            Box::new(NextTask {
+                name: TaskName::for_edge(source, &exhausted, graph),
+                map: EdgeMap::for_edge(source, &exhausted, graph),
+                limit: None,
+            })
+        });
+        Some(Self {
+            edge: format!("{} -> {}", source, destination),
+            max_iterations,
+            on_exhausted,
+        })
+    }
+}
+
+/// The time a node may take to run, set by its `timeout` attribute, and the task to spawn with the
+/// node's input in place of its output if it runs out of time.
+#[derive(Clone)]
+struct NodeTimeout {
+    timeout: Duration,
+    /// The task of the node's `value=timeout` edge, if it has one, or else the graph fails.
+    on_timeout: Option<NextTask>,
+    /// Whether the node is run by a `race` node, so it has to win the race before following its
+    /// `value=timeout` edge.
+    racing: bool,
+}
+impl NodeTimeout {
+    fn for_node(node: &String, graph: &DescriptiveGraph) -> Option<Self> {
+        let timeout = graph.get_timeout(node)?;
+        let on_timeout = graph.get_timeout_destination(node).map(|destination| NextTask {
+            name: TaskName::for_edge(node, &destination, graph),
+            map: EdgeMap::for_edge(node, &destination, graph),
+            limit: None,
+        });
+        Some(Self { timeout, on_timeout, racing: false })
+    }
+}
+
+/// The function the graph calls before each retry of a node, set by the graph's `on_retry`
+/// attribute.
+#[derive(Clone)]
+struct RetryHook {
+    path: syn::Path,
+    span: Span,
+}
+impl RetryHook {
+    fn for_graph(graph: &DescriptiveGraph) -> Option<Self> {
+        let on_retry = graph.get_on_retry()?;
+        Some(Self {
+            // The hook was checked to be a path when the graph attributes were read.
+            path: syn::parse_str::<syn::Path>(on_retry).unwrap(),
+            span: graph.get_source_map().get_graph_attribute_span(GRAPH_ON_RETRY_ATTR),
+        })
+    }
+
+    /// Calls the function with the node's name, the number of the retry and the error that failed
+    /// the last attempt, reporting missing or mismatched functions at the graph's `on_retry`
+    /// attribute.
+    fn call(&self, node_name: &str, retry: &TokenStream, error: &TokenStream) -> TokenStream {
+        let path = &self.path;
+        respan(&quote! {#path(#node_name, #retry, #error)}, self.span)
+    }
+}
+
+/// How a node is run: how long it may take, how many times it's retried if it fails, and how many
+/// of its invocations may run at once.
+#[derive(Clone)]
+struct NodePolicy {
+    timeout: Option<NodeTimeout>,
+    retry: Option<RetryPolicy>,
+    on_retry: Option<RetryHook>,
+    /// The function giving the node's concurrency limit, if it has one.
+    concurrency_limit: Option<Ident>,
+}
+impl NodePolicy {
+    fn for_node(node: &String, graph: &DescriptiveGraph) -> Self {
+        Self {
+            timeout: NodeTimeout::for_node(node, graph),
+            retry: graph.get_retry_policy(node),
+            on_retry: RetryHook::for_graph(graph),
+            concurrency_limit: ConcurrencyLimit::for_node(node, graph).map(|limit| limit.name),
+        }
+    }
+}
+
+fn duration_to_tokens(duration: Duration) -> TokenStream {
+    let secs = duration.as_secs();
+    let nanos = duration.subsec_nanos();
+    quote! {std::time::Duration::new(#secs, #nanos)}
+}
+
+/// The task to spawn to follow an edge, along with the edge's `map` function and iteration limit
+/// if it has them.
+#[derive(Clone)]
+struct NextTask {
+    name: TaskName,
+    map: Option<EdgeMap>,
+    limit: Option<IterationLimit>,
+}
+impl NextTask {
+    fn for_edge(source: &String, destination: &String, graph: &DescriptiveGraph) -> Self {
+        Self {
+            name: TaskName::for_edge(source, destination, graph),
+            map: EdgeMap::for_edge(source, destination, graph),
+            limit: IterationLimit::for_edge(source, destination, graph),
+        }
+    }
+
+    fn for_race(source: &String, destination: &String, graph: &DescriptiveGraph) -> Self {
+        Self {
+            name: TaskName::for_race(destination, graph),
+            map: EdgeMap::for_edge(source, destination, graph),
+            limit: None,
+        }
+    }
+
+    /// The output to pass to the task, converted by the edge's `map` function if it has one.
+    fn convert_output(&self, output: &TokenStream) -> TokenStream {
+        match &self.map {
+            Some(map) => map.apply(output),
+            None => output.clone(),
+        }
+    }
+}
+
 /// How the nodes executed by this task are invoked.
 ///
 /// Examples:
@@ -88,29 +580,354 @@ impl ToTokens for TaskName {
 ///     &deps
 /// ).await;
 /// ```
-struct Invocation(Vec<Nodes>);
+///
+/// If the edge between two of the nodes has a `map` function, it converts the output in between:
+/// ```no_compile
+/// let output = <{node_type2} as conflagrate::NodeType>::run(
+///     {
+///         let output = <{node_type1} as conflagrate::NodeType>::run(node_args, &deps).await;
+///         path::to::map(output)
+///     },
+///     &deps
+/// ).await;
+/// ```
+///
+/// If the last node loops back to the node at `loop_start`, the nodes before it run once and the
+/// rest run over and over, each time with the output of the last node, yielding to the runtime
+/// each time around if any node of the loop has `yield=true`:
+/// ```no_compile
+/// let mut node_args = <{node_type1} as conflagrate::NodeType>::run(node_args, &deps).await;
+/// loop {
+///     let output = <{node_type3} as conflagrate::NodeType>::run(
+///         <{node_type2} as conflagrate::NodeType>::run(node_args, &deps).await,
+///         &deps
+///     ).await;
+///     node_args = output;
+///     tokio::task::yield_now().await;
+/// }
+/// ```
+///
+/// Each node invocation above is written out in full to end the graph with a `GraphError` if the
+/// node panics:
+/// ```no_compile
+/// match conflagrate::catch_panic(<{node_type} as conflagrate::NodeType>::run(node_args, &deps)).await
+///     .map_err(|payload| conflagrate::GraphError::from_panic("{node_name}", "{node_type}", payload))
+/// {
+///     Ok(output) => output,
+///     Err(error) => {
+///         branchtracker.lock().await.fail(error);
+///         return;
+///     },
+/// }
+/// ```
+///
+/// A node with a `timeout` also ends the graph if it runs out of time, unless it has a
+/// `value=timeout` edge, in which case the edge's task carries on the branch with the node's input:
+/// ```no_compile
+/// {
+///     let node_args = node_args;
+///     let timeout_args = node_args.clone();
+///     let run_node = conflagrate::catch_panic(<{node_type} as conflagrate::NodeType>::run(node_args, &deps));
+///     let result = match tokio::time::timeout({timeout}, run_node).await {
+///         Ok(result) => result.map_err(/* ... */),
+///         Err(_) => Err(conflagrate::GraphError::Timeout { node: "{node_name}", /* ... */ }),
+///     };
+///     match result {
+///         Ok(output) => output,
+///         Err(conflagrate::GraphError::Timeout { .. }) => {
+///             deps.cancellation().spawn(async move {
+///                 Self::execute_{timeout_node_name}(branchtracker, timeout_args, deps, fan_out)
+///                     .await;
+///             });
+///             return;
+///         },
+///         Err(error) => {
+///             branchtracker.lock().await.fail(error);
+///             return;
+///         },
+///     }
+/// }
+/// ```
+///
+/// A node with a `retry` attribute is run again with a clone of its input while it fails, by
+/// panicking, timing out or returning an `Err`, before its result is used as above:
+/// ```no_compile
+/// {
+///     let node_args = node_args;
+///     let mut retries = 0usize;
+///     loop {
+///         let result = conflagrate::catch_panic(
+///             <{node_type} as conflagrate::NodeType>::run(node_args.clone(), &deps)
+///         ).await.map_err(/* ... */);
+///         let returned_err = match &result {
+///             Ok(output) => (&&&conflagrate::RetryCheck(output)).failure(),
+///             Err(_) => None,
+///         }.map(|error| conflagrate::GraphError::NodeReturnedErr { /* ... */ });
+///         let error = match (&result, &returned_err) {
+///             (Err(error), _) | (Ok(_), Some(error)) => error,
+///             (Ok(_), None) => break result,
+///         };
+///         if retries == {retry} {
+///             break result;
+///         }
+///         retries += 1;
+///         deps.metrics().record_retry("{node_name}");
+///         {on_retry}("{node_name}", retries, error);  // with an `on_retry` hook
+///         tokio::time::sleep({retry_delay}).await;  // doubled each retry for exponential backoff
+///     }
+/// }
+/// ```
+struct Invocation {
+    nodes: Vec<Nodes>,
+    /// The `map` functions of the edges between consecutive nodes.
+    maps: Vec<Option<EdgeMap>>,
+    policies: Vec<NodePolicy>,
+    cycle: Option<Cycle>,
+}
 impl Invocation {
+    fn new(nodes: &[Nodes], loop_start: Option<usize>, graph: &DescriptiveGraph) -> Self {
+        Self {
+            nodes: Vec::from(nodes),
+            maps: nodes.windows(2)
+                .map(|pair| EdgeMap::for_edge(pair[0].get_name(), pair[1].get_name(), graph))
+                .collect(),
+            policies: nodes.iter()
+                .map(|node| NodePolicy::for_node(node.get_name(), graph))
+                .collect(),
+            cycle: loop_start.map(|start| Cycle::new(nodes, start, graph)),
+        }
+    }
+
+    /// Invocation of a node run by a `race` node, which must win the race before it follows its
+    /// `value=timeout` edge.
+    fn for_racer(nodes: &[Nodes], graph: &DescriptiveGraph) -> Self {
+        let mut invocation = Self::new(nodes, None, graph);
+        for policy in invocation.policies.iter_mut() {
+            if let Some(timeout) = policy.timeout.as_mut() {
+                timeout.racing = true;
+            }
+        }
+        invocation
+    }
+
     fn get_nodetype(&self) -> Ident {
-        self.0.get(0).unwrap().get_nodetype_ident()
+        self.nodes.first().unwrap().get_nodetype_ident()
+    }
+
+    fn node_to_invocation(
+        node: &Nodes,
+        policy: &NodePolicy,
+        node_args: &TokenStream
+    ) -> TokenStream {
+        let fail = Self::fail_on_error();
+        let (on_timeout, racing) = match &policy.timeout {
+            Some(NodeTimeout { on_timeout: Some(next_task), racing, .. }) => (next_task, *racing),
+            _ => {
+                let run_node = Self::node_to_checked_invocation(node, policy, node_args);
+                return quote! {
+                    match #run_node {
+                        Ok(output) => output,
+                        Err(error) => {
+                            #fail
+                        },
+                    }
+                };
+            },
+        };
+        let run_node = Self::node_to_checked_invocation(node, policy, &quote! {node_args});
+        let spawn = spawn_task(on_timeout, &on_timeout.convert_output(&quote! {timeout_args}));
+        let win_race = if racing {
+            quote! {
+                if !race.try_win(index) {
+                    return;
+                }
+            }
+        } else {
+            quote! {}
+        };
+        quote! {
+            {
+                let node_args = #node_args;
+                let timeout_args = node_args.clone();
+                match #run_node {
+                    Ok(output) => output,
+                    Err(conflagrate::GraphError::Timeout { .. }) => {
+                        #win_race
+                        #spawn
+                        return;
+                    },
+                    Err(error) => {
+                        #fail
+                    },
+                }
+            }
+        }
+    }
+
+    /// Runs the node, giving back a `Result` with a `GraphError` if the node panics or runs out of
+    /// time, after retrying it as many times as its `retry` attribute allows.
+    fn node_to_checked_invocation(
+        node: &Nodes,
+        policy: &NodePolicy,
+        node_args: &TokenStream
+    ) -> TokenStream {
+        let retry = match &policy.retry {
+            Some(retry) => retry,
+            None => return Self::node_to_attempt(node, policy, node_args),
+        };
+        let attempt = Self::node_to_attempt(node, policy, &quote! {node_args.clone()});
+        let node_name = node.get_name();
+        let node_type_name = node.get_nodetype_ident().to_string();
+        let max_retries = retry.retries;
+        let wait = match retry.delay {
+            Some(delay) => {
+                let delay = duration_to_tokens(delay);
+                let delay = match retry.exponential {
+                    true => quote! {#delay.saturating_mul(1u32 << (retries - 1).min(31))},
+                    false => delay,
+                };
+                quote! {
+                    tokio::time::sleep(#delay).await;
+                }
+            },
+            None => TokenStream::new(),
+        };
+        let on_retry = match &policy.on_retry {
+            Some(hook) => {
+                let call = hook.call(node_name, &quote! {retries}, &quote! {error});
+                quote! {#call;}
+            },
+            None => quote! {let _ = error;},
+        };
+        quote! {
+            {
+                let node_args = #node_args;
+                let mut retries = 0usize;
+                loop {
+                    let result = #attempt;
+                    let returned_err = match &result {
+                        Ok(output) => {
+                            use conflagrate::{RetryDebugErr as _, RetryErr as _, RetryOutput as _};
+                            (&&&conflagrate::RetryCheck(output)).failure()
+                        },
+                        Err(_) => None,
+                    }.map(|error| conflagrate::GraphError::NodeReturnedErr {
+                        node: #node_name,
+                        nodetype: #node_type_name,
+                        error,
+                    });
+                    let error = match (&result, &returned_err) {
+                        (Err(error), _) | (Ok(_), Some(error)) => error,
+                        (Ok(_), None) => break result,
+                    };
+                    if retries == #max_retries {
+                        break result;
+                    }
+                    retries += 1;
+                    deps.metrics().record_retry(#node_name);
+                    #on_retry
+                    #wait
+                }
+            }
+        }
+    }
+
+    /// Runs the node once, giving back a `Result` with a `GraphError` if the node panics or runs
+    /// out of time.  A node with a concurrency limit first waits for a free slot, once its input is
+    /// ready.
+    fn node_to_attempt(node: &Nodes, policy: &NodePolicy, node_args: &TokenStream) -> TokenStream {
+        match &policy.concurrency_limit {
+            Some(limit) => {
+                let attempt =
+                    Self::node_to_timed_attempt(node, &policy.timeout, &quote! {node_args});
+                quote! {
+                    {
+                        let node_args = #node_args;
+                        let _permit = Self::#limit().acquire().await;
+                        #attempt
+                    }
+                }
+            },
+            None => Self::node_to_timed_attempt(node, &policy.timeout, node_args),
+        }
     }
 
-    fn node_to_invocation(node: &Nodes, node_args: &TokenStream) -> TokenStream {
+    fn node_to_timed_attempt(
+        node: &Nodes,
+        timeout: &Option<NodeTimeout>,
+        node_args: &TokenStream
+    ) -> TokenStream {
+        let node_name = node.get_name();
         let node_type = node.get_nodetype_ident();
+        let node_type_name = node_type.to_string();
+        let run_node = quote! {
+            conflagrate::catch_panic(<#node_type as conflagrate::NodeType>::run(#node_args, &deps))
+        };
+        let from_panic = quote! {
+            |payload| conflagrate::GraphError::from_panic(#node_name, #node_type_name, payload)
+        };
+        let timeout = match timeout {
+            Some(timeout) => duration_to_tokens(timeout.timeout),
+            None => return quote! {
+                #run_node.await.map_err(#from_panic)
+            },
+        };
+        quote! {
+            match tokio::time::timeout(#timeout, #run_node).await {
+                Ok(result) => result.map_err(#from_panic),
+                Err(_) => Err(conflagrate::GraphError::Timeout {
+                    node: #node_name,
+                    nodetype: #node_type_name,
+                    timeout: #timeout,
+                }),
+            }
+        }
+    }
+
+    /// Ends the graph with the `error` of a node, and returns from the task.
+    fn fail_on_error() -> TokenStream {
         quote! {
-            <#node_type as conflagrate::NodeType>::run(#node_args, &deps).await
+            branchtracker.lock().await.fail(error);
+            return;
         }
     }
 
-    fn get_nested_node_invocation(&self) -> TokenStream {
-        let mut node_args = quote!{node_args};
-        for node in self.0.iter() {
-            node_args = Self::node_to_invocation(node, &node_args);
+    /// Invokes a run of consecutive nodes on `node_args`, converting the output between them with
+    /// the `map` functions of their edges.
+    fn get_nested_node_invocation(
+        &self,
+        nodes: Range<usize>,
+        node_args: TokenStream
+    ) -> TokenStream {
+        let mut node_args = node_args;
+        for index in nodes.clone() {
+            if index > nodes.start {
+                node_args = Self::convert(&self.maps[index - 1], node_args);
+            }
+            node_args = Self::node_to_invocation(
+                &self.nodes[index], &self.policies[index], &node_args
+            );
         }
         node_args
     }
 
+    fn convert(map: &Option<EdgeMap>, output: TokenStream) -> TokenStream {
+        match map {
+            Some(map) => {
+                let converted = map.apply(&quote!{output});
+                quote! {
+                    {
+                        let output = #output;
+                        #converted
+                    }
+                }
+            },
+            None => output,
+        }
+    }
+
     fn get_return_capture_args(&self) -> TokenStream {
-        if self.0.last().unwrap().node_returns_matcher_value() {
+        if self.nodes.last().unwrap().node_returns_matcher_value() {
             quote!{(value, output)}
         } else {
             quote!{output}
@@ -119,240 +936,512 @@ impl Invocation {
 }
 impl ToTokens for Invocation {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let nested_invocation = self.get_nested_node_invocation();
-        let return_capture = self.get_return_capture_args();
+        let cycle = match &self.cycle {
+            Some(cycle) => cycle,
+            None => {
+                let nested_invocation = self.get_nested_node_invocation(
+                    0..self.nodes.len(), quote!{node_args}
+                );
+                let return_capture = self.get_return_capture_args();
+                tokens.extend(quote! {
+                    let #return_capture = #nested_invocation;
+                });
+                return;
+            },
+        };
+        let start = cycle.start;
+        let loop_args = match start {
+            0 => quote!{node_args},
+            _ => Self::convert(
+                &self.maps[start - 1],
+                self.get_nested_node_invocation(0..start, quote!{node_args})
+            ),
+        };
+        let loop_invocation = self.get_nested_node_invocation(
+            start..self.nodes.len(), quote!{node_args}
+        );
+        let next_args = Self::convert(&cycle.map, quote!{output});
+        let yield_point = if cycle.yields {
+            quote! {
+                tokio::task::yield_now().await;
+            }
+        } else {
+            quote!{}
+        };
         tokens.extend(quote! {
-            let #return_capture = #nested_invocation;
+            let mut node_args = #loop_args;
+            loop {
+                let output = #loop_invocation;
+                node_args = #next_args;
+                #yield_point
+            }
         })
     }
 }
 
+/// The edge from the last node of a task back to one of its earlier nodes.
+struct Cycle {
+    /// The index of the node the edge leads back to.
+    start: usize,
+    map: Option<EdgeMap>,
+    yields: bool,
+}
+impl Cycle {
+    fn new(nodes: &[Nodes], start: usize, graph: &DescriptiveGraph) -> Self {
+        let last = nodes.last().unwrap().get_name();
+        Self {
+            start,
+            map: EdgeMap::for_edge(last, nodes[start].get_name(), graph),
+            yields: nodes[start..].iter().any(|node| graph.yields(node.get_name())),
+        }
+    }
+}
+
+#[allow(clippy::enum_variant_names, clippy::large_enum_variant)]
 enum Spawn {
     SpawnNone,
     SpawnParallel(SpawnParallel),
+    SpawnGuarded(SpawnGuarded),
     SpawnMatch(SpawnMatch),
     SpawnResultMatch(SpawnResultMatch),
+    SpawnOptionMatch(SpawnOptionMatch),
+    SpawnEnumMatch(SpawnEnumMatch),
+    SpawnRace(SpawnRace),
+    SpawnForEach(SpawnForEach),
+    SpawnSequential(SpawnSequential),
 }
 impl Spawn {
-    fn from_nodes(nodes: &[Nodes]) -> Self {
-        if nodes.len() == 0 {
+    fn from_nodes(nodes: &[Nodes], graph: &DescriptiveGraph) -> Self {
+        if nodes.is_empty() {
             return Self::SpawnNone
         }
         let final_node = nodes.last().unwrap();
+        let source = final_node.get_name();
         match final_node.get_destinations() {
             Branches::Parallel(branches) => {
                 if branches.is_empty() {
                     return Self::SpawnNone;
                 }
-                Spawn::SpawnParallel(SpawnParallel(convert_vec_string_to_vec_task_name(&branches)))
+                if branches.iter().any(|branch| branch.guard.is_some()) {
+                    return Spawn::SpawnGuarded(SpawnGuarded::new(source, &branches, graph));
+                }
+                let destinations = branches.into_iter().map(|branch| branch.node).collect();
+                Spawn::SpawnParallel(SpawnParallel {
+                    destinations: convert_vec_string_to_vec_next_task(source, &destinations, graph),
+                    fan_out: FanOut::for_node(source, graph),
+                })
             },
             Branches::Match(branch_map) => {
                 if branch_map.is_empty() {
                     return Self::SpawnNone;
                 }
 
-                let mut task_map = HashMap::<String, TaskName>::with_capacity(branch_map.len());
-                for mapping in branch_map {
-                    task_map.insert(mapping.0, TaskName::from(&mapping.1));
-                }
+                let task_map: Vec<(MatchValue, Vec<NextTask>)> = branch_map.iter()
+                    .map(|(value, destinations)| (
+                        value.clone(),
+                        convert_vec_string_to_vec_next_task(source, destinations, graph)
+                    ))
+                    .collect();
                 Spawn::SpawnMatch(SpawnMatch::from(task_map))
             },
             Branches::ResultMatch(destinations) => {
                 if destinations.is_empty() {
                     return Self::SpawnNone;
                 }
-                Spawn::SpawnResultMatch(SpawnResultMatch(destinations))
+                Spawn::SpawnResultMatch(SpawnResultMatch {
+                    ok: convert_vec_string_to_vec_next_task(
+                        source, &destinations.get_ok_nodes(), graph
+                    ),
+                    err: convert_vec_string_to_vec_next_task(
+                        source, &destinations.get_err_nodes(), graph
+                    ),
+                })
             },
-        }
-    }
-}
-impl ToTokens for Spawn {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        match self {
-            Spawn::SpawnNone => {
-                tokens.extend(branchtracker_remove_branch());
+            Branches::OptionMatch(destinations) => {
+                if destinations.is_empty() {
+                    return Self::SpawnNone;
+                }
+                Spawn::SpawnOptionMatch(SpawnOptionMatch {
+                    some: convert_vec_string_to_vec_next_task(
+                        source, &destinations.get_some_nodes(), graph
+                    ),
+                    none: convert_vec_string_to_vec_next_task(
+                        source, &destinations.get_none_nodes(), graph
+                    ),
+                })
+            },
+            Branches::EnumMatch(destinations) => {
+                if destinations.is_empty() {
+                    return Self::SpawnNone;
+                }
+                Spawn::SpawnEnumMatch(SpawnEnumMatch::new(final_node, &destinations, graph))
+            },
+            Branches::Race(destinations) => {
+                if destinations.is_empty() {
+                    return Self::SpawnNone;
+                }
+                Spawn::SpawnRace(SpawnRace(
+                    destinations.iter()
+                        .map(|node| NextTask::for_race(source, node, graph))
+                        .collect()
+                ))
+            },
+            Branches::Sequential(destinations) | Branches::Pipe(destinations) => {
+                if destinations.is_empty() {
+                    return Self::SpawnNone;
+                }
+                let pipe = matches!(final_node.get_destinations(), Branches::Pipe(_));
+                Spawn::SpawnSequential(SpawnSequential::new(source, &destinations, pipe, graph))
+            },
+            Branches::ForEach(destinations) => {
+                // Foreach nodes are checked to have exactly one edge.
+                match destinations.first() {
+                    Some(destination) => Spawn::SpawnForEach(
+                        SpawnForEach::new(source, destination, graph)
+                    ),
+                    None => Self::SpawnNone,
+                }
+            },
+        }
+    }
+}
+impl ToTokens for Spawn {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Spawn::SpawnNone => {
+                tokens.extend(branchtracker_remove_branch());
             },
             Spawn::SpawnParallel(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnGuarded(spawn) => spawn.to_tokens(tokens),
             Spawn::SpawnMatch(spawn) => spawn.to_tokens(tokens),
             Spawn::SpawnResultMatch(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnOptionMatch(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnEnumMatch(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnRace(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnForEach(spawn) => spawn.to_tokens(tokens),
+            Spawn::SpawnSequential(spawn) => spawn.to_tokens(tokens),
         }
     }
 }
 
 /// Loop over each branch and create a spawn block, adding branches to the branch-tracker as needed.
 ///
-/// SpawnParallel will create a codeblock that looks like the following:
+/// All the new branches are added to the branch-tracker before any are spawned, so that a branch
+/// finishing early can't be mistaken for the last one.  SpawnParallel will create a codeblock that
+/// looks like the following:
 /// ```no_compile
+/// branchtracker.lock().await.add_branch();
+/// // ...
 /// {
 ///     let bclone = branchtracker.clone();
 ///     let oclone = output.clone();
 ///     let dclone = std::sync::Arc::clone(&deps);
-///     tokio::spawn(async move {
-///         Self::execute_next_node1(bclone, oclone, dclone).await;
+///     let fclone = fan_out.clone();
+///     deps.cancellation().spawn(async move {
+///         Self::execute_next_node1(bclone, oclone, dclone, fclone).await;
 ///     });
 /// }
-/// branchtracker.lock().await.add_branch();
 /// {
 ///     let bclone = branchtracker.clone();
 ///     let oclone = output.clone();
 ///     let dclone = std::sync::Arc::clone(&deps);
-///     tokio::spawn(async move {
-///         Self::execute_next_node2(bclone, oclone, dclone).await;
+///     let fclone = fan_out.clone();
+///     deps.cancellation().spawn(async move {
+///         Self::execute_next_node2(bclone, oclone, dclone, fclone).await;
 ///     });
 /// }
 /// // ...
 /// ```
-struct SpawnParallel(Vec<TaskName>);
+///
+/// If a join node brings the branches back together, they're first split off into a new instance
+/// of the node's fan-out, so the join node can tell them apart from the branches of another run of
+/// the node (see FanOut).
+struct SpawnParallel {
+    destinations: Vec<NextTask>,
+    fan_out: Option<FanOut>,
+}
 impl ToTokens for SpawnParallel {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let mut needs_add_branch = false;
-        let last_index = self.0.len() - 1;
-        for (index, next_task) in self.0.iter().enumerate() {
-            if needs_add_branch {
-                tokens.extend(branchtracker_add_branch())
-            }
-            tokens.extend(create_spawn_block(&next_task, index == last_index));
-            needs_add_branch = true;
+        let last_index = self.destinations.len() - 1;
+        for _ in 0..last_index {
+            tokens.extend(branchtracker_add_branch())
+        }
+        self.fan_out.to_tokens(tokens);
+        for (index, next_task) in self.destinations.iter().enumerate() {
+            tokens.extend(create_spawn_block(next_task, index == last_index));
         }
     }
 }
 
-/// Create a match block on the first return value of the executed node.
+/// Splits off a new instance of the fan-out at a parallel node whose branches are brought back
+/// together by a join node, before the branches are spawned:
+/// ```no_compile
+/// let fan_out = fan_out.split("{node_name}");
+/// ```
+struct FanOut(String);
+impl FanOut {
+    fn for_node(source: &String, graph: &DescriptiveGraph) -> Option<Self> {
+        if !graph.is_join_fan_out(source) {
+            return None;
+        }
+        Some(Self(graph.get_qualified_name(source)))
+    }
+}
+impl ToTokens for FanOut {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let fan_out_key = &self.0;
+        tokens.extend(quote! {
+            let fan_out = fan_out.split(#fan_out_key);
+        })
+    }
+}
+
+/// Spawn the destinations of a parallel node whose guards pass, where some of the node's edges have
+/// a `when` guard.
 ///
-/// SpawnMatch will create a code block that looks like the following:
+/// The guards are all evaluated on the output first, so that the branches taken can be added to
+/// the branch-tracker before any are spawned.  SpawnGuarded will create a code block that looks
+/// like the following:
 /// ```no_compile
-/// match value.as_str() {
-///     "value1" => {
-///         let bclone = branchtracker.clone();
-///         let oclone = output.clone();
-///         let dclone = std::sync::Arc::clone(&deps);
-///         tokio::spawn(async move {
-///             Self::execute_next_node1(bclone, oclone, dclone).await;
-///         });
-///     },
-///     "value2" => {
-///         let bclone = branchtracker.clone();
-///         let oclone = output.clone();
-///         let dclone = std::sync::Arc::clone(&deps);
-///         tokio::spawn(async move {
-///             Self::execute_next_node2(bclone, oclone, dclone).await;
-///         });
-///     },
-///     _ => {
-///         let bclone = branchtracker.clone();
-///         let oclone = output.clone();
-///         let dclone = std::sync::Arc::clone(&deps);
-///         tokio::spawn(async move {
-///             Self::execute_next_node_default(bclone, oclone, dclone).await;
+/// let guards = [path::to::guard1(&output), true, path::to::guard3(&output)];
+/// let taken = guards.iter().filter(|taken| **taken).count();
+/// if taken == 0 {
+///     branchtracker.lock().await.remove_branch(output);
+/// } else {
+///     {
+///         let branchtracker = branchtracker.lock().await;
+///         for _ in 1..taken {
+///             branchtracker.add_branch();
+///         }
+///     }
+///     if guards[0] {
+///         let branchtracker = branchtracker.clone();
+///         let output = output.clone();
+///         let deps = std::sync::Arc::clone(&deps);
+///         let fan_out = fan_out.clone();
+///         deps.cancellation().spawn(async move {
+///             Self::execute_next_node1(branchtracker, output, deps, fan_out).await;
 ///         });
-///     },
+///     }
+///     // ...
 /// }
 /// ```
 ///
+/// As in SpawnParallel, the branches are first split off into a new instance of the node's fan-out
+/// if a join node brings them back together.
+///
+/// If any edge has no guard, at least one branch is always taken and the `taken == 0` case is left
+/// out.  Otherwise the node must output the same type as the graph, which it ends with if no guard
+/// passes.  A node with a single, guarded edge spawns it with its output without cloning:
+/// ```no_compile
+/// if path::to::guard(&output) {
+///     // spawn block for the edge
+/// } else {
+///     branchtracker.lock().await.remove_branch(output);
+/// }
+/// ```
+struct SpawnGuarded {
+    destinations: Vec<(Option<TokenStream>, NextTask)>,
+    fan_out: Option<FanOut>,
+}
+impl SpawnGuarded {
+    fn new(
+        source: &String,
+        destinations: &[GuardedDestination],
+        graph: &DescriptiveGraph
+    ) -> Self {
+        let source_map = graph.get_source_map();
+        let destinations = destinations.iter().map(|destination| {
+            let guard = destination.guard.as_ref().map(|guard| {
+                // The guard was checked to be a path when the edge was added.
+                let path = syn::parse_str::<syn::Path>(guard).unwrap();
+                // Report missing or mismatched guard functions at the edge's `when` attribute.
+                let span = source_map.get_edge_attribute_span(
+                    source, &destination.node, EDGE_WHEN_ATTR
+                );
+                respan(&quote! {#path(&output)}, span)
+            });
+            (guard, NextTask::for_edge(source, &destination.node, graph))
+        }).collect();
+        Self {
+            destinations,
+            fan_out: FanOut::for_node(source, graph),
+        }
+    }
+}
+impl ToTokens for SpawnGuarded {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let remove_branch_line = branchtracker_remove_branch();
+        let fan_out = &self.fan_out;
+        if let [(Some(guard), next_task)] = self.destinations.as_slice() {
+            let spawn_block = create_spawn_block(next_task, true);
+            tokens.extend(quote! {
+                if #guard #spawn_block else {
+                    #remove_branch_line
+                }
+            });
+            return;
+        }
+        let conditions = self.destinations.iter().map(|(guard, _)| match guard {
+            Some(guard) => guard.clone(),
+            None => quote! {true},
+        });
+        let spawn_blocks = self.destinations.iter().enumerate().map(|(index, (guard, next_task))| {
+            let spawn_block = create_spawn_block(next_task, false);
+            match guard {
+                Some(_) => quote! {
+                    if guards[#index] #spawn_block
+                },
+                None => spawn_block,
+            }
+        });
+        let spawn = quote! {
+            {
+                let branchtracker = branchtracker.lock().await;
+                for _ in 1..taken {
+                    branchtracker.add_branch();
+                }
+            }
+            #fan_out
+            #(#spawn_blocks)*
+        };
+        let all_guarded = self.destinations.iter().all(|(guard, _)| guard.is_some());
+        tokens.extend(quote! {
+            let guards = [#(#conditions),*];
+            let taken = guards.iter().filter(|taken| **taken).count();
+        });
+        if all_guarded {
+            tokens.extend(quote! {
+                if taken == 0 {
+                    #remove_branch_line
+                } else {
+                    #spawn
+                }
+            });
+        } else {
+            tokens.extend(spawn);
+        }
+    }
+}
+
+/// Create an if/else chain matching the first return value of the executed node against the
+/// values of the node's edges, in the order the edges were declared.
+///
+/// The key is compared through the `conflagrate::MatchKey` trait, or against a range or pattern if
+/// the edge has a `pattern` attribute.  SpawnMatch will create a code block that looks like the
+/// following:
+/// ```no_compile
+/// if conflagrate::MatchKey::matches_value(&value, "value1") {
+///     let branchtracker = branchtracker;
+///     let output = output;
+///     let deps = deps;
+///     let fan_out = fan_out;
+///     deps.cancellation().spawn(async move {
+///         Self::execute_next_node1(branchtracker, output, deps, fan_out).await;
+///     });
+/// } else if conflagrate::glob_match("cmd_*", &conflagrate::MatchKey::as_text(&value)) {
+///     // ...
+/// } else if (200..300).contains(&value) {
+///     // ...
+/// } else if {
+///     static REGEX: std::sync::OnceLock<conflagrate::Regex> = std::sync::OnceLock::new();
+///     REGEX.get_or_init(|| conflagrate::Regex::new("^[a-z]+$").unwrap())
+///         .is_match(&conflagrate::MatchKey::as_text(&value))
+/// } {
+///     // ...
+/// } else {
+///     // ...
+///     deps.cancellation().spawn(async move {
+///         Self::execute_next_node_default(branchtracker, output, deps, fan_out).await;
+///     });
+/// }
+/// ```
+///
+/// If the node has `fanout=true`, a value with several edges spawns all of their nodes in parallel,
+/// as in SpawnParallel.
+///
 /// In the case that no default branch is provided, the matcher node must output the same type as
-/// the graph, and the default case will invoke the `remove_branch()` method of the branch tracker:
+/// the graph, and the final `else` will invoke the `remove_branch()` method of the branch tracker:
 /// ```no_compile
-/// match value.as_str() {
+/// if conflagrate::MatchKey::matches_value(&value, "value1") {
 ///     // ...
-///     _ => {
-///         branchtracker.lock().await.remove_branch(output);
-///     }
+/// } else {
+///     branchtracker.lock().await.remove_branch(output);
 /// }
 /// ```
-struct SpawnMatch(Vec<MatchCase>);
-impl From<HashMap<String, TaskName>> for SpawnMatch {
-    fn from(map: HashMap<String, TaskName>) -> Self {
-        let mut match_cases = Vec::<MatchCase>::new();
-        let mut default: MatchCase = MatchCase::NoDefault;
-        for case in map {
-            let case = MatchCase::from(case);
-            if let MatchCase::DefaultCase(_) = case {
-                default = case;
+struct SpawnMatch {
+    cases: Vec<(MatchCondition, Vec<NextTask>)>,
+    default: Option<Vec<NextTask>>,
+}
+impl From<Vec<(MatchValue, Vec<NextTask>)>> for SpawnMatch {
+    fn from(map: Vec<(MatchValue, Vec<NextTask>)>) -> Self {
+        let mut cases = Vec::<(MatchCondition, Vec<NextTask>)>::new();
+        let mut default = None;
+        for (match_value, task_names) in map {
+            if match_value.is_default() {
+                default = Some(task_names);
             } else {
-                match_cases.push(case);
+                cases.push((MatchCondition(match_value), task_names));
             }
         }
-        match_cases.push(default);
-        Self(match_cases)
+        Self { cases, default }
     }
 }
 impl ToTokens for SpawnMatch {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let cases = &self.0;
+        let conditions = self.cases.iter().map(|(condition, _)| condition);
+        let blocks = self.cases.iter().map(|(_, task_names)| destinations_to_block(task_names));
+        let default_block = match &self.default {
+            Some(task_names) => destinations_to_block(task_names),
+            None => destinations_to_block(&Vec::new()),
+        };
         tokens.extend(quote! {
-            match value.as_str() {
-                #(#cases)*
-            }
+            #(if #conditions #blocks else)* #default_block
         })
     }
 }
 
-enum MatchCase {
-    RegularCase(String, TaskName),
-    DefaultCase(TaskName),
-    NoDefault,
-}
-impl From<(String, TaskName)> for MatchCase {
-    fn from((value, task_name): (String, TaskName)) -> Self {
-        match value.as_str() {
-            DEFAULT_MATCH_VALUE => DefaultCase(task_name),
-            _ => RegularCase(value, task_name),
-        }
-    }
-}
-impl ToTokens for MatchCase {
+/// The condition under which the key returned by a matcher node matches an edge's value.
+struct MatchCondition(MatchValue);
+impl ToTokens for MatchCondition {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        match self {
-            Self::RegularCase(value, task_name) => {
-                let spawn_block = create_spawn_block(task_name, true);
-                tokens.extend(quote! {
-                    #value => #spawn_block,
-                });
+        let value = &self.0.value;
+        tokens.extend(match self.0.pattern {
+            MatchPattern::Exact => quote! {
+                conflagrate::MatchKey::matches_value(&value, #value)
             },
-            Self::DefaultCase(task_name) => {
-                let spawn_block = create_spawn_block(task_name, true);
-                tokens.extend(quote! {
-                    _ => #spawn_block,
-                });
+            MatchPattern::Glob => quote! {
+                conflagrate::glob_match(#value, &conflagrate::MatchKey::as_text(&value))
             },
-            Self::NoDefault => {
-                let remove_branch_line = branchtracker_remove_branch();
-                tokens.extend(quote! {
-                    _ => {
-                        #remove_branch_line
-                    },
-                });
+            MatchPattern::Regex => quote! {
+                {
+                    static REGEX: std::sync::OnceLock<conflagrate::Regex> = std::sync::OnceLock::new();
+                    REGEX.get_or_init(|| conflagrate::Regex::new(#value).unwrap())
+                        .is_match(&conflagrate::MatchKey::as_text(&value))
+                }
             },
-        }
+            MatchPattern::Range => {
+                // The range was checked when the edge was added.
+                let range = parse_range(value).unwrap();
+                quote! {
+                    (#range).contains(&value)
+                }
+            },
+        })
     }
 }
 
-struct SpawnResultMatch(ResultDestinations);
+struct SpawnResultMatch {
+    ok: Vec<NextTask>,
+    err: Vec<NextTask>,
+}
 impl SpawnResultMatch {
-    fn destinations_to_blocks(destinations: &Vec<String>) -> TokenStream {
-        if destinations.is_empty() {
-            let remove_branch_line = branchtracker_remove_branch();
-            quote! {
-                {
-                    #remove_branch_line
-                }
-            }
-        } else {
-            let spawn_parallel = SpawnParallel(convert_vec_string_to_vec_task_name(destinations));
-            quote! {
-               {
-                   #spawn_parallel
-               }
-            }
-        }
-    }
-
     fn get_err_block(&self) -> TokenStream {
-        Self::destinations_to_blocks(&self.0.get_err_nodes())
+        destinations_to_block(&self.err)
     }
 
     fn get_ok_block(&self) -> TokenStream {
-        Self::destinations_to_blocks(&self.0.get_ok_nodes())
+        destinations_to_block(&self.ok)
     }
 }
 impl ToTokens for SpawnResultMatch {
@@ -368,6 +1457,514 @@ impl ToTokens for SpawnResultMatch {
     }
 }
 
+/// Create a match block on the `Option` returned by the executed node, spawning the `some` edges
+/// with the contents of `Some` and the `none` edges with `()`.
+///
+/// SpawnOptionMatch will create a code block that looks like the following:
+/// ```no_compile
+/// match output {
+///     Some(output) => {
+///         // spawn blocks for the `some` edges
+///     },
+///     None => {
+///         let output = ();
+///         // spawn blocks for the `none` edges
+///     },
+/// }
+/// ```
+///
+/// As with SpawnResultMatch, a side without edges ends the branch with its value.
+struct SpawnOptionMatch {
+    some: Vec<NextTask>,
+    none: Vec<NextTask>,
+}
+impl ToTokens for SpawnOptionMatch {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let some_block = destinations_to_block(&self.some);
+        let none_block = destinations_to_block(&self.none);
+        tokens.extend(quote!{
+            match output {
+                Some(output) => #some_block,
+                None => {
+                    let output = ();
+                    #none_block
+                },
+            }
+        })
+    }
+}
+
+/// Create a match block on the variant of the enum returned by the executed node, passing the
+/// variant's contents to the next node.
+///
+/// SpawnEnumMatch will create a code block that looks like the following:
+/// ```no_compile
+/// type __EnumOutput = <{node_type} as conflagrate::NodeType>::ReturnType;
+/// conflagrate::check_enum_variant::<__EnumOutput, _, _>(__EnumOutput::Variant1);
+/// // ...
+/// match output {
+///     __EnumOutput::Variant1 { 0: output, .. } => {
+///         let branchtracker = branchtracker;
+///         let output = output;
+///         let deps = deps;
+///         let fan_out = fan_out;
+///         deps.cancellation().spawn(async move {
+///             Self::execute_next_node1(branchtracker, output, deps, fan_out).await;
+///         });
+///     },
+///     // ...
+///     output => {
+///         // ...
+///         deps.cancellation().spawn(async move {
+///             Self::execute_next_node_default(branchtracker, output, deps, fan_out).await;
+///         });
+///     },
+/// }
+/// ```
+///
+/// The default case, which receives the whole enum, is only generated for a node with a default
+/// edge.  Without one, the compiler checks that an edge is given for every variant.  Each routed
+/// variant is checked to hold exactly one value, since that value is what's passed on.
+struct SpawnEnumMatch {
+    nodetype: Ident,
+    span: Span,
+    cases: Vec<(Ident, Vec<NextTask>)>,
+    default: Option<Vec<NextTask>>,
+}
+impl SpawnEnumMatch {
+    fn new(node: &Nodes, destinations: &ValueDestinations, graph: &DescriptiveGraph) -> Self {
+        let source = node.get_name();
+        let source_map = graph.get_source_map();
+        let mut cases = Vec::<(Ident, Vec<NextTask>)>::with_capacity(destinations.len());
+        let mut default = None;
+        for (value, value_destinations) in destinations {
+            let task_names = convert_vec_string_to_vec_next_task(source, value_destinations, graph);
+            match value.as_str() {
+                DEFAULT_MATCH_VALUE => default = Some(task_names),
+                _ => {
+                    let span = source_map.get_edge_attribute_span(
+                        source, value_destinations.first().unwrap(), EDGE_VALUE_ATTR
+                    );
+                    cases.push((Ident::new(value, span), task_names));
+                },
+            }
+        }
+        Self {
+            nodetype: node.get_nodetype_ident(),
+            span: source_map.get_node_span(source),
+            cases,
+            default,
+        }
+    }
+}
+impl ToTokens for SpawnEnumMatch {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let node_type = &self.nodetype;
+        // Report variants that don't hold exactly one value at the edge naming them.
+        let checks = self.cases.iter().map(|(variant, _)| {
+            quote_spanned! {variant.span()=>
+                conflagrate::check_enum_variant::<__EnumOutput, _, _>(__EnumOutput::#variant);
+            }
+        });
+        let cases = self.cases.iter().map(|(variant, task_names)| {
+            let spawn_block = destinations_to_block(task_names);
+            // Report unknown variants at the edge naming them.  Variants with more than one field
+            // are already reported by the check above.
+            let pattern = quote_spanned! {variant.span()=> __EnumOutput::#variant { 0: output, .. }};
+            quote! {
+                #pattern => #spawn_block,
+            }
+        });
+        let default = self.default.as_ref().map(|task_names| {
+            let spawn_block = destinations_to_block(task_names);
+            quote! {
+                output => #spawn_block,
+            }
+        });
+        // Report variants missing an edge at the matcher node.
+        let match_output = quote_spanned! {self.span=> match output};
+        tokens.extend(quote! {
+            type __EnumOutput = <#node_type as conflagrate::NodeType>::ReturnType;
+            #(#checks)*
+            #match_output {
+                #(#cases)*
+                #default
+            }
+        })
+    }
+}
+
+/// Start the trailing nodes of a `race` node in parallel, registering each task with a shared
+/// `conflagrate::Race` so the first to finish can abort the others.
+///
+/// No branches are added to the branch-tracker: the racing tasks share the `race` node's branch,
+/// which the winner carries on.  SpawnRace will create a code block that looks like the following:
+/// ```no_compile
+/// let race = std::sync::Arc::new(conflagrate::Race::new());
+/// {
+///     let branchtracker = branchtracker.clone();
+///     let output = output.clone();
+///     let deps = std::sync::Arc::clone(&deps);
+///     let fan_out = fan_out.clone();
+///     let racer = std::sync::Arc::clone(&race);
+///     let task = deps.cancellation().spawn(async move {
+///         Self::race_next_node1(branchtracker, output, deps, fan_out, racer, 0).await;
+///     });
+///     race.register(0, task.abort_handle());
+/// }
+/// // ...
+/// ```
+struct SpawnRace(Vec<NextTask>);
+impl ToTokens for SpawnRace {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let last_index = self.0.len() - 1;
+        let racers = self.0.iter().enumerate().map(|(index, next_task)| {
+            let task_args = create_task_args(next_task, index == last_index);
+            let next_task_name = &next_task.name;
+            quote! {
+                {
+                    #task_args
+                    let racer = std::sync::Arc::clone(&race);
+                    let task = deps.cancellation().spawn(async move {
+                        Self::#next_task_name(branchtracker, output, deps, fan_out, racer, #index)
+                            .await;
+                    });
+                    race.register(#index, task.abort_handle());
+                }
+            }
+        });
+        tokens.extend(quote! {
+            let race = std::sync::Arc::new(conflagrate::Race::new());
+            #(#racers)*
+        })
+    }
+}
+
+/// Run the trailing node of a `foreach` node once for each element of the node's output, at most
+/// `max_concurrent_elements` at a time if the node has that attribute.
+///
+/// Without a gather node, each element starts its own branch through the trailing node's task.
+/// SpawnForEach will create a code block that looks like the following:
+/// ```no_compile
+/// let items: Vec<_> = output.into_iter().collect();
+/// if items.is_empty() {
+///     branchtracker.lock().await.drop_branch();
+/// } else {
+///     {
+///         let branchtracker = branchtracker.lock().await;
+///         for _ in 1..items.len() {
+///             branchtracker.add_branch();
+///         }
+///     }
+///     let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new({max_concurrent_elements}));
+///     for output in items {
+///         let branchtracker = branchtracker.clone();
+///         let deps = std::sync::Arc::clone(&deps);
+///         let fan_out = fan_out.clone();
+///         let permit = std::sync::Arc::clone(&semaphore).acquire_owned().await.unwrap();
+///         deps.cancellation().spawn(async move {
+///             Self::execute_next_node(branchtracker, output, deps, fan_out).await;
+///             drop(permit);
+///         });
+///     }
+/// }
+/// ```
+///
+/// If the trailing node's edge leads to a gather node, the trailing node is run directly instead,
+/// and its outputs are collected in the order of the elements and passed on to the gather node:
+/// ```no_compile
+/// let items: Vec<_> = output.into_iter().collect();
+/// let semaphore = std::sync::Arc::new(tokio::sync::Semaphore::new({max_concurrent_elements}));
+/// let mut tasks = Vec::with_capacity(items.len());
+/// for node_args in items {
+///     let deps = std::sync::Arc::clone(&deps);
+///     let permit = std::sync::Arc::clone(&semaphore).acquire_owned().await.unwrap();
+///     tasks.push(deps.cancellation().spawn(async move {
+///         let output = conflagrate::catch_panic(
+///             <{node_type} as conflagrate::NodeType>::run(node_args, &deps)
+///         ).await;
+///         drop(permit);
+///         output
+///     }));
+/// }
+/// let mut output = Vec::with_capacity(tasks.len());
+/// for task in tasks {
+///     let task_output = match task.await {
+///         Ok(task_output) => task_output,
+///         Err(error) => Err(Box::new(error) as Box<dyn std::any::Any + Send>),
+///     };
+///     match task_output {
+///         Ok(task_output) => output.push(task_output),
+///         Err(payload) => {
+///             // end the graph with the node's `GraphError`, as a panic in a task does
+///         },
+///     }
+/// }
+/// // spawn block for the gather node
+/// ```
+///
+/// Without `max_concurrent_elements`, the semaphore and permits are left out.
+struct SpawnForEach {
+    each: ForEachTask,
+    max_concurrent_elements: Option<usize>,
+}
+#[allow(clippy::large_enum_variant)]
+enum ForEachTask {
+    Spawn(NextTask),
+    Gather(Nodes, NodePolicy, Option<EdgeMap>, NextTask),
+}
+impl SpawnForEach {
+    fn new(source: &String, destination: &String, graph: &DescriptiveGraph) -> Self {
+        let each = match graph.get_gather_node(destination) {
+            Some(gather_node) => ForEachTask::Gather(
+                graph.get_nodes().get(destination).unwrap().clone(),
+                NodePolicy::for_node(destination, graph),
+                EdgeMap::for_edge(source, destination, graph),
+                NextTask::for_edge(destination, &gather_node, graph)
+            ),
+            None => ForEachTask::Spawn(NextTask::for_edge(source, destination, graph)),
+        };
+        Self {
+            each,
+            max_concurrent_elements: graph.get_max_concurrent_elements(source),
+        }
+    }
+}
+impl ToTokens for SpawnForEach {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let (semaphore, acquire_permit, release_permit) = match self.max_concurrent_elements {
+            Some(max_concurrent_elements) => (
+                quote! {
+                    let semaphore =
+                        std::sync::Arc::new(tokio::sync::Semaphore::new(#max_concurrent_elements));
+                },
+                quote! {
+                    let permit = std::sync::Arc::clone(&semaphore).acquire_owned().await.unwrap();
+                },
+                quote! {
+                    drop(permit);
+                },
+            ),
+            None => (TokenStream::new(), TokenStream::new(), TokenStream::new()),
+        };
+        match &self.each {
+            ForEachTask::Spawn(next_task) => {
+                let next_task_name = &next_task.name;
+                let element = next_task.convert_output(&quote! {output});
+                tokens.extend(quote! {
+                    let items: Vec<_> = output.into_iter().collect();
+                    if items.is_empty() {
+                        branchtracker.lock().await.drop_branch();
+                    } else {
+                        {
+                            let branchtracker = branchtracker.lock().await;
+                            for _ in 1..items.len() {
+                                branchtracker.add_branch();
+                            }
+                        }
+                        #semaphore
+                        for output in items {
+                            let branchtracker = branchtracker.clone();
+                            let output = #element;
+                            let deps = std::sync::Arc::clone(&deps);
+                            let fan_out = fan_out.clone();
+                            #acquire_permit
+                            deps.cancellation().spawn(async move {
+                                Self::#next_task_name(branchtracker, output, deps, fan_out).await;
+                                #release_permit
+                            });
+                        }
+                    }
+                })
+            },
+            ForEachTask::Gather(node, policy, element_map, gather_task) => {
+                let element = match element_map {
+                    Some(map) => map.apply(&quote! {node_args}),
+                    None => quote! {node_args},
+                };
+                let invocation = Invocation::node_to_checked_invocation(node, policy, &element);
+                let fail = Invocation::fail_on_error();
+                let node_name = node.get_name();
+                let node_type_name = node.get_nodetype_ident().to_string();
+                let task_output = gather_task.convert_output(&quote! {task_output});
+                let spawn_block = create_spawn_block(
+                    &NextTask { name: gather_task.name.clone(), map: None, limit: None }, true
+                );
+                tokens.extend(quote! {
+                    let items: Vec<_> = output.into_iter().collect();
+                    #semaphore
+                    let mut tasks = Vec::with_capacity(items.len());
+                    for node_args in items {
+                        let deps = std::sync::Arc::clone(&deps);
+                        #acquire_permit
+                        tasks.push(deps.cancellation().spawn(async move {
+                            let output = #invocation;
+                            #release_permit
+                            output
+                        }));
+                    }
+                    let mut output = Vec::with_capacity(tasks.len());
+                    for task in tasks {
+                        let task_output = match task.await {
+                            Ok(task_output) => task_output,
+                            Err(error) => Err(conflagrate::GraphError::from_panic(
+                                #node_name, #node_type_name, Box::new(error)
+                            )),
+                        };
+                        match task_output {
+                            Ok(task_output) => output.push(#task_output),
+                            Err(error) => {
+                                #fail
+                            },
+                        }
+                    }
+                    #spawn_block
+                })
+            },
+        }
+    }
+}
+
+/// Run the trailing nodes of a `sequential` or `pipe` node one after another, in the order of the
+/// node's edges.
+///
+/// Each trailing node is run in this task, and its output is then passed to its "continue_"
+/// function to follow its own edges before the next trailing node runs.  Every trailing node
+/// continues as a branch of its own, so the branches are added to the branch-tracker up front.
+/// SpawnSequential will create a code block that looks like the following:
+/// ```no_compile
+/// {
+///     let branchtracker = branchtracker.lock().await;
+///     for _ in 1..{num_destinations} {
+///         branchtracker.add_branch();
+///     }
+/// }
+/// {
+///     let node_args = output.clone();
+///     let output = <{node_type1} as conflagrate::NodeType>::run(node_args, &deps).await;
+///     Self::continue_next_node1(
+///         branchtracker.clone(), output, std::sync::Arc::clone(&deps), fan_out.clone()
+///     ).await;
+/// }
+/// // ...
+/// {
+///     let node_args = output;
+///     let output = <{node_type_n} as conflagrate::NodeType>::run(node_args, &deps).await;
+///     Self::continue_next_node_n(branchtracker, output, deps, fan_out).await;
+/// }
+/// ```
+///
+/// In a pipe, each trailing node takes the output of the one before it instead:
+/// ```no_compile
+/// let output = {
+///     let node_args = output;
+///     let output = <{node_type1} as conflagrate::NodeType>::run(node_args, &deps).await;
+///     let next_output = output.clone();
+///     Self::continue_next_node1(
+///         branchtracker.clone(), output, std::sync::Arc::clone(&deps), fan_out.clone()
+///     ).await;
+///     next_output
+/// };
+/// // ...
+/// ```
+///
+/// An edge's `map` function converts the trailing node's input as it's bound to `node_args`.
+struct SpawnSequential {
+    destinations: Vec<(Nodes, NodePolicy, Option<EdgeMap>, TaskName)>,
+    pipe: bool,
+}
+impl SpawnSequential {
+    fn new(
+        source: &String,
+        destinations: &[String],
+        pipe: bool,
+        graph: &DescriptiveGraph
+    ) -> Self {
+        Self {
+            destinations: destinations.iter().map(|destination| (
+                graph.get_nodes().get(destination).unwrap().clone(),
+                NodePolicy::for_node(destination, graph),
+                EdgeMap::for_edge(source, destination, graph),
+                TaskName::for_continuation(destination, graph),
+            )).collect(),
+            pipe,
+        }
+    }
+}
+impl ToTokens for SpawnSequential {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let num_destinations = self.destinations.len();
+        tokens.extend(quote! {
+            {
+                let branchtracker = branchtracker.lock().await;
+                for _ in 1..#num_destinations {
+                    branchtracker.add_branch();
+                }
+            }
+        });
+        for (index, (node, policy, map, continue_node)) in self.destinations.iter().enumerate() {
+            let is_last = index == num_destinations - 1;
+            // In a pipe the output is moved into each trailing node in turn.
+            let output = if is_last || self.pipe {quote! {output}} else {quote! {output.clone()}};
+            let node_args = match map {
+                Some(map) => map.apply(&output),
+                None => output,
+            };
+            let (branchtracker, deps, fan_out) = if is_last {
+                (quote! {branchtracker}, quote! {deps}, quote! {fan_out})
+            } else {
+                (
+                    quote! {branchtracker.clone()},
+                    quote! {std::sync::Arc::clone(&deps)},
+                    quote! {fan_out.clone()},
+                )
+            };
+            let invocation = Invocation::node_to_invocation(node, policy, &quote! {node_args});
+            let run_node = quote! {
+                let node_args = #node_args;
+                let output = #invocation;
+            };
+            if self.pipe && !is_last {
+                tokens.extend(quote! {
+                    let output = {
+                        #run_node
+                        let next_output = output.clone();
+                        Self::#continue_node(#branchtracker, output, #deps, #fan_out).await;
+                        next_output
+                    };
+                });
+            } else {
+                tokens.extend(quote! {
+                    {
+                        #run_node
+                        Self::#continue_node(#branchtracker, output, #deps, #fan_out).await;
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Spawns the destinations matching one value of a matcher node in parallel, or ends the branch
+/// with `output` if the value has no edges.
+fn destinations_to_block(destinations: &Vec<NextTask>) -> TokenStream {
+    if destinations.is_empty() {
+        let remove_branch_line = branchtracker_remove_branch();
+        quote! {
+            {
+                #remove_branch_line
+            }
+        }
+    } else {
+        let spawn_parallel = SpawnParallel {destinations: destinations.to_owned(), fan_out: None};
+        quote! {
+           {
+               #spawn_parallel
+           }
+        }
+    }
+}
+
 fn branchtracker_add_branch() -> TokenStream {
     quote! {
         branchtracker.lock().await.add_branch();
@@ -380,26 +1977,107 @@ fn branchtracker_remove_branch() -> TokenStream {
     }
 }
 
-fn create_spawn_block(next_task_name: &TaskName, owns_args: bool) -> TokenStream {
+/// Binds the arguments of the next task, moving them if this is the last task to be spawned and
+/// cloning them otherwise.
+fn create_task_args(next_task: &NextTask, owns_args: bool) -> TokenStream {
     let branchtracker = if owns_args {quote! {branchtracker}} else {quote! {branchtracker.clone()}};
     let output = if owns_args {quote! {output}} else {quote! {output.clone()}};
+    let output = next_task.convert_output(&output);
     let deps = if owns_args {quote! {deps}} else {quote! {std::sync::Arc::clone(&deps)}};
+    let fan_out = if owns_args {quote! {fan_out}} else {quote! {fan_out.clone()}};
     quote! {
-        {
-            let branchtracker = #branchtracker;
-            let output = #output;
-            let deps = #deps;
-            tokio::spawn(async move {
-                Self::#next_task_name(branchtracker, output, deps).await;
+        let branchtracker = #branchtracker;
+        let output = #output;
+        let deps = #deps;
+        let fan_out = #fan_out;
+    }
+}
+
+/// Spawns the next task, first counting the edge if it has an iteration limit:
+/// ```no_compile
+/// {
+///     let branchtracker = branchtracker.clone();
+///     let output = output.clone();
+///     let deps = std::sync::Arc::clone(&deps);
+///     let fan_out = fan_out.clone();
+///     if branchtracker.lock().await.take_edge("node1 -> node2", 3) {
+///         deps.cancellation().spawn(async move {
+///             Self::execute_node2(branchtracker, output, deps, fan_out).await;
+///         });
+///     } else {
+///         deps.cancellation().spawn(async move {
+///             Self::execute_exhausted_node(branchtracker, output, deps, fan_out).await;
+///         });
+///     }
+/// }
+/// ```
+/// Without an `on_exhausted` edge, the graph fails instead:
+/// ```no_compile
+///     } else {
+///         branchtracker.lock().await.fail(conflagrate::GraphError::IterationLimitExceeded {
+///             edge: "node1 -> node2",
+///             max_iterations: 3,
+///         });
+///     }
+/// ```
+fn create_spawn_block(next_task: &NextTask, owns_args: bool) -> TokenStream {
+    let limit = match &next_task.limit {
+        Some(limit) => limit,
+        None => {
+            let task_args = create_task_args(next_task, owns_args);
+            let spawn = spawn_task(next_task, &quote! {output});
+            return quote! {
+                {
+                    #task_args
+                    #spawn
+                }
+            };
+        },
+    };
+    // The output is converted by the `map` function of whichever edge is taken.
+    let unconverted = NextTask { map: None, limit: None, ..next_task.clone() };
+    let task_args = create_task_args(&unconverted, owns_args);
+    let spawn = spawn_task(next_task, &next_task.convert_output(&quote! {output}));
+    let edge = &limit.edge;
+    let max_iterations = limit.max_iterations;
+    let exhausted = match &limit.on_exhausted {
+        Some(exhausted) => spawn_task(exhausted, &exhausted.convert_output(&quote! {output})),
+        None => quote! {
+            branchtracker.lock().await.fail(conflagrate::GraphError::IterationLimitExceeded {
+                edge: #edge,
+                max_iterations: #max_iterations,
             });
+        },
+    };
+    quote! {
+        {
+            #task_args
+            if branchtracker.lock().await.take_edge(#edge, #max_iterations) {
+                #spawn
+            } else {
+                #exhausted
+            }
         }
     }
 }
 
-fn convert_vec_string_to_vec_task_name(nodes: &Vec<String>) -> Vec<TaskName> {
-    let mut task_names = Vec::<TaskName>::with_capacity(nodes.len());
+fn spawn_task(next_task: &NextTask, output: &TokenStream) -> TokenStream {
+    let next_task_name = &next_task.name;
+    quote! {
+        deps.cancellation().spawn(async move {
+            Self::#next_task_name(branchtracker, #output, deps, fan_out).await;
+        });
+    }
+}
+
+fn convert_vec_string_to_vec_next_task(
+    source: &String,
+    nodes: &Vec<String>,
+    graph: &DescriptiveGraph
+) -> Vec<NextTask> {
+    let mut task_names = Vec::<NextTask>::with_capacity(nodes.len());
     for node in nodes {
-        task_names.push(TaskName::from(node))
+        task_names.push(NextTask::for_edge(source, node, graph))
     }
     task_names
 }
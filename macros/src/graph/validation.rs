@@ -0,0 +1,774 @@
+use std::collections::{HashMap, HashSet};
+use crate::graph::descriptivegraph::{
+    DescriptiveGraph, EntryPoint, ENTRY_POINT_METHOD_PREFIXES, EDGE_MAX_ITERATIONS_ATTR,
+    EDGE_ON_EXHAUSTED_ATTR, EDGE_PATTERN_ATTR, EDGE_TIMEOUT_VAL, EDGE_VALUE_ATTR, EDGE_WHEN_ATTR,
+    NODE_BRANCH_ATTR, NODE_FANOUT_ATTR, NODE_GATHER_ATTR, NODE_JOIN_ALL_VAL, NODE_JOIN_ATTR,
+    NODE_MAX_CONCURRENT_ELEMENTS_ATTR, NODE_MAX_ITERATIONS_ATTR, NODE_START_ATTR, NODE_TIMEOUT_ATTR
+};
+use crate::graph::node::{Branches, Nodes, NODE_BRANCH_FOREACH_VAL};
+
+/// Checks the graph as a whole before it is converted into an `ExecutableGraph`.
+///
+/// Parsing the graph only looks at one statement at a time, so mistakes that depend on the rest of
+/// the graph (like an edge to a node that is never given a `type`) would otherwise be silently
+/// dropped.  All problems found are combined into a single error.
+pub fn validate(graph: &DescriptiveGraph) -> Result<(), syn::Error> {
+    let mut errors = Vec::<syn::Error>::new();
+    check_start_nodes(graph, &mut errors);
+    check_entry_point_methods(graph, &mut errors);
+    check_branch_values(graph, &mut errors);
+    check_join_nodes(graph, &mut errors);
+    check_foreach_nodes(graph, &mut errors);
+    check_edges(graph, &mut errors);
+    check_edge_maps(graph, &mut errors);
+    check_iteration_limits(graph, &mut errors);
+    check_timeouts(graph, &mut errors);
+    check_reachability(graph, &mut errors);
+    check_task_names(graph, &mut errors);
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut combined) => {
+            for error in errors {
+                combined.combine(error);
+            }
+            Err(combined)
+        },
+        None => Ok(()),
+    }
+}
+
+fn check_start_nodes(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut entry_points = HashMap::<&Option<String>, &String>::new();
+    for entry_point in graph.get_entry_points() {
+        let first_node = match entry_points.get(&entry_point.name) {
+            Some(first_node) => first_node,
+            None => {
+                entry_points.insert(&entry_point.name, &entry_point.node);
+                continue;
+            },
+        };
+        let message = match &entry_point.name {
+            None => format!(
+                "node `{}` is marked as the start node, but `{}` already is; only one node may be \
+                labeled with `start=true`",
+                entry_point.node, first_node
+            ),
+            Some(name) => format!(
+                "node `{}` is marked as the `{}` entry point, but `{}` already is",
+                entry_point.node, name, first_node
+            ),
+        };
+        errors.push(
+            source_map.error_at_node_attribute(&entry_point.node, NODE_START_ATTR, &message)
+        );
+    }
+}
+
+/// Checks that no two entry points generate a method with the same name, like `start=graph`
+/// generating a `run_graph` method next to the default entry point's.
+fn check_entry_point_methods(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut methods = HashMap::<String, &EntryPoint>::new();
+    for entry_point in graph.get_entry_points() {
+        let clash = ENTRY_POINT_METHOD_PREFIXES.iter()
+            .map(|prefix| entry_point.method_name(prefix))
+            .find_map(|method| match methods.get(&method) {
+                // Entry points sharing a name are already reported by `check_start_nodes`.
+                Some(first) if first.name != entry_point.name => Some((method, first.node.clone())),
+                _ => None,
+            });
+        if let Some((method, first_node)) = clash {
+            let message = format!(
+                "the entry point of node `{}` generates a `{}` method, but the entry point of \
+                node `{}` already does; rename one of them",
+                entry_point.node, method, first_node
+            );
+            errors.push(
+                source_map.error_at_node_attribute(&entry_point.node, NODE_START_ATTR, &message)
+            );
+        }
+        for prefix in ENTRY_POINT_METHOD_PREFIXES {
+            methods.entry(entry_point.method_name(prefix)).or_insert(entry_point);
+        }
+    }
+}
+
+fn check_branch_values(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    for declaration in graph.get_declarations() {
+        if declaration.nodetype.is_some() && !Nodes::is_known_branch(&declaration.branch) {
+            let message = format!(
+                "unknown branch behavior `{}` for node `{}`",
+                declaration.branch, declaration.name
+            );
+            errors.push(
+                source_map.error_at_node_attribute(&declaration.name, NODE_BRANCH_ATTR, &message)
+            );
+        } else if declaration.fanout && !Nodes::supports_fanout(&declaration.branch) {
+            let message = format!(
+                "`{}` only applies to `matcher` and `enummatcher` nodes, not to node `{}`",
+                NODE_FANOUT_ATTR, declaration.name
+            );
+            errors.push(
+                source_map.error_at_node_attribute(&declaration.name, NODE_FANOUT_ATTR, &message)
+            );
+        }
+    }
+}
+
+fn check_join_nodes(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut reported = HashSet::<&String>::new();
+    for declaration in graph.get_declarations() {
+        let name = &declaration.name;
+        if declaration.nodetype.is_none() || declaration.join.is_empty() {
+            continue;
+        }
+        if declaration.join != NODE_JOIN_ALL_VAL {
+            let message = format!(
+                "unknown join behavior `{}` for node `{}`; the only supported value is `{}`",
+                declaration.join, name, NODE_JOIN_ALL_VAL
+            );
+            errors.push(source_map.error_at_node_attribute(name, NODE_JOIN_ATTR, &message));
+            continue;
+        }
+        if graph.get_join_sources(name).len() < 2 && reported.insert(name) {
+            let message = format!(
+                "join node `{}` needs incoming edges from at least two nodes to join",
+                name
+            );
+            errors.push(source_map.error_at_node_attribute(name, NODE_JOIN_ATTR, &message));
+        }
+    }
+}
+
+fn check_foreach_nodes(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let nodes = graph.get_nodes();
+    let mut reported = HashSet::<&String>::new();
+    for declaration in graph.get_declarations() {
+        let name = &declaration.name;
+        if declaration.nodetype.is_none() || !reported.insert(name) {
+            continue;
+        }
+        if declaration.max_concurrent_elements.is_some()
+            && declaration.branch != NODE_BRANCH_FOREACH_VAL
+        {
+            let message = format!(
+                "`{}` only applies to `{}` nodes, not to node `{}`",
+                NODE_MAX_CONCURRENT_ELEMENTS_ATTR, NODE_BRANCH_FOREACH_VAL, name
+            );
+            errors.push(source_map.error_at_node_attribute(
+                name, NODE_MAX_CONCURRENT_ELEMENTS_ATTR, &message
+            ));
+        }
+        let branches = nodes.get(name).map(Nodes::get_destinations);
+        if let Some(Branches::ForEach(destinations)) = branches {
+            if destinations.len() != 1 {
+                let message = format!(
+                    "foreach node `{}` needs exactly one edge, to the node to run for each element",
+                    name
+                );
+                errors.push(source_map.error_at_node_attribute(name, NODE_BRANCH_ATTR, &message));
+            }
+        }
+        if graph.is_gather_node(name) && !is_gathered_from_foreach(graph, name) {
+            let message = format!(
+                "gather node `{}` must have a single incoming edge, from a node that is only \
+                reached from `foreach` nodes and has no other edges",
+                name
+            );
+            errors.push(source_map.error_at_node_attribute(name, NODE_GATHER_ATTR, &message));
+        }
+    }
+}
+
+/// Whether a gather node collects the outputs of a node run only by `foreach` nodes, which is
+/// the node the `foreach` nodes run directly for each element instead of spawning its task.
+fn is_gathered_from_foreach(graph: &DescriptiveGraph, gather_node: &String) -> bool {
+    let nodes = graph.get_nodes();
+    let worker = match graph.get_join_sources(gather_node).as_slice() {
+        [worker] => worker.clone(),
+        _ => return false,
+    };
+    let has_only_gather_edge = match nodes[&worker].get_destinations() {
+        Branches::Parallel(destinations) => matches!(
+            destinations.as_slice(),
+            [destination] if &destination.node == gather_node && destination.guard.is_none()
+        ),
+        _ => false,
+    };
+    let worker_sources = graph.get_join_sources(&worker);
+    has_only_gather_edge
+        && !graph.is_join_node(gather_node)
+        && !graph.is_join_node(&worker)
+        && !worker_sources.is_empty()
+        && worker_sources.iter().all(|source| nodes[source].is_foreach())
+}
+
+fn check_edges(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let nodes = graph.get_nodes();
+    let declared: HashSet<&String> = graph.get_declarations().iter()
+        .map(|declaration| &declaration.name)
+        .collect();
+    for edge in graph.get_edges() {
+        for endpoint in [&edge.source, &edge.destination] {
+            if nodes.contains_key(endpoint) {
+                continue;
+            }
+            let message = if declared.contains(endpoint) {
+                format!("edge connects node `{}`, which has no `type` attribute", endpoint)
+            } else {
+                format!("edge connects node `{}`, which is never declared", endpoint)
+            };
+            errors.push(source_map.error_at_edge(&edge.source, &edge.destination, &message));
+        }
+        if let Some(source) = nodes.get(&edge.source) {
+            if !edge.value.is_empty() && !edge.on_timeout && !source.uses_edge_values() {
+                let message = format!(
+                    "edge `{} -> {}` has a `value`, but node `{}` doesn't use a matcher branch",
+                    edge.source, edge.destination, edge.source
+                );
+                errors.push(source_map.error_at_edge_attribute(
+                    &edge.source, &edge.destination, EDGE_VALUE_ATTR, &message
+                ));
+            }
+            if !edge.pattern.is_empty() && !source.uses_edge_patterns() {
+                let message = format!(
+                    "edge `{} -> {}` has a `pattern`, but node `{}` isn't a `matcher` node",
+                    edge.source, edge.destination, edge.source
+                );
+                errors.push(source_map.error_at_edge_attribute(
+                    &edge.source, &edge.destination, EDGE_PATTERN_ATTR, &message
+                ));
+            }
+            if source.is_race() && graph.is_join_node(&edge.destination) {
+                let message = format!(
+                    "race node `{}` can't race join node `{}`, which has to wait for its other \
+                    inputs",
+                    edge.source, edge.destination
+                );
+                errors.push(source_map.error_at_edge(&edge.source, &edge.destination, &message));
+            }
+            if source.is_sequential() && graph.is_join_node(&edge.destination) {
+                let message = format!(
+                    "sequential node `{}` runs its trailing nodes itself, so it can't lead to join \
+                    node `{}`, which has to wait for its other inputs",
+                    edge.source, edge.destination
+                );
+                errors.push(source_map.error_at_edge(&edge.source, &edge.destination, &message));
+            }
+            if !edge.guard.is_empty() && !source.uses_edge_guards() {
+                let message = format!(
+                    "edge `{} -> {}` has a `when` guard, but node `{}` uses a matcher branch; \
+                    only edges leaving parallel nodes can be guarded",
+                    edge.source, edge.destination, edge.source
+                );
+                errors.push(source_map.error_at_edge_attribute(
+                    &edge.source, &edge.destination, EDGE_WHEN_ATTR, &message
+                ));
+            }
+        }
+    }
+}
+
+/// Edges between the same two nodes (like two values of a matcher node leading to the same node)
+/// run the same task, so they can't convert the output differently.
+fn check_edge_maps(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut maps = HashMap::<(&String, &String), &String>::new();
+    for edge in graph.get_edges() {
+        let map = maps.entry((&edge.source, &edge.destination)).or_insert(&edge.map);
+        if *map != &edge.map {
+            let message = format!(
+                "edges from `{}` to `{}` must all have the same `map` function",
+                edge.source, edge.destination
+            );
+            errors.push(source_map.error_at_edge(&edge.source, &edge.destination, &message));
+        }
+    }
+}
+
+/// Iteration limits are checked where the task of a node spawns the next one, so they only apply
+/// to nodes that spawn each of their destinations separately.
+fn check_iteration_limits(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let nodes = graph.get_nodes();
+    let supports_limits = |node: &Nodes| {
+        !node.is_race() && !node.is_sequential() && !node.is_foreach()
+    };
+    let mut reported = HashSet::<&String>::new();
+    for declaration in graph.get_declarations() {
+        let name = &declaration.name;
+        let node = match nodes.get(name) {
+            Some(node) => node,
+            None => continue,
+        };
+        if declaration.max_iterations.is_some() && !supports_limits(node) && reported.insert(name) {
+            let message = format!(
+                "`{}` doesn't apply to node `{}`, which runs its destinations itself; only edges \
+                leaving parallel and matcher nodes can be limited",
+                NODE_MAX_ITERATIONS_ATTR, name
+            );
+            errors.push(
+                source_map.error_at_node_attribute(name, NODE_MAX_ITERATIONS_ATTR, &message)
+            );
+        }
+    }
+    let mut exhausted_sources = HashSet::<&String>::new();
+    for edge in graph.get_edges() {
+        let source = match nodes.get(&edge.source) {
+            Some(source) => source,
+            None => continue,
+        };
+        let gathered = graph.is_gather_node(&edge.destination)
+            && graph.get_iteration_limit(&edge.source, &edge.destination).is_some();
+        if gathered {
+            let message = format!(
+                "edge `{} -> {}` leads to gather node `{}`, so it can't have an iteration limit",
+                edge.source, edge.destination, edge.destination
+            );
+            errors.push(source_map.error_at_edge(&edge.source, &edge.destination, &message));
+        }
+        if edge.max_iterations.is_some() && (edge.on_exhausted || !supports_limits(source)) {
+            let message = if edge.on_exhausted {
+                format!(
+                    "edge `{} -> {}` is the `{}` edge, so it can't have an iteration limit itself",
+                    edge.source, edge.destination, EDGE_ON_EXHAUSTED_ATTR
+                )
+            } else {
+                format!(
+                    "edge `{} -> {}` can't have `{}`, because node `{}` runs its destinations \
+                    itself; only edges leaving parallel and matcher nodes can be limited",
+                    edge.source, edge.destination, EDGE_MAX_ITERATIONS_ATTR, edge.source
+                )
+            };
+            errors.push(source_map.error_at_edge_attribute(
+                &edge.source, &edge.destination, EDGE_MAX_ITERATIONS_ATTR, &message
+            ));
+        }
+        if !edge.on_exhausted {
+            continue;
+        }
+        let message = if !exhausted_sources.insert(&edge.source) {
+            format!("node `{}` has more than one `{}` edge", edge.source, EDGE_ON_EXHAUSTED_ATTR)
+        } else if !supports_limits(source) || !graph.has_iteration_limit(&edge.source) {
+            format!(
+                "edge `{} -> {}` is an `{}` edge, but no edge leaving node `{}` has `{}`",
+                edge.source, edge.destination, EDGE_ON_EXHAUSTED_ATTR, edge.source,
+                EDGE_MAX_ITERATIONS_ATTR
+            )
+        } else if !edge.value.is_empty() || !edge.pattern.is_empty() || !edge.guard.is_empty() {
+            format!(
+                "edge `{} -> {}` is an `{}` edge, so it can't have a `{}`, `{}` or `{}`",
+                edge.source, edge.destination, EDGE_ON_EXHAUSTED_ATTR, EDGE_VALUE_ATTR,
+                EDGE_PATTERN_ATTR, EDGE_WHEN_ATTR
+            )
+        } else {
+            continue;
+        };
+        errors.push(source_map.error_at_edge_attribute(
+            &edge.source, &edge.destination, EDGE_ON_EXHAUSTED_ATTR, &message
+        ));
+    }
+}
+
+/// A timeout edge is followed from the middle of the task that runs its source node, which hands
+/// the branch over to the edge's task, so the source must be run by a task of its own and the
+/// destination must have one.
+fn check_timeouts(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut timeout_sources = HashSet::<&String>::new();
+    for edge in graph.get_edges().iter().filter(|edge| edge.on_timeout) {
+        let source = &edge.source;
+        let destination = &edge.destination;
+        let message = if !timeout_sources.insert(source) {
+            format!(
+                "node `{}` has more than one `{}={}` edge",
+                source, EDGE_VALUE_ATTR, EDGE_TIMEOUT_VAL
+            )
+        } else if graph.is_sequential_destination(source)
+            || graph.get_gather_node(source).is_some()
+        {
+            format!(
+                "node `{}` is run by a `sequential` or `foreach` node, so it can't follow an edge \
+                when it times out; remove the edge to fail the run instead",
+                source
+            )
+        } else if graph.is_gather_node(destination)
+            || graph.get_gather_node(destination).is_some()
+        {
+            format!(
+                "edge `{} -> {}` is the `{}` edge of node `{}`, so it can't lead to a node that \
+                gathers or is gathered",
+                source, destination, NODE_TIMEOUT_ATTR, source
+            )
+        } else if !edge.pattern.is_empty() || !edge.guard.is_empty() || edge.on_exhausted
+            || edge.max_iterations.is_some()
+        {
+            format!(
+                "edge `{} -> {}` is the `{}` edge of node `{}`, so it can't have a `{}`, `{}`, \
+                `{}` or `{}`",
+                source, destination, NODE_TIMEOUT_ATTR, source, EDGE_PATTERN_ATTR, EDGE_WHEN_ATTR,
+                EDGE_MAX_ITERATIONS_ATTR, EDGE_ON_EXHAUSTED_ATTR
+            )
+        } else {
+            continue;
+        };
+        errors.push(
+            source_map.error_at_edge_attribute(source, destination, EDGE_VALUE_ATTR, &message)
+        );
+    }
+}
+
+fn check_reachability(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let reachable = graph.get_reachable_nodes();
+    let mut reported = HashSet::<&String>::new();
+    for declaration in graph.get_declarations() {
+        let name = &declaration.name;
+        if graph.get_nodes().contains_key(name)
+            && !reachable.contains(name)
+            && reported.insert(name)
+        {
+            let message = format!("node `{}` can't be reached from any start node", name);
+            errors.push(source_map.error_at_node(name, &message));
+        }
+    }
+}
+
+fn check_task_names(graph: &DescriptiveGraph, errors: &mut Vec<syn::Error>) {
+    let source_map = graph.get_source_map();
+    let mut task_names = HashMap::<String, &String>::new();
+    for declaration in graph.get_declarations() {
+        let name = &declaration.name;
+        if !graph.get_nodes().contains_key(name) {
+            continue;
+        }
+        let qualified_name = graph.get_qualified_name(name);
+        match task_names.get(&qualified_name) {
+            Some(other) if *other != name => {
+                let message = format!(
+                    "node `{}` and node `{}` would both generate the task `execute_{}`; rename one \
+                    of them or move it into a differently named cluster",
+                    other, name, qualified_name
+                );
+                errors.push(source_map.error_at_node(name, &message));
+            },
+            Some(_) => {},
+            None => {
+                task_names.insert(qualified_name, name);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_messages(text: &str) -> Vec<String> {
+        let result = DescriptiveGraph::from_test_source(text).and_then(|graph| validate(&graph));
+        match result {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|error| error.to_string()).collect(),
+        }
+    }
+
+    fn assert_single_error(text: &str, expected: &str) {
+        let errors = error_messages(text);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].starts_with(expected), "expected `{}`, found `{}`", expected, errors[0]);
+    }
+
+    #[test]
+    fn accepts_valid_graph() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=matcher];
+            b[type=B];
+            c[type=C, start=other];
+            a -> b [value=x];
+            a -> c;
+        }"#);
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn reports_second_default_start_node() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=A, start=true];
+        }"#, "test.dot:3:29: node `b` is marked as the start node, but `a` already is");
+    }
+
+    #[test]
+    fn reports_second_named_start_node() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=A, start=other];
+            c[type=A, start=other];
+            a -> b -> c;
+        }"#, "test.dot:4:29: node `c` is marked as the `other` entry point, but `b` already is");
+    }
+
+    #[test]
+    fn reports_clashing_entry_point_methods() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=A, start=graph];
+            a -> b;
+        }"#, "test.dot:3:29: the entry point of node `b` generates a `run_graph` method, but the \
+            entry point of node `a` already does");
+    }
+
+    #[test]
+    fn reports_clashing_named_entry_point_methods() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=x];
+            b[type=A, start=graph_x];
+            a -> b;
+        }"#, "test.dot:3:29: the entry point of node `b` generates a `run_graph_x` method");
+    }
+
+    #[test]
+    fn reports_missing_start_node() {
+        let errors = error_messages("digraph G { a[type=A]; }");
+        assert_eq!(
+            errors,
+            vec!["test.dot: No starting node found!  Give one node the attribute 'start'."]
+        );
+    }
+
+    #[test]
+    fn reports_entry_point_named_with() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=A, start=with];
+            a -> b;
+        }"#, "test.dot:3:29: the entry point of node `b` generates a `run_with` method");
+    }
+
+    #[test]
+    fn reports_entry_point_named_on() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=A, start=on];
+            a -> b;
+        }"#, "test.dot:3:29: the entry point of node `b` generates a `run_on` method");
+    }
+
+    #[test]
+    fn reports_entry_point_clashing_with_run_with_method() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=x];
+            b[type=A, start=with_x];
+            a -> b;
+        }"#, "test.dot:3:29: the entry point of node `b` generates a `run_with_x` method");
+    }
+
+    #[test]
+    fn reports_unknown_branch() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, branch=sideways];
+        }"#, "test.dot:2:42: unknown branch behavior `sideways` for node `a`");
+    }
+
+    #[test]
+    fn reports_fanout_on_parallel_node() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, fanout=true];
+        }"#, "test.dot:2:42: `fanout` only applies to `matcher` and `enummatcher` nodes");
+    }
+
+    #[test]
+    fn reports_unknown_join() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B, join=any];
+            a -> b;
+        }"#, "test.dot:3:28: unknown join behavior `any` for node `b`");
+    }
+
+    #[test]
+    fn reports_join_with_one_source() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B, join=all];
+            a -> b;
+        }"#, "test.dot:3:28: join node `b` needs incoming edges from at least two nodes");
+    }
+
+    #[test]
+    fn reports_edges_to_undeclared_and_untyped_nodes() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true];
+            b[label=B];
+            a -> b;
+            a -> c;
+        }"#);
+        assert_eq!(errors, vec![
+            "test.dot:4:18: edge connects node `b`, which has no `type` attribute",
+            "test.dot:5:18: edge connects node `c`, which is never declared",
+        ]);
+    }
+
+    #[test]
+    fn reports_value_on_parallel_edge() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B];
+            a -> b [value=x];
+        }"#, "test.dot:4:27: edge `a -> b` has a `value`, but node `a` doesn't use a matcher");
+    }
+
+    #[test]
+    fn reports_pattern_on_result_matcher_edge() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, branch=resultmatcher];
+            b[type=B];
+            a -> b [value=ok, pattern=glob];
+        }"#, "test.dot:4:39: edge `a -> b` has a `pattern`, but node `a` isn't a `matcher` node");
+    }
+
+    #[test]
+    fn reports_guard_on_matcher_edge() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, branch=matcher];
+            b[type=B];
+            a -> b [value=x, when=is_ready];
+        }"#, "test.dot:4:35: edge `a -> b` has a `when` guard, but node `a` uses a matcher branch");
+    }
+
+    #[test]
+    fn reports_race_to_join_node() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=race];
+            b[type=B];
+            c[type=C, join=all];
+            a -> b -> c;
+            a -> c;
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("race node `a` can't race join node `c`"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn reports_unreachable_node() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B];
+        }"#, "test.dot:3:13: node `b` can't be reached from any start node");
+    }
+
+    #[test]
+    fn reports_clashing_task_names() {
+        assert_single_error(r#"digraph G {
+            x_a[type=A, start=true];
+            subgraph cluster_x {
+                a[type=A];
+            }
+            x_a -> a;
+        }"#, "test.dot:4:17: node `x_a` and node `a` would both generate the task `execute_x_a`");
+    }
+
+    #[test]
+    fn reports_max_concurrent_elements_on_non_foreach_node() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, max_concurrent_elements=2];
+        }"#, "test.dot:2:59: `max_concurrent_elements` only applies to `foreach` nodes, not \
+            to node `a`");
+    }
+
+    #[test]
+    fn reports_foreach_node_with_two_edges() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true, branch=foreach];
+            b[type=B];
+            c[type=C];
+            a -> b;
+            a -> c;
+        }"#, "test.dot:2:42: foreach node `a` needs exactly one edge, to the node to run for each \
+            element");
+    }
+
+    #[test]
+    fn reports_gather_node_not_fed_by_foreach() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B, gather=true];
+            a -> b;
+        }"#, "test.dot:3:30: gather node `b` must have a single incoming edge, from a node that is \
+            only reached from `foreach` nodes and has no other edges");
+    }
+
+    #[test]
+    fn reports_map_that_isnt_a_path() {
+        assert_single_error(r#"digraph G {
+            a[type=A, start=true];
+            b[type=B];
+            a -> b [map="to text"];
+        }"#, "test.dot:4:25: `map` must be the path of a function, like `my_module::my_function`, \
+            found `to text`");
+    }
+
+    #[test]
+    fn reports_sequential_node_leading_to_join_node() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=parallel];
+            b[type=B, branch=sequential];
+            c[type=C, join=all];
+            a -> b -> c;
+            a -> c;
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].contains("sequential node `b` runs its trailing nodes itself"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn reports_iteration_limit_on_sequential_node() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=sequential, max_iterations=2];
+            b[type=B];
+            a -> b;
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(errors[0].contains("`max_iterations` doesn't apply to node `a`"), "{}", errors[0]);
+    }
+
+    #[test]
+    fn reports_on_exhausted_edge_without_a_limit() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=matcher];
+            b[type=B];
+            a -> a;
+            a -> b [on_exhausted=true];
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].contains("no edge leaving node `a` has `max_iterations`"), "{}", errors[0]
+        );
+    }
+
+    #[test]
+    fn reports_timeout_edge_from_node_run_by_sequential_node() {
+        let errors = error_messages(r#"digraph G {
+            a[type=A, start=true, branch=sequential];
+            b[type=B, timeout="1s"];
+            c[type=C];
+            a -> b;
+            b -> c [value=timeout];
+        }"#);
+        assert_eq!(errors.len(), 1, "{:?}", errors);
+        assert!(
+            errors[0].contains("node `b` is run by a `sequential` or `foreach` node"),
+            "{}",
+            errors[0]
+        );
+    }
+}
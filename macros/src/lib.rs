@@ -6,10 +6,10 @@ mod graph;
 mod nodetype;
 
 use crate::dependency::dependency_impl;
-use crate::graph::graph_impl;
-use crate::nodetype::nodetype_impl;
+use crate::graph::{graph_file_impl, graph_tokens_impl};
+use crate::nodetype::{nodetype_impl, NodeTypeOptions};
 use proc_macro::TokenStream;
-use syn::{parse_macro_input, ItemFn};
+use syn::{parse_macro_input, AttributeArgs, ItemFn, LitStr};
 
 
 /// Defines a dependency that can be accessed from any node in the graph.
@@ -72,15 +72,15 @@ use syn::{parse_macro_input, ItemFn};
 ///     }
 /// }
 ///
-/// fn main() {
-///     MessageGraph::run(());
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     MessageGraph::run(())
 /// }
 /// ```
 ///
 /// # See Also
 /// * [`nodetype`](macro@nodetype) -- Macro for associating a function with a type of node.
-/// Reference types in
-/// input arguments are interpreted to be dependencies.
+///   Reference types in
+///   input arguments are interpreted to be dependencies.
 #[proc_macro_attribute]
 pub fn dependency(_: TokenStream, func: TokenStream) -> TokenStream {
     TokenStream::from(dependency_impl(parse_macro_input!(func as ItemFn)))
@@ -135,19 +135,45 @@ pub fn dependency(_: TokenStream, func: TokenStream) -> TokenStream {
 /// determined by the output of the matcher node.  This puts a constraint on the form of the
 /// output of the node.
 ///
-/// The return type of the node is required to be a 2-tuple of the form `(String, T)`, where the
-/// String first element is used for matching and the `T` second element is passed to the next node
-/// as its input.  Edges tagged with the `value` attribute (see
+/// The return type of the node is required to be a 2-tuple of the form `(K, T)`, where the
+/// first element is the key used for matching and the `T` second element is passed to the next
+/// node as its input.  The key is usually a `String`, but can be any type implementing
+/// [`MatchKey`](https://docs.rs/conflagrate/latest/conflagrate/trait.MatchKey.html), which
+/// includes the integer types, `char` and `bool`.  Edges tagged with the `value` attribute (see
 /// [`graph`: Edge Attributes](graph#edge-attributes)) are matched using the attribute's value,
 /// and a matching edge determines the following node.  If no matches are found, an edge without a
 /// `value` attribute is used as the default. If no default edge is provided, the graph will
 /// terminate, using the matcher node's output as its output.
 ///
+/// Each value may only be given to one of the node's edges, so an edge can't silently replace
+/// another.  To follow several edges for the same value, give the node the `fanout=true`
+/// attribute: all the edges sharing the matched value are then followed in parallel, each with
+/// its own copy of the output.  The same applies to the variants of an `enummatcher` node.
+///
+/// Edges are tried in the order they are declared, and the first one that matches is followed.
+/// By default an edge matches if the key equals its `value`, but an edge's `pattern` attribute can
+/// ask for a looser match:
+/// * `pattern=glob` -- The `value` is a glob pattern, where `*` matches any run of characters and
+///   `?` matches any one character (e.g. `value="cmd_*"`).
+/// * `pattern=regex` -- The `value` is a regular expression, checked when the graph is compiled.
+/// * `pattern=range` -- The `value` is a Rust range of literals (e.g. `value="200..300"` or
+///   `value="'a'..='z'"`), which the key must fall within.
+///
+/// ```ignore
+/// digraph {
+///     get_status[type=GetStatus, branch=matcher, start=true];
+///     ...
+///     get_status -> teapot [value=418];
+///     get_status -> success [value="200..300", pattern=range];
+///     get_status -> failure;
+/// }
+/// ```
+///
 /// ```
 /// # use std::collections::VecDeque;
 /// # use conflagrate::nodetype;
 /// #[nodetype]
-/// async fn GetCommand(input: VecDeque<String>) -> (String, VecDeque<String>) {
+/// async fn GetCommand(mut input: VecDeque<String>) -> (String, VecDeque<String>) {
 ///     let cmd = input.pop_front().unwrap_or(String::from(""));
 ///     (cmd, input)
 /// }
@@ -168,14 +194,168 @@ pub fn dependency(_: TokenStream, func: TokenStream) -> TokenStream {
 /// # use std::collections::VecDeque;
 /// # use conflagrate::nodetype;
 /// #[nodetype]
-/// async fn GetCommand(input: VecDeque<String>) -> Result<(String, VecDeque<String>), String> {
+/// async fn GetCommand(mut input: VecDeque<String>) -> Result<(String, VecDeque<String>), String> {
 ///     match input.pop_front() {
-///         Ok(cmd) => Ok((cmd, input)),
-///         Err(e) => Err(format!("unable to get command from input: {}", e.to_string())),
+///         Some(cmd) => Ok((cmd, input)),
+///         None => Err(String::from("unable to get command from empty input")),
 ///     }
 /// }
 /// ```
 ///
+/// ## Option Matcher
+///
+/// Nodes described with the `branch=optionmatcher` attribute return an
+/// [`Option<T>`](core::option), which suits lookups and polls that may come up empty.  Edges
+/// marked with `value=some` receive `T` from `Some(T)`, and edges marked with `value=none` are
+/// followed for `None` and receive `()` as their input.  As with the result matcher, several
+/// edges can share either value to run their nodes in parallel, and a side without any edges
+/// ends the graph with its value.
+///
+/// ```
+/// # use std::collections::HashMap;
+/// # use conflagrate::{dependency, nodetype};
+/// # #[dependency]
+/// # async fn users() -> HashMap<u32, String> {
+/// #     HashMap::<u32, String>::new()
+/// # }
+/// #[nodetype]
+/// async fn LookupUser(id: u32, users: &HashMap<u32, String>) -> Option<String> {
+///     users.get(&id).cloned()
+/// }
+/// ```
+///
+/// ## Enum Matcher
+///
+/// A node with the `branch=enummatcher` attribute returns an enum of your own, and the variant it
+/// returns chooses the trailing node.  Each edge's `value` attribute names a variant, and the
+/// node it leads to receives the contents of that variant as its input, so every trailing node
+/// can take a different type.  Variants routed by an edge must be tuple variants holding exactly
+/// one value, like `Variant(value)`.  Unit variants, struct variants and tuple variants with more
+/// than one field are compile errors at the edge naming them, so use `Variant(())` for a variant
+/// with nothing to pass on, and a tuple or struct for one with several values.  They can still be
+/// left to the default edge: an edge without a `value` attribute receives the whole enum for any
+/// variant not otherwise routed.  If there is no default edge, every variant must have an edge,
+/// which is checked at compile time.
+///
+/// ```
+/// # use conflagrate::nodetype;
+/// pub enum Command {
+///     Add(u32),
+///     Echo(String),
+///     Quit(()),
+/// }
+///
+/// #[nodetype]
+/// async fn ParseCommand(input: String) -> Command {
+///     match input.split_once(' ') {
+///         Some(("add", number)) => Command::Add(number.parse().unwrap_or(0)),
+///         Some(("echo", text)) => Command::Echo(String::from(text)),
+///         _ => Command::Quit(()),
+///     }
+/// }
+/// ```
+///
+/// With `Quit` declared as a unit variant instead, its edge doesn't compile:
+///
+/// ```compile_fail
+/// # use conflagrate::{graph, nodetype};
+/// pub enum Command {
+///     Echo(String),
+///     Quit,
+/// }
+/// # #[nodetype]
+/// # pub fn ParseCommand(input: String) -> Command {
+/// #     Command::Echo(input)
+/// # }
+/// # #[nodetype]
+/// # pub fn Echo(text: String) {
+/// #     println!("{}", text);
+/// # }
+/// # #[nodetype]
+/// # pub fn Quit() {}
+///
+/// graph!{
+///     digraph {
+///         parse[type=ParseCommand, branch=enummatcher, start=true];
+///         echo[type=Echo];
+///         quit[type=Quit];
+///         parse -> echo [value=Echo];
+///         parse -> quit [value=Quit];  // error: can only route variants holding exactly one value
+///     }
+/// }
+/// # fn main() {}
+/// ```
+///
+/// ## Race
+///
+/// A node with the `branch=race` attribute starts all of its trailing nodes in parallel, like
+/// `parallel`, but only the trailing node that finishes first continues along its own edges.  The
+/// tasks of the others are aborted at their next `.await`, so a trailing node that blocks without
+/// awaiting still runs to completion before it's discarded.  This suits hedged requests to
+/// redundant backends, or racing work against a node that sleeps for a timeout:
+///
+/// ```ignore
+/// digraph {
+///     request[type=Request, branch=race, start=true];
+///     primary[type=QueryPrimary];
+///     replica[type=QueryReplica];
+///     timeout[type=Timeout];
+///     request -> primary -> respond;
+///     request -> replica -> respond;
+///     request -> timeout -> report_timeout;
+/// }
+/// ```
+///
+/// As with `parallel`, the output from the race node is cloned to each trailing node.  The
+/// trailing nodes can't be `join=all` nodes, since a join node waits for all of its inputs.  A
+/// trailing node that runs out of its `timeout` finishes the race like any other: if it's first,
+/// it wins and the graph follows its `value=timeout` edge (see [Timeouts](#timeouts)).
+///
+/// ## Sequential
+///
+/// A node with the `branch=sequential` attribute runs its trailing nodes one at a time, in the
+/// order their edges are declared, instead of all at once.  Each trailing node starts once the
+/// one before it has finished, and like `parallel`, each takes a clone of the node's output.  The
+/// nodes after each trailing node still run in parallel with the rest, so only the trailing nodes
+/// themselves are ordered.  This keeps side effects in order, like writing to a database before
+/// sending a notification about it:
+///
+/// ```ignore
+/// digraph {
+///     save[type=Save, branch=sequential, start=true];
+///     write_db[type=WriteDb];
+///     notify[type=Notify];
+///     save -> write_db;
+///     save -> notify;
+/// }
+/// ```
+///
+/// With `branch=pipe` instead, the first trailing node takes the node's output, and each of the
+/// others takes the output of the trailing node before it.  In a pipe, the trailing nodes before
+/// the last must return a type implementing `Clone`, since their output also continues along
+/// their own edges.
+///
+/// The trailing nodes of `sequential` and `pipe` nodes can't be `join=all` nodes.
+///
+/// ## For Each
+///
+/// A node with the `branch=foreach` attribute returns a collection, and its one trailing node runs
+/// once for each element, all in parallel.  The node's return type can be anything implementing
+/// `IntoIterator`, and the trailing node takes a single element as its input.
+///
+/// ```
+/// # use conflagrate::nodetype;
+/// #[nodetype]
+/// async fn ListFiles(directory: String) -> Vec<String> {
+///     std::fs::read_dir(directory).unwrap()
+///         .map(|entry| entry.unwrap().path().display().to_string())
+///         .collect()
+/// }
+/// ```
+///
+/// The trailing node's outputs can be collected back together with a gather node (see [`graph`:
+/// Scatter and Gather](graph#scatter-and-gather)).
+///
 /// # Blocking Versus Non-Blocking
 ///
 /// Conflagrate applications are built using `tokio`, so `nodetype`s are converted to async
@@ -183,6 +363,24 @@ pub fn dependency(_: TokenStream, func: TokenStream) -> TokenStream {
 /// it is blocking and spawns its codeblock in a separate thread.  To avoid spawning extra
 /// threads, use `async fn` wherever possible.
 ///
+/// # Concurrency Limits
+///
+/// `#[nodetype(max_concurrency = N)]` lets at most `N` invocations of the `nodetype` run at once,
+/// across every node of that type in every graph and every run.  Invocations over the limit wait
+/// their turn in the order they arrived.  This suits `nodetype`s wrapping a resource with limited
+/// capacity, such as a database or a rate-limited API.  To limit only one node of a graph, use the
+/// node's `max_concurrency` attribute instead (see
+/// [`graph`: Concurrency Limits](graph#concurrency-limits)).
+///
+/// ```
+/// # use conflagrate::nodetype;
+/// #[nodetype(max_concurrency = 2)]
+/// pub async fn Query(id: u32) -> String {
+///     // ... at most two queries run at once
+///     format!("row {}", id)
+/// }
+/// ```
+///
 /// # Visibility (Public Versus Private)
 ///
 /// To facilitate larger projects split into multiple modules, the `run` and `run_graph` methods
@@ -261,21 +459,25 @@ pub fn dependency(_: TokenStream, func: TokenStream) -> TokenStream {
 ///     }
 /// }
 ///
-/// fn main() {
-///     GreetingGraph::run(());
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     GreetingGraph::run(())
 /// }
 /// ```
 ///
 /// # See Also
 /// * [`dependency`](macro@dependency) -- Macro for defining an external resource or data to be used
-/// as input by a node in addition to the output of the previous node.
+///   as input by a node in addition to the output of the previous node.
 /// * [`graph`](macro@graph) -- Macro for building an executable control flow graph using
-/// `nodetype`s.
+///   `nodetype`s.
+#[allow(clippy::test_attr_in_doctest)]
 #[proc_macro_attribute]
-pub fn nodetype(_: TokenStream, func: TokenStream) -> TokenStream {
-    TokenStream::from(
-        nodetype_impl(parse_macro_input!(func as ItemFn))
-    )
+pub fn nodetype(args: TokenStream, func: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AttributeArgs);
+    let func = parse_macro_input!(func as ItemFn);
+    TokenStream::from(match NodeTypeOptions::from_args(args) {
+        Ok(options) => nodetype_impl(func, options),
+        Err(error) => error.to_compile_error(),
+    })
 }
 
 /// Defines the control flow graph of an application.
@@ -289,27 +491,800 @@ pub fn nodetype(_: TokenStream, func: TokenStream) -> TokenStream {
 /// # Node Attributes
 ///
 /// * `type` -- The [`nodetype`](macro@nodetype) associated with the node the in graph, which is a
-/// block of executable code that takes as input the output from the previous node and provides as
-/// output the input to the next node.  Multiple nodes in the graph can use the same `nodetype`
-/// to facillitate more code reuse.
-/// * `start` -- Labels the node to start the graph from.  Only one node may be labeled with the
-/// `start` attribute.
+///   block of executable code that takes as input the output from the previous node and provides as
+///   output the input to the next node.  Multiple nodes in the graph can use the same `nodetype`
+///   to facillitate more code reuse.
+/// * `start` -- Labels the node to start the graph from.  `start=true` marks the graph's default
+///   entry point, run by the `run` and `run_graph` methods, and only one node may be labeled with
+///   it.  `start=<name>` marks a named entry point instead (see [Entry Points](#entry-points)).
 /// * `branch` -- Tells conflagrate how to handle a node that has more than one node trailing it in
-/// the graph.  May take the following values:
+///   the graph.  May take the following values:
 ///     * `parallel` (default) -- Conflagrate executes all trailing nodes simultaneously in
-/// parallel.  The return value from the node is cloned and passed separately to each tail.  If
-/// the `branch` attribute is omitted, this value is assumed.
+///       parallel.  The return value from the node is cloned and passed separately to each tail.  If
+///       the `branch` attribute is omitted, this value is assumed.
 ///     * `matcher` -- Conflagrate executes only one trailing node determined by the output of
-/// the matcher node.  This puts constraints on the required return type of the `nodetype` (see
-/// [`nodetype`: Matcher](nodetype#matcher)).
+///       the matcher node.  This puts constraints on the required return type of the `nodetype` (see
+///       [`nodetype`: Matcher](nodetype#matcher)).
 ///     * `resultmatcher` -- A variant of `matcher` that matches on a `Result` instead of a
-/// `String` (see [`nodetype`: Result Matcher](nodetype#result-matcher)).
+///       `String` (see [`nodetype`: Result Matcher](nodetype#result-matcher)).
+///     * `optionmatcher` -- A variant of `matcher` that matches on an `Option` (see
+///       [`nodetype`: Option Matcher](nodetype#option-matcher)).
+///     * `enummatcher` -- A variant of `matcher` that matches on the variants of an enum (see
+///       [`nodetype`: Enum Matcher](nodetype#enum-matcher)).
+///     * `race` -- Conflagrate starts all trailing nodes in parallel, and only the first to finish
+///       continues along its own edges.  The others are aborted (see [`nodetype`: Race](nodetype#race)).
+///     * `foreach` -- Conflagrate runs the trailing node once for each element of the node's output,
+///       which must implement `IntoIterator` (see [`nodetype`: For Each](nodetype#for-each)).
+///     * `sequential` -- Conflagrate runs the trailing nodes one after another, in the order their
+///       edges are declared, each with a clone of the node's output (see [`nodetype`:
+///       Sequential](nodetype#sequential)).
+///     * `pipe` -- A variant of `sequential` where each trailing node takes the output of the one
+///       before it.
+/// * `fanout` -- With `fanout=true`, a `matcher` or `enummatcher` node may have several edges with
+///   the same `value`, and follows all of them in parallel when the value matches (see
+///   [`nodetype`: Matcher](nodetype#matcher)).
+/// * `join` -- With `join=all`, the node waits for an output from every node with an edge into it
+///   and then runs once with all of them (see [Joining Branches](#joining-branches)).
+/// * `max_concurrency` -- Limits how many invocations of the node run at once, across every run of
+///   the graph (see [Concurrency Limits](#concurrency-limits)).
+/// * `max_concurrent_elements` -- Limits how many elements of a `foreach` node's output are run at
+///   once (see [Scatter and Gather](#scatter-and-gather)).
+/// * `gather` -- With `gather=true`, the node collects the outputs of the node run by a `foreach`
+///   node into a `Vec` (see [Scatter and Gather](#scatter-and-gather)).
+/// * `max_iterations` -- Limits how many times each edge leaving the node may be taken in one run
+///   of the graph (see [Bounded Loops](#bounded-loops)).
+/// * `yield` -- With `yield=true`, a loop of parallel nodes that includes the node yields to the
+///   runtime each time around (see [Worker Loops](#worker-loops)).
+/// * `timeout` -- Limits how long the node may run, as a duration like `500ms`, `30s`, `5m` or
+///   `1h` (see [Timeouts](#timeouts)).
+/// * `retry` -- How many times to run the node again if it fails (see [Retries](#retries)).
+/// * `retry_delay` -- How long to wait before each retry, as a duration like `timeout`.
+/// * `backoff` -- With `backoff=exponential`, the `retry_delay` doubles with each retry.  Defaults
+///   to `fixed`.
 ///
 /// # Edge Attributes
 ///
 /// * `value` -- Used with nodes with the `branch=matcher` attribute (see above).  The return value
-/// of the matcher node is compared against this (string) value.  If it matches, this edge is
-/// followed to determine the next node to be executed in the graph.
+///   of the matcher node is compared against this (string) value.  If it matches, this edge is
+///   followed to determine the next node to be executed in the graph.  For `resultmatcher` nodes
+///   the value is `ok` or `err`, for `optionmatcher` nodes it is `some` or `none`, and for
+///   `enummatcher` nodes it is the name of an enum variant.  On an edge leaving a node with a
+///   `timeout`, `value=timeout` marks the edge to follow if the node times out (see
+///   [Timeouts](#timeouts)).
+/// * `pattern` -- Used with `value` on edges leaving `matcher` nodes to match the value as a
+///   `glob`, `regex` or `range` instead of exactly (see [`nodetype`: Matcher](nodetype#matcher)).
+/// * `when` -- Used on edges leaving parallel nodes to only follow the edge if a predicate on the
+///   node's output passes (see [Guarded Edges](#guarded-edges)).
+/// * `map` -- Converts the output of the edge's source into the input of its destination with a
+///   function (see [Converting Outputs](#converting-outputs)).
+/// * `max_iterations` -- Limits how many times the edge may be taken in one run of the graph,
+///   overriding the limit set on its source node (see [Bounded Loops](#bounded-loops)).
+/// * `on_exhausted` -- With `on_exhausted=true`, the edge is followed in place of any edge leaving
+///   the same node that has reached its iteration limit (see [Bounded Loops](#bounded-loops)).
+///
+/// # Graph Attributes
+///
+/// Attributes given to the graph itself, in a `graph[...]` statement or as `key=value` statements
+/// at the top of the graph, apply to the whole graph.  Most configure the tokio runtime created by
+/// the `run` method (see [Choosing a Runtime](#choosing-a-runtime)).  Attributes given to
+/// subgraphs don't apply.
+/// * `on_retry` -- A function called before each retry of a node (see [Retries](#retries)).
+/// * `flavor` -- `multi_thread` (default) runs the nodes on a pool of worker threads, and
+///   `current_thread` runs them on the thread calling `run`.
+/// * `worker_threads` -- The number of worker threads of a `multi_thread` runtime.  Defaults to the
+///   number of CPU cores.
+/// * `max_blocking_threads` -- The most threads the runtime spawns to run blocking nodes on.
+/// * `thread_name` -- The name of the threads the runtime spawns.
+///
+/// # Default Attributes
+///
+/// DOT's `node[...]` and `edge[...]` statements set default attributes for the nodes and edges
+/// that follow them, and conflagrate honors them for its own attributes (`type`, `branch`, `start`
+/// and `value`).  As in DOT, defaults set inside a subgraph only apply within that subgraph,
+/// attributes given directly to a node or edge override the defaults, and nodes first mentioned
+/// in an edge pick up the node defaults as well.  Defaults only apply to a node when it's first
+/// mentioned: a later statement for the same node adds its attributes to the ones the node already
+/// has, and the node keeps the edges already leading out of it.
+///
+/// ```ignore
+/// digraph {
+///     node[type=Worker, branch=resultmatcher];
+///     edge[value=ok];
+///
+///     fetch[type=Fetch, start=true];
+///     fetch -> parse -> store;
+///     fetch -> report [value=err];
+/// }
+/// ```
+///
+/// # Guarded Edges
+///
+/// An edge leaving a parallel node can be given a `when` attribute naming a function
+/// `fn(&T) -> bool`, where `T` is the node's output.  The function is called with the output
+/// before the edge is followed, and the edge is skipped if it returns false, so a node can fan out
+/// conditionally without returning a matcher key.  Paths with `::` must be quoted.  A node whose
+/// edges are all guarded ends the graph with its output if none of the guards pass.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn Count(n: u32) -> u32 {
+///     n + 1
+/// }
+///
+/// #[nodetype]
+/// pub fn Notify(n: u32) -> u32 {
+///     println!("Reached a multiple of ten: {}", n);
+///     n
+/// }
+///
+/// fn is_multiple_of_ten(n: &u32) -> bool {
+///     n % 10 == 0
+/// }
+///
+/// fn is_below_limit(n: &u32) -> bool {
+///     *n < 100
+/// }
+///
+/// graph!{
+///     digraph Counter {
+///         count[type=Count, start=true];
+///         notify[type=Notify];
+///         count -> notify [when=is_multiple_of_ten];
+///         count -> count [when=is_below_limit];
+///     }
+/// }
+///
+/// fn main() {
+///     let last = Counter::run(0).unwrap();
+///     println!("Stopped at {}", last);
+/// }
+/// ```
+///
+/// # Joining Branches
+///
+/// Parallel branches normally run independently until each ends.  A node labeled `join=all`
+/// brings them back together: it buffers the outputs arriving on its incoming edges and runs
+/// once it has one from every predecessor, taking all of them as its input.  The node's
+/// `nodetype` either takes one argument per incoming edge, in the order the edges are declared,
+/// or a single `Vec` if all predecessors return the same type.
+///
+/// The node only joins branches split off by the same run of the parallel node they fanned out
+/// from, the closest one all of its inputs pass through.  If that node runs several times at once,
+/// e.g. once for each element of a `foreach` node, each run's branches are joined separately,
+/// whichever order they arrive in.  If a predecessor runs again before the others have caught up,
+/// its outputs are queued and joined in the order they arrived.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub async fn GetNumber() -> u32 {
+///     7
+/// }
+///
+/// #[nodetype]
+/// async fn Square(number: u32) -> u32 {
+///     number * number
+/// }
+///
+/// #[nodetype]
+/// async fn Describe(number: u32) -> String {
+///     format!("{} squared", number)
+/// }
+///
+/// #[nodetype]
+/// pub async fn Report(square: u32, description: String) -> String {
+///     format!("{} is {}", description, square)
+/// }
+///
+/// graph!{
+///     digraph Squares {
+///         get_number[type=GetNumber, start=true];
+///         square[type=Square];
+///         describe[type=Describe];
+///         report[type=Report, join=all];
+///
+///         get_number -> square -> report;
+///         get_number -> describe -> report;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     println!("{}", Squares::run(())?);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Converting Outputs
+///
+/// Normally the input of a node must be exactly the output of the node before it.  Rather than
+/// writing a node just to convert one into the other, give the edge a `map` attribute naming a
+/// function `fn(T) -> U`, where `T` is the output of the edge's source and `U` is the input of its
+/// destination.  The function is called on the output as the edge is followed, without an extra
+/// node or task.  Paths with `::` must be quoted.  Edges between the same two nodes, like two
+/// values of a `matcher` node leading to the same node, must share the same `map` function.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn ReadNumber() -> String {
+///     String::from("42")
+/// }
+///
+/// #[nodetype]
+/// pub fn Square(number: u64) -> u64 {
+///     number * number
+/// }
+///
+/// #[nodetype]
+/// pub fn Print(number: u64) {
+///     println!("{}", number);
+/// }
+///
+/// fn parse(text: String) -> u64 {
+///     text.trim().parse().unwrap_or(0)
+/// }
+///
+/// graph!{
+///     digraph SquareNumber {
+///         read[type=ReadNumber, start=true];
+///         square[type=Square];
+///         print[type=Print];
+///         read -> square [map=parse];
+///         square -> print;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     SquareNumber::run(())
+/// }
+/// ```
+///
+/// # Scatter and Gather
+///
+/// A `foreach` node scatters the elements of its output to its trailing node, which runs once for
+/// each of them.  Without anything else, each element continues along the trailing node's edges
+/// as a branch of its own, and an empty collection ends the branch without an output.  Giving
+/// the `foreach` node `max_concurrent_elements=N` runs at most `N` elements at a time.
+///
+/// To gather the results back together, label the node after the trailing node `gather=true`.
+/// The gather node runs once all the elements have been run, and takes a `Vec` of the trailing
+/// node's outputs in the order of the elements they came from.  The trailing node must be reached
+/// only from `foreach` nodes, and its one edge must lead to the gather node.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn Split(sentence: String) -> Vec<String> {
+///     sentence.split_whitespace().map(String::from).collect()
+/// }
+///
+/// #[nodetype]
+/// pub async fn Measure(word: String) -> usize {
+///     word.len()
+/// }
+///
+/// #[nodetype]
+/// pub fn Report(lengths: Vec<usize>) {
+///     println!("Word lengths: {:?}", lengths);
+/// }
+///
+/// graph!{
+///     digraph WordLengths {
+///         split[type=Split, branch=foreach, max_concurrent_elements=8, start=true];
+///         measure[type=Measure];
+///         report[type=Report, gather=true];
+///         split -> measure -> report;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     WordLengths::run(String::from("the quick brown fox"))
+/// }
+/// ```
+///
+/// # Bounded Loops
+///
+/// An edge back to an earlier node loops for as long as the graph keeps taking it.  To stop a
+/// loop from running forever, give the edge `max_iterations=N`, or give its source node
+/// `max_iterations=N` to limit each of the node's edges.  The count is kept separately for each
+/// run of the graph, and the edge is taken at most `N` times in a run.  The next time it would be
+/// taken, the node's `on_exhausted=true` edge is followed instead, with the same output.  If the
+/// node has no `on_exhausted` edge, the graph ends with a
+/// `GraphError::IterationLimitExceeded` error instead (see [Runtime Errors](#runtime-errors)).
+///
+/// Only edges leaving parallel and matcher nodes can be limited.  The `on_exhausted` edge takes
+/// no `value`, `pattern` or `when`, since it is never followed on its own.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn Guess(attempt: u32) -> (String, u32) {
+///     let correct = attempt * 7 % 10 == 3;
+///     (String::from(if correct {"correct"} else {"wrong"}), attempt + 1)
+/// }
+///
+/// #[nodetype]
+/// pub fn Celebrate(attempts: u32) {
+///     println!("Guessed it after {} attempts", attempts);
+/// }
+///
+/// #[nodetype]
+/// pub fn GiveUp(_attempts: u32) {
+///     println!("Out of guesses");
+/// }
+///
+/// graph!{
+///     digraph Guessing {
+///         guess[type=Guess, branch=matcher, start=true];
+///         celebrate[type=Celebrate];
+///         give_up[type=GiveUp];
+///
+///         guess -> celebrate [value=correct];
+///         guess -> guess [max_iterations=5];
+///         guess -> give_up [on_exhausted=true];
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     Guessing::run(0)
+/// }
+/// ```
+///
+/// # Worker Loops
+///
+/// A loop doesn't need a matcher node to decide when to go around again.  A chain of parallel
+/// nodes that leads back to one of its own nodes, each with a single edge, runs forever, as a
+/// worker that processes one item after another would.  The whole loop runs in one task, which
+/// holds its branch open, so only a failure, like a node panicking, or `cancel()` on the run's
+/// handle ends the graph (see [Cancelling a Run](#cancelling-a-run)).  Give any node of the loop
+/// `yield=true` to yield to the runtime once each time around, letting other tasks run between
+/// iterations of a loop that would otherwise rarely give up its thread.
+///
+/// ```no_run
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn ReadLine() -> String {
+///     let mut line = String::new();
+///     std::io::stdin().read_line(&mut line).unwrap();
+///     line
+/// }
+///
+/// #[nodetype]
+/// pub fn Echo(line: String) {
+///     print!("{}", line);
+/// }
+///
+/// graph!{
+///     digraph EchoWorker {
+///         read[type=ReadLine, start=true];
+///         echo[type=Echo, yield=true];
+///
+///         read -> echo -> read;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     EchoWorker::run(())
+/// }
+/// ```
+///
+/// # Timeouts
+///
+/// A node with a `timeout` attribute is given that long to run.  If it runs out of time, the graph
+/// follows the node's `value=timeout` edge instead, passing the node's input to the edge's
+/// destination, so that a slow call to an external service can fall back to something else.
+/// Without a `value=timeout` edge, the run fails with a `Timeout` error (see
+/// [Runtime Errors](#runtime-errors)).  The edge may have a `map` function to convert the input
+/// for its destination, but no other attributes, and it can't leave a node run by a
+/// `sequential`, `pipe` or `foreach` node.  A node run by a `race` node only follows its
+/// `value=timeout` edge if it's the first to finish the race.
+///
+/// An `async` node that times out is stopped at its current `.await`.  A blocking node can't be
+/// stopped, so it runs to the end of its function in the background while the graph moves on.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub async fn LookUpForecast(city: String) -> String {
+///     // ... a call to a slow weather service
+///     format!("Sunny in {}", city)
+/// }
+///
+/// #[nodetype]
+/// pub fn NoForecast(city: String) -> String {
+///     format!("No forecast for {}", city)
+/// }
+///
+/// #[nodetype]
+/// pub fn Print(forecast: String) {
+///     println!("{}", forecast);
+/// }
+///
+/// graph!{
+///     digraph Forecast {
+///         look_up[type=LookUpForecast, start=true, timeout="500ms"];
+///         no_forecast[type=NoForecast];
+///         print[type=Print];
+///
+///         look_up -> print;
+///         look_up -> no_forecast [value=timeout];
+///         no_forecast -> print;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     Forecast::run(String::from("Paris"))
+/// }
+/// ```
+///
+/// # Retries
+///
+/// A node with a `retry` attribute is run again, with a clone of its input, each time it fails, up
+/// to the given number of retries.  A node fails when it panics, when it runs out of time, or when
+/// it returns an `Err`, whatever its branch.  Only once the node succeeds or runs out of retries is
+/// its output passed on, its `Err` routed, or its failure handled as it would be without retries.
+/// `retry_delay` waits between retries, and `backoff=exponential` doubles the wait with each retry.
+///
+/// Every retry is counted in the
+/// [`NodeMetrics`](https://docs.rs/conflagrate/latest/conflagrate/struct.NodeMetrics.html) of the
+/// run, which the `GraphRunHandle` from `start_graph` gives access to (see
+/// [Cancelling a Run](#cancelling-a-run)).  Nodes can take a `node_metrics` dependency, provided by
+/// `conflagrate::node_metrics`, to read them as well.
+///
+/// To see each retry as it happens, give the graph an `on_retry` attribute (see
+/// [Graph Attributes](#graph-attributes)) with the path of a function taking the node's name, the
+/// number of the retry, starting at 1, and the `GraphError` that failed the attempt before it.  A
+/// returned `Err` is described by `GraphError::NodeReturnedErr`, with the `Err`'s `Debug`
+/// representation if it has one.  The function is called before the `retry_delay`, for every node
+/// of the graph with a `retry`.
+///
+/// ```no_run
+/// # use conflagrate::{graph, nodetype, GraphError};
+/// #[nodetype]
+/// pub async fn Fetch(url: String) -> Result<String, String> {
+///     // ... a request that sometimes fails
+///     Err(format!("couldn't reach {}", url))
+/// }
+///
+/// #[nodetype]
+/// pub fn Print(text: String) {
+///     println!("{}", text);
+/// }
+///
+/// fn log_retry(node: &str, retry: usize, error: &GraphError) {
+///     println!("retry {} of `{}` after: {}", retry, node, error);
+/// }
+///
+/// graph!{
+///     digraph Download {
+///         on_retry=log_retry;
+///         fetch[type=Fetch, start=true, branch=resultmatcher, retry=3, retry_delay="100ms",
+///               backoff=exponential];
+///         print[type=Print];
+///         report[type=Print];
+///
+///         fetch -> print [value=ok];
+///         fetch -> report [value=err];
+///     }
+/// }
+///
+/// fn main() {
+///     let rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(async {
+///         let run = Download::start_graph(String::from("https://example.com"), None);
+///         let metrics = run.metrics().clone();
+///         run.await.unwrap();
+///         println!("retried {} times", metrics.retries("fetch"));
+///     });
+/// }
+/// ```
+///
+/// # Concurrency Limits
+///
+/// Parallel edges, `foreach` nodes and loops can start any number of invocations of the same node
+/// at once.  A node with `max_concurrency=N` runs at most `N` invocations at a time, and the rest
+/// wait their turn in the order they arrived.  The limit is shared by every run of the graph, so
+/// graphs started concurrently with `start_graph` or `run_graph` queue for the same slots.  A slot
+/// is taken once the node's input is ready and held for one attempt of the node, including any
+/// `timeout`, so a node waiting to `retry` gives up its slot.
+///
+/// To limit a node type wherever it's used, in any graph, give the limit to the
+/// [`nodetype`](macro@nodetype) instead (see
+/// [`nodetype`: Concurrency Limits](nodetype#concurrency-limits)).  On a `foreach` node,
+/// `max_concurrency` limits the `foreach` node itself; its `max_concurrent_elements` limits how many
+/// of its elements run at once within one run (see [Scatter and Gather](#scatter-and-gather)).
+///
+/// ```no_run
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn Users() -> Vec<u32> {
+///     (0..100).collect()
+/// }
+///
+/// #[nodetype]
+/// pub async fn Lookup(id: u32) -> String {
+///     // ... a query the database can only take a few of at once
+///     format!("user {}", id)
+/// }
+///
+/// #[nodetype]
+/// pub fn Collect(names: Vec<String>) -> usize {
+///     names.len()
+/// }
+///
+/// graph!{
+///     digraph Report {
+///         users[type=Users, start=true, branch=foreach];
+///         lookup[type=Lookup, max_concurrency=4];
+///         collect[type=Collect, gather=true];
+///
+///         users -> lookup;
+///         lookup -> collect;
+///     }
+/// }
+///
+/// fn main() {
+///     let rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(async {
+///         // Both runs share the four slots of `lookup`.
+///         let (first, second) = tokio::join!(
+///             Report::run_graph((), None),
+///             Report::run_graph((), None)
+///         );
+///         assert_eq!(first.unwrap() + second.unwrap(), 200);
+///     });
+/// }
+/// ```
+///
+/// # Choosing a Runtime
+///
+/// The `run` method creates a tokio runtime, runs the graph on it until the graph ends, and shuts
+/// the runtime down again.  Which runtime it creates is set by the graph's attributes (see
+/// [Graph Attributes](#graph-attributes)) and given by the graph's `runtime_config()` method as a
+/// [`RuntimeConfig`](https://docs.rs/conflagrate/latest/conflagrate/struct.RuntimeConfig.html).
+/// `run_with` takes the `RuntimeConfig` to use as an argument instead, so the runtime can be
+/// chosen when the program starts.
+///
+/// To run the graph on a runtime that already exists, without creating another, give `run_on`
+/// the runtime's `tokio::runtime::Handle`.  Like `run`, it blocks until the graph ends, so it must
+/// be called from outside the runtime.  The nodes only make progress while the runtime's threads
+/// are running, which a `multi_thread` runtime's always are, but a `current_thread` runtime's
+/// thread only is while it's inside its own `block_on`.  From inside a runtime, use `run_graph`
+/// or `start_graph` instead.
+///
+/// ```no_run
+/// # use conflagrate::{graph, nodetype, RuntimeConfig};
+/// #[nodetype]
+/// pub fn Greet(name: String) -> String {
+///     format!("Hello, {}!", name)
+/// }
+///
+/// graph!{
+///     digraph Greeter {
+///         graph[flavor=multi_thread, worker_threads=2, thread_name="greeter"];
+///
+///         greet[type=Greet, start=true];
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     // On a runtime with two worker threads named "greeter", as set by the graph.
+///     println!("{}", Greeter::run(String::from("graph"))?);
+///
+///     // On a runtime chosen here instead.
+///     let config = RuntimeConfig::current_thread().max_blocking_threads(4);
+///     println!("{}", Greeter::run_with(config, String::from("again"))?);
+///
+///     // On a runtime that's already running.
+///     let rt = tokio::runtime::Runtime::new().unwrap();
+///     println!("{}", Greeter::run_on(rt.handle(), String::from("once more"))?);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Entry Points
+///
+/// A graph can be started from more than one node.  Each node labeled `start=<name>` adds
+/// `run_<name>`, `run_with_<name>`, `run_on_<name>`, `run_graph_<name>`, and `start_graph_<name>`
+/// methods to the graph that behave like `run`, `run_with`, `run_on`, `run_graph`, and
+/// `start_graph`, but start from that node and take that node's input arguments.  Entry point names
+/// must be unique within the graph, and can't generate a method another entry point already has
+/// (`start=graph` or `start=with` next to a `start=true` node would add a second `run_graph` or
+/// `run_with`).  A graph may have named entry points with or without a default `start=true`
+/// entry point.
+///
+/// ```
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn ParseNumber(text: String) -> u32 {
+///     text.trim().parse().unwrap_or(0)
+/// }
+///
+/// #[nodetype]
+/// pub fn Double(number: u32) -> u32 {
+///     number * 2
+/// }
+///
+/// graph!{
+///     digraph Doubler {
+///         parse[type=ParseNumber, start=true];
+///         double[type=Double, start=from_number];
+///
+///         parse -> double;
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     assert_eq!(Doubler::run(String::from("21"))?, 42);
+///     assert_eq!(Doubler::run_from_number(21)?, 42);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Subgraphs and Clusters
+///
+/// Nodes declared inside a `subgraph` are part of the executable graph like any other node, which
+/// allows large graphs to be organized into sections.  A subgraph can also be used as either end
+/// of an edge:
+/// * An edge into a subgraph fans out in parallel to every node in the subgraph.
+/// * An edge out of a subgraph fans in from the subgraph's exits, the nodes in it that have no
+///   edges to other nodes of the same subgraph.
+///
+/// Subgraphs sharing a name are the same subgraph, so an empty `subgraph name {}` refers to all
+/// the nodes declared in the subgraph elsewhere.  Subgraphs whose names start with `cluster` also
+/// prefix the rest of their name to the names of the generated tasks of the nodes declared in
+/// them (e.g. node `parse` in `cluster_ingest` runs as `execute_ingest_parse`), so the generated
+/// code stays free of collisions as sections are added.
+///
+/// ```ignore
+/// digraph {
+///     receive[type=Receive, start=true];
+///     subgraph cluster_ingest {
+///         node[type=Ingest];
+///         parse -> store;
+///     }
+///     respond[type=Respond];
+///
+///     receive -> subgraph cluster_ingest {} -> respond;
+/// }
+/// ```
+///
+/// # Compile Errors
+///
+/// Mistakes in the graph definition, such as DOT syntax errors, a missing `start` node, or edge
+/// values a node's branching behavior doesn't support, are reported as compile errors pointing at
+/// the offending node, edge or attribute.  All problems found in the graph are reported together.
+///
+/// Once parsed, the graph is also checked as a whole.  The following are compile errors:
+/// * Nodes that can't be reached from any `start` node.
+/// * Edges to or from nodes that have no `type`, or that are never declared.
+/// * More than one node labeled with `start=true`, or with the same `start=<name>`, or entry
+///   points that generate methods with the same name.
+/// * A `value` attribute on an edge leaving a node that doesn't use a matcher branch, other than
+///   the `value=timeout` edge of a node with a `timeout`, or a `pattern` attribute on an edge
+///   leaving a node that isn't a `matcher`.
+/// * An invalid `regex` or `range` pattern.
+/// * A `when` attribute that isn't a path, or on an edge leaving a node that uses a matcher branch.
+/// * A `map` attribute that isn't a path, or edges between the same two nodes with different
+///   `map` functions.
+/// * An `on_retry` graph attribute that isn't a path.
+/// * Two edges with the same `value` leaving a matcher node without `fanout=true`.
+/// * An unknown `branch` or `join` value.
+/// * A `join=all` node with edges from fewer than two nodes.
+/// * An edge from a `race`, `sequential` or `pipe` node to a `join=all` node.
+/// * A `foreach` node without exactly one edge, or a `max_concurrent_elements` attribute on any
+///   other node.
+/// * A `gather=true` node that doesn't directly follow a node run by `foreach` nodes.
+/// * A `max_iterations` attribute on a `race`, `sequential`, `pipe` or `foreach` node or its
+///   edges, or on an edge into a `gather=true` node.
+/// * An `on_exhausted` edge leaving a node none of whose edges has `max_iterations`, more than one
+///   `on_exhausted` edge leaving the same node, or an `on_exhausted` edge with `max_iterations`,
+///   `value`, `pattern` or `when`.
+/// * A `timeout` or `retry_delay` that isn't a positive duration in `ms`, `s`, `m` or `h`.
+/// * A `retry` that isn't a positive integer, an unknown `backoff`, a `retry_delay` or `backoff`
+///   on a node without `retry`, or `backoff=exponential` without a `retry_delay`.
+/// * An unknown `flavor`, a `worker_threads` or `max_blocking_threads` that isn't a positive
+///   integer, or `worker_threads` with `flavor=current_thread`.
+/// * More than one `value=timeout` edge leaving the same node, a `value=timeout` edge with
+///   `pattern`, `when`, `max_iterations` or `on_exhausted`, one leaving a node run by a
+///   `sequential`, `pipe` or `foreach` node, or one leading to a node that gathers or is gathered.
+///
+/// # Output Type
+///
+/// The output of the graph is the output of whichever node ends it.  Nodes with no trailing
+/// edges, parallel nodes whose edges all have `when` guards, `matcher` nodes without a default
+/// edge, and `resultmatcher` and `optionmatcher` nodes missing edges for a variant can all end the
+/// graph, so they must all end it with the same type.  The graph's output type is taken from the
+/// first of these nodes declared in the graph, and any other node ending the graph with a
+/// different type is reported as a compile error at its declaration.
+///
+/// # Runtime Errors
+///
+/// The `run` and `run_graph` methods return a `Result`, with the graph's output if it finishes,
+/// or a [`GraphError`](https://docs.rs/conflagrate/latest/conflagrate/enum.GraphError.html) if
+/// it fails:
+/// * `NodePanicked` -- A node panicked.  The error names the node and its `nodetype`, and holds
+///   the value it panicked with.
+/// * `NodeFailed` -- The runtime couldn't run a blocking node to completion, as when the runtime
+///   shuts down while the node is running.  The error holds the node's `JoinError`.
+/// * `Timeout` -- A node ran out of time, and it has no `value=timeout` edge (see
+///   [Timeouts](#timeouts)).  The error names the node and its `nodetype`, and holds its `timeout`.
+/// * `IterationLimitExceeded` -- An edge reached its `max_iterations` limit, and its source has no
+///   `on_exhausted` edge (see [Bounded Loops](#bounded-loops)).
+/// * `NoOutput` -- Every branch ended without an output, as when a `foreach` node scatters an
+///   empty collection.
+/// * `Cancelled` -- The run was cancelled (see [Cancelling a Run](#cancelling-a-run)).
+/// * `RuntimeFailed` -- The `run` or `run_with` method couldn't build its runtime (see
+///   [Choosing a Runtime](#choosing-a-runtime)).  The error holds the `std::io::Error` from tokio.
+///
+/// The first error ends the graph, however many other branches are still running, and their
+/// outputs are ignored.  `GraphError` implements `std::error::Error`, so `main` can return it:
+/// ```no_run
+/// # use conflagrate::{graph, nodetype};
+/// #[nodetype]
+/// pub fn ParseAge(text: String) -> u8 {
+///     text.trim().parse().expect("not an age")
+/// }
+///
+/// graph!{
+///     digraph Age {
+///         parse[type=ParseAge, start=true];
+///     }
+/// }
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     let age = Age::run(String::from("forty"))?;
+///     println!("{}", age);
+///     Ok(())
+/// }
+/// ```
+///
+/// # Cancelling a Run
+///
+/// The `start_graph` method (and `start_graph_<name>` for a named entry point) starts a run of the
+/// graph on the current tokio runtime and returns a
+/// [`GraphRunHandle`](https://docs.rs/conflagrate/latest/conflagrate/struct.GraphRunHandle.html).
+/// Awaiting the handle gives the run's output, as `run_graph` does.  Calling `cancel()` on it
+/// aborts the tasks of every node still running and keeps any more from being started, and the
+/// handle then gives a `Cancelled` error.  Dropping the handle, or a `run_graph` future, before the
+/// run ends cancels the run too.
+///
+/// Aborting a task stops a node at its next `.await`, so a node that works for a long time between
+/// `.await`s should watch for the cancellation itself.  Nodes can take a `cancellation` dependency,
+/// provided by `conflagrate::cancellation`, that gives the run's
+/// [`CancellationToken`](https://docs.rs/conflagrate/latest/conflagrate/struct.CancellationToken.html):
+/// ```no_run
+/// # use conflagrate::{cancellation, graph, nodetype, CancellationToken, GraphError};
+/// #[nodetype]
+/// pub async fn Work(job: u32, cancellation: &CancellationToken) -> u32 {
+///     for _step in 0..1000 {
+///         if cancellation.is_cancelled() {
+///             break;
+///         }
+///         // ...
+///     }
+///     job + 1
+/// }
+///
+/// graph!{
+///     digraph Worker {
+///         work[type=Work, start=true];
+///
+///         work -> work;
+///     }
+/// }
+///
+/// fn main() {
+///     let rt = tokio::runtime::Runtime::new().unwrap();
+///     rt.block_on(async {
+///         let run = Worker::start_graph(0, None);
+///         // ...
+///         run.cancel();
+///         assert!(matches!(run.await, Err(GraphError::Cancelled)));
+///     });
+/// }
+/// ```
 ///
 /// # Examples
 ///
@@ -329,8 +1304,8 @@ pub fn nodetype(_: TokenStream, func: TokenStream) -> TokenStream {
 ///     }
 /// }
 ///
-/// fn main() {
-///     Graph::run(());
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     Graph::run(())
 /// }
 /// ```
 ///
@@ -361,7 +1336,7 @@ pub fn nodetype(_: TokenStream, func: TokenStream) -> TokenStream {
 ///     }
 /// }
 ///
-/// fn main() {
+/// fn main() -> Result<(), conflagrate::GraphError> {
 ///     Loop::run(())
 /// }
 /// ```
@@ -376,8 +1351,64 @@ pub fn nodetype(_: TokenStream, func: TokenStream) -> TokenStream {
 ///
 /// * [`nodetype`](macro@nodetype) -- Macro associating functions with nodes.
 /// * [`dependency`](macro@nodetype) -- Macro associating a function providing a resource with the
-/// name of the function, providing that resource to `nodetype`s that reference them.
+///   name of the function, providing that resource to `nodetype`s that reference them.
+/// * [`graph_file`](macro@graph_file) -- Same as `graph`, but reads the graph from a `.dot` file.
 #[proc_macro]
 pub fn graph(graph: TokenStream) -> TokenStream {
-    TokenStream::from(graph_impl(graph.to_string()))
+    TokenStream::from(graph_tokens_impl(graph.into()))
+}
+
+/// Defines the control flow graph of an application from an external DOT file.
+///
+/// Behaves exactly like [`graph`](macro@graph), except the graph definition is read at compile
+/// time from the file at the given path instead of being written inline.  The path is resolved
+/// relative to the `CARGO_MANIFEST_DIR` of the crate invoking the macro, so graphs can be kept
+/// alongside the code in files that can be opened and edited with the usual Graphviz tools.
+///
+/// The file is tracked by the compiler, so editing it triggers a rebuild, and its contents are
+/// available in the generated `SOURCE` constant just as with an inline graph.
+///
+/// # Examples
+///
+/// Given a file `flows/greeting.dot` in the crate root containing:
+/// ```dot
+/// digraph GreetingGraph {
+///     get_name[type=GetName, start=true];
+///     print_name[type=PrintGreeting];
+///
+///     get_name -> print_name;
+/// }
+/// ```
+///
+/// The graph can be built with:
+/// ```ignore
+/// # use conflagrate::{graph_file, nodetype};
+/// #[nodetype]
+/// pub fn GetName() -> String {
+///    let mut name = String::new();
+///    println!("Hello, what is your name?");
+///    std::io::stdin().read_line(&mut name).unwrap();
+///    name.truncate(name.len() - 1);
+///    name
+/// }
+///
+/// #[nodetype]
+/// pub fn PrintGreeting(name: String) {
+///     println!("Hello, {}!", name)
+/// }
+///
+/// graph_file!("flows/greeting.dot");
+///
+/// fn main() -> Result<(), conflagrate::GraphError> {
+///     GreetingGraph::run(())
+/// }
+/// ```
+///
+/// # See Also
+///
+/// * [`graph`](macro@graph) -- Macro defining a graph inline, including the supported node and
+///   edge attributes.
+#[proc_macro]
+pub fn graph_file(path: TokenStream) -> TokenStream {
+    TokenStream::from(graph_file_impl(parse_macro_input!(path as LitStr)))
 }
@@ -1,11 +1,53 @@
 use proc_macro2::{Span, TokenStream};
 use quote::{quote, ToTokens};
-use syn::{Block, FnArg, Ident, ItemFn, Pat, PatType, ReturnType, Type};
+use syn::{
+    AttributeArgs, Block, FnArg, Ident, ItemFn, Lit, Meta, NestedMeta, Pat, PatType, ReturnType,
+    Type
+};
 use syn::punctuated::Punctuated;
 use syn::token::Comma;
 
 use crate::funcutils::create_dependency_injection_statements;
 
+const MAX_CONCURRENCY_OPT: &str = "max_concurrency";
+
+/// The options of the `nodetype` attribute, as in `#[nodetype(max_concurrency = 4)]`.
+#[derive(Default)]
+pub struct NodeTypeOptions {
+    max_concurrency: Option<usize>,
+}
+impl NodeTypeOptions {
+    pub fn from_args(args: AttributeArgs) -> syn::Result<Self> {
+        let mut options = Self::default();
+        for arg in args {
+            match &arg {
+                NestedMeta::Meta(Meta::NameValue(option))
+                    if option.path.is_ident(MAX_CONCURRENCY_OPT) =>
+                {
+                    let max_concurrency = match &option.lit {
+                        Lit::Int(value) => value.base10_parse::<usize>().ok(),
+                        _ => None,
+                    };
+                    match max_concurrency {
+                        Some(max_concurrency) if max_concurrency > 0 => {
+                            options.max_concurrency = Some(max_concurrency);
+                        },
+                        _ => return Err(syn::Error::new_spanned(
+                            &option.lit,
+                            format!("`{}` must be a positive integer", MAX_CONCURRENCY_OPT)
+                        )),
+                    }
+                },
+                _ => return Err(syn::Error::new_spanned(
+                    &arg,
+                    format!("unknown nodetype option, expected `{} = N`", MAX_CONCURRENCY_OPT)
+                )),
+            }
+        }
+        Ok(options)
+    }
+}
+
 /// Splits the node function signature inputs into a list of outputs-turn-inputs from the
 /// previous node and a set of dependencies to pull from the dependency cache (dependency
 /// injection).  Which goes into which is determined by ownership: if the function owns the input,
@@ -67,14 +109,13 @@ fn output_to_output_type(
 }
 
 fn determine_if_blocking(func_ast: &ItemFn) -> bool {
-    match func_ast.sig.asyncness {
-        Some(_) => false,
-        None => true,
-    }
+    func_ast.sig.asyncness.is_none()
 }
 
-fn create_codeblock(is_blocking: bool, deps: Vec<PatType>, code: &Box<Block>) -> TokenStream {
+fn create_codeblock(is_blocking: bool, deps: Vec<PatType>, code: &Block) -> TokenStream {
     let code = combine_dep_injection_with_node_code(deps, code);
+    // A panic in a blocking node is passed on with its `JoinError`, which the graph unwraps into
+    // the original panic when it reports the node's failure.
     if is_blocking {
         quote! {
         {
@@ -82,22 +123,37 @@ fn create_codeblock(is_blocking: bool, deps: Vec<PatType>, code: &Box<Block>) ->
             {
                 #code
             }
-            ).await.unwrap()
+            ).await.unwrap_or_else(|error| std::panic::resume_unwind(Box::new(error)))
         }}
     } else {
         quote!{{#code}}
     }
 }
 
-fn code_to_tokenstream(code: &Box<Block>) -> TokenStream {
+/// Holds a permit of the nodetype's concurrency limit while the node runs.  The limit is a static
+/// of the `run` method, so it's shared by every node of the type, in every graph.
+fn limit_codeblock(max_concurrency: Option<usize>, code: TokenStream) -> TokenStream {
+    match max_concurrency {
+        Some(max_concurrency) => quote! {
+        {
+            static CONCURRENCY_LIMIT: conflagrate::ConcurrencyLimit =
+                conflagrate::ConcurrencyLimit::new(#max_concurrency);
+            let _permit = CONCURRENCY_LIMIT.acquire().await;
+            #code
+        }},
+        None => code,
+    }
+}
+
+fn code_to_tokenstream(code: &Block) -> TokenStream {
     let mut out = TokenStream::new();
-    for stmt in (*code).stmts.iter() {
+    for stmt in code.stmts.iter() {
         out.extend(quote!{#stmt})
     }
     out
 }
 
-fn combine_dep_injection_with_node_code(deps: Vec<PatType>, code: &Box<Block>) -> TokenStream {
+fn combine_dep_injection_with_node_code(deps: Vec<PatType>, code: &Block) -> TokenStream {
     let mut block = create_dependency_injection_statements(deps);
     block.extend(code_to_tokenstream(code));
     block
@@ -109,15 +165,16 @@ fn create_test_method(func_ast: &ItemFn) -> ItemFn {
     test_method
 }
 
-pub fn nodetype_impl(func_ast: ItemFn) -> TokenStream {
+pub fn nodetype_impl(func_ast: ItemFn, options: NodeTypeOptions) -> TokenStream {
     let is_blocking = determine_if_blocking(&func_ast);
     let vis = &func_ast.vis;
     let name = &func_ast.sig.ident;
     let inputs = &func_ast.sig.inputs;
-    let (input_type, deps) = args_to_inputs_and_deps(&inputs);
-    let input_names = inputs_to_names(&inputs);
+    let (input_type, deps) = args_to_inputs_and_deps(inputs);
+    let input_names = inputs_to_names(inputs);
     let output = output_to_output_type(&func_ast.sig.output);
     let code = create_codeblock(is_blocking, deps, &func_ast.block);
+    let code = limit_codeblock(options.max_concurrency, code);
     let test_method = create_test_method(&func_ast);
     quote! {
         #vis struct #name {}
@@ -129,6 +186,7 @@ pub fn nodetype_impl(func_ast: ItemFn) -> TokenStream {
             #code
         }
         impl #name {
+           #[allow(dead_code)]
            #test_method
         }
     }
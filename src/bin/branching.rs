@@ -38,6 +38,6 @@ digraph {
 
 }
 
-fn main() {
+fn main() -> Result<(), conflagrate::GraphError> {
     Graph::run(())
 }
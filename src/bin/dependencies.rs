@@ -44,6 +44,6 @@ graph! {
     }
 }
 
-fn main() {
-    MemoryEcho::run(());
+fn main() -> Result<(), conflagrate::GraphError> {
+    MemoryEcho::run(())
 }
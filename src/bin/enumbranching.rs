@@ -0,0 +1,52 @@
+use conflagrate::{graph, nodetype};
+
+pub enum Shape {
+    Circle(f64),
+    Square(f64),
+    Unknown(String),
+}
+
+#[nodetype]
+pub fn GetShape() -> Shape {
+    let mut input = String::new();
+    println!("Enter a shape and a size (e.g. 'circle 2'):");
+    std::io::stdin().read_line(&mut input).unwrap();
+    input.truncate(input.len() - 1);
+    match input.split_once(' ') {
+        Some(("circle", radius)) => Shape::Circle(radius.parse().unwrap_or(0.0)),
+        Some(("square", side)) => Shape::Square(side.parse().unwrap_or(0.0)),
+        _ => Shape::Unknown(input),
+    }
+}
+
+#[nodetype]
+pub async fn CircleArea(radius: f64) {
+    println!("The circle's area is {:.2}", std::f64::consts::PI * radius * radius);
+}
+
+#[nodetype]
+pub async fn SquareArea(side: f64) {
+    println!("The square's area is {:.2}", side * side);
+}
+
+#[nodetype]
+pub async fn UnknownShape(input: String) {
+    println!("Whoops!  Unexpected input: '{}'", input);
+}
+
+graph!{
+digraph {
+    get_shape[label="Get Shape", type=GetShape, branch=enummatcher, start=true];
+    circle[label="Circle Area", type=CircleArea];
+    square[label="Square Area", type=SquareArea];
+    unknown[label="Unknown Shape", type=UnknownShape];
+
+    get_shape -> circle [label="Circle", value=Circle];
+    get_shape -> square [label="Square", value=Square];
+    get_shape -> unknown [label="Unknown", value=Unknown];
+}
+}
+
+fn main() -> Result<(), conflagrate::GraphError> {
+    Graph::run(())
+}
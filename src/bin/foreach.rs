@@ -0,0 +1,38 @@
+use conflagrate::{graph, nodetype};
+
+#[nodetype]
+pub fn GetWords() -> Vec<String> {
+    let mut words = String::new();
+    println!("Type a few words:");
+    std::io::stdin().read_line(&mut words).unwrap();
+    words.split_whitespace().map(String::from).collect()
+}
+
+#[nodetype]
+pub async fn Capitalize(word: String) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => word,
+    }
+}
+
+#[nodetype]
+pub async fn PrintWords(words: Vec<String>) {
+    println!("{}", words.join(" "));
+}
+
+graph!{
+digraph {
+    get_words[label="Get Words", type=GetWords, branch=foreach, max_concurrent_elements=4,
+              start=true];
+    capitalize[label="Capitalize", type=Capitalize];
+    print_words[label="Print Words", type=PrintWords, gather=true];
+
+    get_words -> capitalize -> print_words;
+}
+}
+
+fn main() -> Result<(), conflagrate::GraphError> {
+    Graph::run(())
+}
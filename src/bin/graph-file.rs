@@ -0,0 +1,21 @@
+use conflagrate::{graph_file, nodetype};
+
+#[nodetype]
+pub fn StartNodeType() -> String {
+    let mut name = String::new();
+    println!("Hello, what is your name?");
+    std::io::stdin().read_line(&mut name).unwrap();
+    name.truncate(name.len() - 1);
+    name
+}
+
+#[nodetype]
+pub async fn GreetingNodeType(name: String) {
+    println!("Welcome {}!", name)
+}
+
+graph_file!("src/bin/graph-file.dot");
+
+fn main() -> Result<(), conflagrate::GraphError> {
+    GraphFile::run(())
+}
@@ -24,6 +24,6 @@ strict digraph {
 
 }
 
-fn main() {
+fn main() -> Result<(), conflagrate::GraphError> {
     Graph::run(())
 }
@@ -0,0 +1,41 @@
+use conflagrate::{graph, nodetype};
+
+#[nodetype]
+pub fn GetName() -> String {
+    let mut name = String::new();
+    println!("Hello, what is your name?");
+    std::io::stdin().read_line(&mut name).unwrap();
+    name.truncate(name.len() - 1);
+    name
+}
+
+#[nodetype]
+pub async fn CountLetters(name: String) -> usize {
+    name.chars().filter(|c| c.is_alphabetic()).count()
+}
+
+#[nodetype]
+pub async fn Reverse(name: String) -> String {
+    name.chars().rev().collect()
+}
+
+#[nodetype]
+pub async fn Summarize(letters: usize, reversed: String) {
+    println!("Your name has {} letters and reads \"{}\" backwards.", letters, reversed);
+}
+
+graph!{
+digraph {
+    get_name[label="Get Name", type=GetName, start=true];
+    count_letters[label="Count Letters", type=CountLetters];
+    reverse[label="Reverse", type=Reverse];
+    summarize[label="Summarize", type=Summarize, join=all];
+
+    get_name -> count_letters -> summarize;
+    get_name -> reverse -> summarize;
+}
+}
+
+fn main() -> Result<(), conflagrate::GraphError> {
+    Graph::run(())
+}
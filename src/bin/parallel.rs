@@ -29,6 +29,6 @@ strict digraph {
 }
 }
 
-fn main() {
+fn main() -> Result<(), conflagrate::GraphError> {
     Graph::run(())
 }
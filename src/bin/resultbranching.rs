@@ -1,6 +1,7 @@
 use conflagrate::{graph, nodetype};
 
 #[nodetype]
+#[allow(clippy::result_unit_err)]
 pub fn GetInput() -> Result<(), ()> {
     let mut input = String::new();
     println!("Please type 'success':");
@@ -41,6 +42,6 @@ digraph {
 
 }
 
-fn main() {
+fn main() -> Result<(), conflagrate::GraphError> {
     Graph::run(())
 }
@@ -1,20 +1,28 @@
+use std::any::Any;
+use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicI32};
 use std::sync::atomic::Ordering::Relaxed;
 use tokio::sync::oneshot;
 use tokio::sync::oneshot::{Receiver, Sender};
+use crate::grapherror::GraphError;
+use crate::join::JoinBuffer;
 
 pub struct BranchTracker<T> {
     num_branches: AtomicI32,
-    sender: Option<Sender<T>>,
+    sender: Option<Sender<Result<T, GraphError>>>,
     done: AtomicBool,
+    joins: HashMap<(&'static str, Option<u64>), JoinBuffer>,
+    iterations: HashMap<&'static str, usize>,
 }
 impl<T> BranchTracker<T> {
-    pub fn new() -> (Receiver<T>, BranchTracker<T>) {
+    pub fn new() -> (Receiver<Result<T, GraphError>>, BranchTracker<T>) {
         let (sender, receiver) = oneshot::channel();
         (receiver, BranchTracker{
             num_branches: AtomicI32::new(1),
             sender: Some(sender),
-            done: AtomicBool::new(false)
+            done: AtomicBool::new(false),
+            joins: HashMap::new(),
+            iterations: HashMap::new(),
         })
     }
 
@@ -29,10 +37,137 @@ impl<T> BranchTracker<T> {
         if self.num_branches.load(Relaxed) <= 0 {
             self.done.store(true, Relaxed);
             if let Some(sender) = self.sender.take() {
-                match sender.send(last_node_output) {
-                    _ => {}
-                }
+                let _ = sender.send(Ok(last_node_output));
             }
         }
     }
+
+    /// Ends a branch without providing the graph's output, as when a branch's output is buffered
+    /// by a join node to be continued by another branch.
+    ///
+    /// If this was the last branch, the graph ends without an output.
+    pub fn drop_branch(&mut self) {
+        if self.done.load(Relaxed) { return; }
+        self.num_branches.fetch_add(-1, Relaxed);
+        if self.num_branches.load(Relaxed) <= 0 {
+            self.done.store(true, Relaxed);
+            self.sender.take();
+        }
+    }
+
+    /// Ends the graph with an error instead of an output, however many branches are still
+    /// running, as when a node panics.
+    pub fn fail(&mut self, error: GraphError) {
+        if self.done.load(Relaxed) { return; }
+        self.done.store(true, Relaxed);
+        if let Some(sender) = self.sender.take() {
+            let _ = sender.send(Err(error));
+        }
+    }
+
+    /// Counts another time an edge with an iteration limit is taken in this run of the graph,
+    /// returning false once it has been taken more than `max_iterations` times.
+    pub fn take_edge(&mut self, edge: &'static str, max_iterations: usize) -> bool {
+        let iterations = self.iterations.entry(edge).or_insert(0);
+        *iterations += 1;
+        *iterations <= max_iterations
+    }
+
+    /// Buffers the output arriving at one incoming edge of a join node, returning the outputs of
+    /// all of the join node's incoming edges once every one of them has arrived.
+    ///
+    /// Outputs are only joined with others from the same `instance` of the fan-out they descend
+    /// from, if the join node has one.
+    pub fn join(
+        &mut self,
+        node: &'static str,
+        instance: Option<u64>,
+        slot: usize,
+        num_slots: usize,
+        value: Box<dyn Any + Send>
+    ) -> Option<Vec<Box<dyn Any + Send>>> {
+        let buffer = self.joins.entry((node, instance))
+            .or_insert_with(|| JoinBuffer::new(num_slots));
+        let joined = buffer.push(slot, value);
+        if buffer.is_empty() {
+            self.joins.remove(&(node, instance));
+        }
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn last_branch_sends_its_output() {
+        let (mut receiver, mut tracker) = BranchTracker::<u32>::new();
+        tracker.add_branch();
+        tracker.remove_branch(1);
+        assert!(receiver.try_recv().is_err());
+        tracker.remove_branch(2);
+        assert_eq!(receiver.try_recv().unwrap().unwrap(), 2);
+    }
+
+    #[test]
+    fn dropping_the_last_branch_ends_without_output() {
+        let (mut receiver, mut tracker) = BranchTracker::<u32>::new();
+        tracker.add_branch();
+        tracker.remove_branch(1);
+        tracker.drop_branch();
+        assert!(matches!(receiver.try_recv(), Err(oneshot::error::TryRecvError::Closed)));
+    }
+
+    #[test]
+    fn dropped_branch_leaves_the_others_running() {
+        let (mut receiver, mut tracker) = BranchTracker::<u32>::new();
+        tracker.add_branch();
+        tracker.drop_branch();
+        assert!(matches!(receiver.try_recv(), Err(oneshot::error::TryRecvError::Empty)));
+        tracker.remove_branch(3);
+        assert_eq!(receiver.try_recv().unwrap().unwrap(), 3);
+    }
+
+    #[test]
+    fn failing_ends_the_graph_with_the_error() {
+        let (mut receiver, mut tracker) = BranchTracker::<u32>::new();
+        tracker.add_branch();
+        tracker.fail(GraphError::NoOutput);
+        assert!(matches!(receiver.try_recv().unwrap(), Err(GraphError::NoOutput)));
+    }
+
+    #[test]
+    fn branches_ending_after_a_failure_are_ignored() {
+        let (mut receiver, mut tracker) = BranchTracker::<u32>::new();
+        tracker.add_branch();
+        tracker.fail(GraphError::IterationLimitExceeded { edge: "a -> a", max_iterations: 1 });
+        tracker.fail(GraphError::NoOutput);
+        tracker.remove_branch(1);
+        tracker.drop_branch();
+        tracker.add_branch();
+        let result = receiver.try_recv().unwrap();
+        assert!(matches!(result, Err(GraphError::IterationLimitExceeded { .. })));
+    }
+
+    #[test]
+    fn edges_are_taken_up_to_their_limit() {
+        let (_receiver, mut tracker) = BranchTracker::<u32>::new();
+        assert!(tracker.take_edge("a -> b", 2));
+        assert!(tracker.take_edge("a -> b", 2));
+        assert!(!tracker.take_edge("a -> b", 2));
+        assert!(tracker.take_edge("b -> a", 2));
+    }
+
+    #[test]
+    fn joins_outputs_of_the_same_fan_out_instance() {
+        let (_receiver, mut tracker) = BranchTracker::<u32>::new();
+        assert!(tracker.join("c", Some(1), 0, 2, Box::new(10u32)).is_none());
+        assert!(tracker.join("c", Some(2), 0, 2, Box::new(20u32)).is_none());
+        let joined = tracker.join("c", Some(2), 1, 2, Box::new(21u32)).unwrap();
+        let joined: Vec<u32> = joined.into_iter().map(|value| *value.downcast().unwrap()).collect();
+        assert_eq!(joined, vec![20, 21]);
+        assert!(tracker.join("c", Some(1), 1, 2, Box::new(11u32)).is_some());
+        assert!(tracker.joins.is_empty());
+    }
 }
@@ -0,0 +1,105 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering::SeqCst;
+use tokio::sync::Notify;
+use tokio::task::{AbortHandle, JoinHandle};
+use crate::DependencyCache;
+
+/// Signals that a run of a graph has been cancelled.
+///
+/// Every run of a graph has its own token, which the run's [`GraphRunHandle`] cancels.  Nodes can
+/// observe it by taking a `cancellation: &CancellationToken` dependency, with the [`cancellation`]
+/// dependency provider in scope.  Clones of a token share its state.
+///
+/// [`GraphRunHandle`]: crate::GraphRunHandle
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    notify: Notify,
+    tasks: Mutex<Vec<AbortHandle>>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the token, aborting every task spawned with it that's still running.
+    pub fn cancel(&self) {
+        let mut tasks = self.0.tasks.lock().unwrap();
+        self.0.cancelled.store(true, SeqCst);
+        self.0.notify.notify_waiters();
+        for task in tasks.drain(..) {
+            task.abort();
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(SeqCst)
+    }
+
+    /// Waits until the token is cancelled.
+    pub async fn cancelled(&self) {
+        let notified = self.0.notify.notified();
+        if !self.is_cancelled() {
+            notified.await;
+        }
+    }
+
+    /// Spawns a task that's aborted when the token is cancelled.  If the token has already been
+    /// cancelled, the task is aborted before it can run.
+    #[doc(hidden)]
+    pub fn spawn<F>(&self, task: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let handle = tokio::spawn(task);
+        let mut tasks = self.0.tasks.lock().unwrap();
+        if self.is_cancelled() {
+            handle.abort();
+        } else {
+            tasks.retain(|task| !task.is_finished());
+            tasks.push(handle.abort_handle());
+        }
+        handle
+    }
+}
+
+/// Provides the [`CancellationToken`] of the running graph to nodes that take a `cancellation`
+/// dependency.
+///
+/// Graphs provide their token themselves, so this is only called for nodes run on their own, which
+/// get the token of the dependency cache they're run with.
+pub async fn cancellation(_deps: &DependencyCache) {
+    _deps.insert("cancellation", _deps.cancellation()).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicBool;
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_aborts_spawned_tasks() {
+        let token = CancellationToken::new();
+        let task = token.spawn(std::future::pending::<()>());
+        token.cancel();
+        assert!(task.await.unwrap_err().is_cancelled());
+        token.cancelled().await;
+    }
+
+    #[tokio::test]
+    async fn tasks_spawned_after_cancelling_never_run() {
+        static RAN: AtomicBool = AtomicBool::new(false);
+        let token = CancellationToken::new();
+        token.cancel();
+        let task = token.spawn(async { RAN.store(true, SeqCst) });
+        assert!(task.await.unwrap_err().is_cancelled());
+        assert!(!RAN.load(SeqCst));
+    }
+}
@@ -0,0 +1,17 @@
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+/// Limits how many invocations of a node run at once, across every run of every graph sharing it.
+///
+/// Invocations over the limit wait their turn, in the order they arrived.
+pub struct ConcurrencyLimit(Semaphore);
+impl ConcurrencyLimit {
+    pub const fn new(max_concurrency: usize) -> Self {
+        Self(Semaphore::const_new(max_concurrency))
+    }
+
+    /// Waits for a free slot, which is held until the permit is dropped.
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        // The semaphore is never closed.
+        self.0.acquire().await.unwrap()
+    }
+}
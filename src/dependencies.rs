@@ -2,23 +2,88 @@ use std::any::Any;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use crate::{CancellationToken, NodeMetrics};
 
-pub struct DependencyCache(Mutex<StackMap>);
+/// The dependencies provided to nodes.
+///
+/// Each run of a graph gets its own cache layered over the cache it was started with, holding the
+/// run's [`CancellationToken`] and [`NodeMetrics`].  Lookups fall through to the parent cache, and
+/// new dependencies are inserted into the bottom-most cache, so they're still shared with other
+/// runs.
+pub struct DependencyCache {
+    map: Mutex<StackMap>,
+    parent: Option<Arc<DependencyCache>>,
+    cancellation: CancellationToken,
+    metrics: NodeMetrics,
+}
 impl DependencyCache {
     pub fn new() -> Self {
-        Self(Mutex::new(StackMap::new()))
+        Self {
+            map: Mutex::new(StackMap::new()),
+            parent: None,
+            cancellation: CancellationToken::new(),
+            metrics: NodeMetrics::new(),
+        }
+    }
+
+    /// Creates the cache for a single run of a graph, with a new [`CancellationToken`] and
+    /// [`NodeMetrics`].
+    pub fn for_run(parent: Arc<DependencyCache>) -> Self {
+        let cancellation = CancellationToken::new();
+        let metrics = NodeMetrics::new();
+        let mut map = StackMap::new();
+        map.insert("cancellation", cancellation.clone());
+        map.insert("node_metrics", metrics.clone());
+        Self {
+            map: Mutex::new(map),
+            parent: Some(parent),
+            cancellation,
+            metrics,
+        }
+    }
+
+    pub fn cancellation(&self) -> CancellationToken {
+        self.cancellation.clone()
+    }
+
+    pub fn metrics(&self) -> NodeMetrics {
+        self.metrics.clone()
     }
 
     pub async fn insert<T: Any + Send + Sync>(&self, key: &str, value: T) {
-        self.0.lock().await.insert(key, value)
+        let mut cache = self;
+        while let Some(parent) = &cache.parent {
+            cache = parent;
+        }
+        cache.map.lock().await.insert(key, value)
     }
 
     pub async fn get<T: Any + Send + Sync>(&self, key: &str) -> Option<Arc<T>> {
-        self.0.lock().await.get(key)
+        let mut cache = self;
+        loop {
+            if let Some(value) = cache.map.lock().await.get(key) {
+                return Some(value);
+            }
+            cache = cache.parent.as_ref()?;
+        }
     }
 
     pub async fn contains(&self, key: &str) -> bool {
-        self.0.lock().await.contains(key)
+        let mut cache = self;
+        loop {
+            if cache.map.lock().await.contains(key) {
+                return true;
+            }
+            match &cache.parent {
+                Some(parent) => cache = parent,
+                None => return false,
+            }
+        }
+    }
+}
+impl Default for DependencyCache {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -36,7 +101,7 @@ impl StackMap {
 
     pub fn insert<T: Any + Send + Sync>(&mut self, key: &str, value: T) {
         let retval = self.map.insert(String::from(key), Arc::new(value));
-        if let Some(_) = &retval {
+        if retval.is_some() {
             self.stack.push(String::from(key));
         }
     }
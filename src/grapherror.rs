@@ -0,0 +1,129 @@
+use std::any::Any;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::task::JoinError;
+
+/// Why a run of a graph ended without an output.
+#[derive(Debug)]
+pub enum GraphError {
+    /// A node panicked while it ran.  `payload` is the value the node panicked with, as given to
+    /// [`std::panic::resume_unwind`].
+    NodePanicked {
+        node: &'static str,
+        nodetype: &'static str,
+        payload: Box<dyn Any + Send>,
+    },
+    /// The runtime couldn't run a blocking node to completion, as when it shuts down while the
+    /// node is still running.
+    NodeFailed {
+        node: &'static str,
+        nodetype: &'static str,
+        error: JoinError,
+    },
+    /// A node with a `timeout` ran out of time, and it has no `value=timeout` edge to follow
+    /// instead.
+    Timeout {
+        node: &'static str,
+        nodetype: &'static str,
+        timeout: Duration,
+    },
+    /// A node with a `retry` attribute returned an `Err`.  `error` describes it, with its `Debug`
+    /// representation if it has one.  This is only given to the graph's `on_retry` function: once
+    /// the node is out of retries, the `Err` is passed on like any other output.
+    NodeReturnedErr {
+        node: &'static str,
+        nodetype: &'static str,
+        error: String,
+    },
+    /// An edge with `max_iterations` reached its limit, and its source node has no `on_exhausted`
+    /// edge to follow instead.
+    IterationLimitExceeded {
+        edge: &'static str,
+        max_iterations: usize,
+    },
+    /// Every branch of the graph ended without an output, as when a `foreach` node scatters an
+    /// empty collection or the branches waiting at a join node never get their other inputs.
+    NoOutput,
+    /// The run was cancelled through its [`GraphRunHandle`](crate::GraphRunHandle) before it
+    /// ended.
+    Cancelled,
+    /// The tokio runtime to run the graph on couldn't be built, as configured by the graph's
+    /// [`RuntimeConfig`](crate::RuntimeConfig).
+    RuntimeFailed {
+        error: std::io::Error,
+    },
+}
+impl GraphError {
+    /// Describes a panic caught while running a node.  A blocking node's `JoinError` is unwrapped
+    /// into the panic it was caused by, if it was caused by one.
+    #[doc(hidden)]
+    pub fn from_panic(
+        node: &'static str,
+        nodetype: &'static str,
+        payload: Box<dyn Any + Send>
+    ) -> Self {
+        match payload.downcast::<JoinError>() {
+            Ok(error) if error.is_panic() => Self::NodePanicked {
+                node, nodetype, payload: error.into_panic()
+            },
+            Ok(error) => Self::NodeFailed { node, nodetype, error: *error },
+            Err(payload) => Self::NodePanicked { node, nodetype, payload },
+        }
+    }
+}
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NodePanicked { node, nodetype, payload } => {
+                let message = payload.downcast_ref::<&str>().copied()
+                    .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("Box<dyn Any>");
+                write!(f, "node `{}` of type `{}` panicked: {}", node, nodetype, message)
+            },
+            Self::NodeFailed { node, nodetype, error } => {
+                write!(f, "node `{}` of type `{}` failed: {}", node, nodetype, error)
+            },
+            Self::Timeout { node, nodetype, timeout } => write!(
+                f, "node `{}` of type `{}` timed out after {:?}", node, nodetype, timeout
+            ),
+            Self::NodeReturnedErr { node, nodetype, error } => write!(
+                f, "node `{}` of type `{}` returned an error: {}", node, nodetype, error
+            ),
+            Self::IterationLimitExceeded { edge, max_iterations } => write!(
+                f, "edge `{}` reached its limit of {} iterations", edge, max_iterations
+            ),
+            Self::NoOutput => write!(f, "the graph ended without an output"),
+            Self::Cancelled => write!(f, "the run of the graph was cancelled"),
+            Self::RuntimeFailed { error } => {
+                write!(f, "the runtime to run the graph on couldn't be built: {}", error)
+            },
+        }
+    }
+}
+impl std::error::Error for GraphError {}
+
+/// A future that catches a panic while it's polled, instead of unwinding the task running it.
+pub struct CatchPanic<F> {
+    future: Pin<Box<F>>,
+}
+impl<F: Future> Future for CatchPanic<F> {
+    type Output = Result<F::Output, Box<dyn Any + Send>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let future = self.future.as_mut();
+        match std::panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => Poll::Ready(Ok(output)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    }
+}
+
+/// Runs a node's future, giving back the panic payload if the node panics.
+pub fn catch_panic<F: Future>(future: F) -> CatchPanic<F> {
+    CatchPanic { future: Box::pin(future) }
+}
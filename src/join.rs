@@ -0,0 +1,182 @@
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering::Relaxed;
+
+/// The instances of the fan-outs a branch descends from, so a join node only joins the outputs of
+/// branches split off by the same run of the node that fanned them out.
+///
+/// A parallel node whose trailing nodes are joined again splits off a new instance every time it
+/// runs, replacing the instance of any earlier run of the same node.  Every task passes it along
+/// to the tasks it spawns.
+#[derive(Clone, Default)]
+pub struct FanOut(Arc<Vec<(&'static str, u64)>>);
+impl FanOut {
+    /// Starts a new instance of the fan-out at `node` for the branches it's about to spawn.
+    pub fn split(&self, node: &'static str) -> Self {
+        static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(0);
+        let mut instances: Vec<_> = self.0.iter()
+            .filter(|(split, _)| *split != node)
+            .copied()
+            .collect();
+        instances.push((node, NEXT_INSTANCE.fetch_add(1, Relaxed)));
+        Self(Arc::new(instances))
+    }
+
+    /// The instance of the fan-out at `node` the branch descends from, if it descends from one.
+    pub fn instance(&self, node: &'static str) -> Option<u64> {
+        self.0.iter().find(|(split, _)| *split == node).map(|(_, instance)| *instance)
+    }
+}
+
+/// Holds the outputs that have arrived at a join node until one has arrived from every incoming
+/// edge.
+///
+/// Each incoming edge of the join node has its own slot.  Outputs are queued in the order they
+/// arrive, so if a predecessor runs more than once before the others catch up (e.g. in a loop),
+/// its outputs are paired up with the others' first-come, first-served.
+pub struct JoinBuffer {
+    slots: Vec<VecDeque<Box<dyn Any + Send>>>,
+}
+impl JoinBuffer {
+    pub fn new(num_slots: usize) -> Self {
+        Self {
+            slots: (0..num_slots).map(|_| VecDeque::new()).collect(),
+        }
+    }
+
+    /// Adds an output to a slot, returning one output from every slot if all of them are filled.
+    pub fn push(
+        &mut self,
+        slot: usize,
+        value: Box<dyn Any + Send>
+    ) -> Option<Vec<Box<dyn Any + Send>>> {
+        self.slots[slot].push_back(value);
+        if self.slots.iter().any(VecDeque::is_empty) {
+            return None;
+        }
+        Some(self.slots.iter_mut().map(|slot| slot.pop_front().unwrap()).collect())
+    }
+
+    /// Whether no outputs are waiting in any slot.
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(VecDeque::is_empty)
+    }
+}
+
+/// The input arguments of a join node, assembled from the outputs of its predecessors.
+///
+/// Implemented for tuples, where each predecessor provides one element in the order of the join
+/// node's incoming edges, and for `Vec`, where all predecessors provide the same type.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be used as the input of a join node",
+    label = "join nodes must take a tuple or `Vec` of their predecessors' outputs"
+)]
+pub trait JoinArgs: Sized {
+    fn from_slots(slots: Vec<Box<dyn Any + Send>>) -> Self;
+}
+
+/// The type a join node expects from the predecessor on its `SLOT`th incoming edge.
+#[diagnostic::on_unimplemented(
+    message = "join node input `{Self}` has no element for incoming edge {SLOT}",
+    label = "join nodes take a tuple with one element per incoming edge, or a `Vec`"
+)]
+pub trait JoinSlot<const SLOT: usize> {
+    type Input: Send + 'static;
+}
+
+fn downcast<T: 'static>(value: Option<Box<dyn Any + Send>>) -> T {
+    // The generated slot functions only accept the type declared by `JoinSlot`, so a mismatch here
+    // is a bug in the generated code.
+    *value.expect("join node received too few inputs")
+        .downcast::<T>()
+        .expect("join node received an input of the wrong type")
+}
+
+impl<T: Send + 'static> JoinArgs for Vec<T> {
+    fn from_slots(slots: Vec<Box<dyn Any + Send>>) -> Self {
+        slots.into_iter().map(|value| downcast::<T>(Some(value))).collect()
+    }
+}
+
+impl<T: Send + 'static, const SLOT: usize> JoinSlot<SLOT> for Vec<T> {
+    type Input = T;
+}
+
+macro_rules! impl_join_tuple {
+    ($($slot:tt $name:ident),+) => {
+        impl<$($name: Send + 'static),+> JoinArgs for ($($name,)+) {
+            fn from_slots(slots: Vec<Box<dyn Any + Send>>) -> Self {
+                let mut slots = slots.into_iter();
+                ($(downcast::<$name>(slots.next()),)+)
+            }
+        }
+        impl_join_tuple!(@slots ($($name),+) $($slot $name),+);
+    };
+    (@slots $all:tt $($slot:tt $name:ident),+) => {
+        $(impl_join_tuple!(@slot $all $slot $name);)+
+    };
+    (@slot ($($all:ident),+) $slot:tt $name:ident) => {
+        impl<$($all: Send + 'static),+> JoinSlot<$slot> for ($($all,)+) {
+            type Input = $name;
+        }
+    };
+}
+
+impl_join_tuple!(0 A, 1 B);
+impl_join_tuple!(0 A, 1 B, 2 C);
+impl_join_tuple!(0 A, 1 B, 2 C, 3 D);
+impl_join_tuple!(0 A, 1 B, 2 C, 3 D, 4 E);
+impl_join_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F);
+impl_join_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G);
+impl_join_tuple!(0 A, 1 B, 2 C, 3 D, 4 E, 5 F, 6 G, 7 H);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unbox(values: Vec<Box<dyn Any + Send>>) -> Vec<u32> {
+        values.into_iter().map(|value| *value.downcast::<u32>().unwrap()).collect()
+    }
+
+    #[test]
+    fn releases_outputs_once_every_slot_is_filled() {
+        let mut buffer = JoinBuffer::new(3);
+        assert!(buffer.push(2, Box::new(2u32)).is_none());
+        assert!(buffer.push(0, Box::new(0u32)).is_none());
+        assert_eq!(unbox(buffer.push(1, Box::new(1u32)).unwrap()), vec![0, 1, 2]);
+        assert!(buffer.push(0, Box::new(3u32)).is_none());
+    }
+
+    #[test]
+    fn pairs_repeated_outputs_first_come_first_served() {
+        let mut buffer = JoinBuffer::new(2);
+        assert!(buffer.push(0, Box::new(1u32)).is_none());
+        assert!(buffer.push(0, Box::new(2u32)).is_none());
+        assert_eq!(unbox(buffer.push(1, Box::new(10u32)).unwrap()), vec![1, 10]);
+        assert_eq!(unbox(buffer.push(1, Box::new(20u32)).unwrap()), vec![2, 20]);
+        assert!(buffer.push(1, Box::new(30u32)).is_none());
+    }
+
+    #[test]
+    fn splits_a_new_instance_each_time() {
+        let root = FanOut::default();
+        assert_eq!(root.instance("a"), None);
+        let first = root.split("a");
+        let nested = first.split("b");
+        let second = nested.split("a");
+        assert_eq!(nested.instance("a"), first.instance("a"));
+        assert!(nested.instance("b").is_some());
+        assert_ne!(second.instance("a"), first.instance("a"));
+        assert_eq!(second.instance("b"), nested.instance("b"));
+    }
+
+    #[test]
+    fn assembles_tuples_and_vecs() {
+        let slots: Vec<Box<dyn Any + Send>> = vec![Box::new(1u32), Box::new(String::from("a"))];
+        assert_eq!(<(u32, String)>::from_slots(slots), (1, String::from("a")));
+        let slots: Vec<Box<dyn Any + Send>> = vec![Box::new(1u32), Box::new(2u32)];
+        assert_eq!(Vec::<u32>::from_slots(slots), vec![1, 2]);
+    }
+}
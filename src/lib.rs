@@ -10,7 +10,8 @@
 //! entirely new subsystems without needing to refactor existing components or control flow.
 //!
 //! The control flow graph is defined with the [`graph`] macro, which converts your graph as
-//! defined with the [DOT language](https://graphviz.org) into an executable structure.  Each
+//! defined with the [DOT language](https://graphviz.org) into an executable structure (or with
+//! [`graph_file`] to load the graph from a `.dot` file).  Each
 //! node in the graph is annotated with a [`nodetype`] that associates a block of code with the
 //! node. Finally, sometimes a node needs more to do its job than just the output of the previous
 //! node in the graph.  Conflagrate provides a simple dependency injection system to provide
@@ -67,19 +68,52 @@
 //!     }
 //! }
 //!
-//! fn main() {
-//!     MemoryEcho::run(());
+//! fn main() -> Result<(), conflagrate::GraphError> {
+//!     MemoryEcho::run(())
 //! }
 //! ```
 
 mod branchtracker;
+mod cancellation;
+mod concurrency;
 mod dependencies;
+mod grapherror;
+mod join;
+mod matching;
+mod metrics;
+mod nodeoutput;
+mod race;
+mod runhandle;
+mod runtime;
 
-pub use conflagrate_macros::{dependency, graph, nodetype};
+pub use cancellation::{cancellation, CancellationToken};
+pub use conflagrate_macros::{dependency, graph, graph_file, nodetype};
+pub use grapherror::GraphError;
+pub use matching::MatchKey;
+pub use metrics::{node_metrics, NodeMetrics};
+pub use runhandle::GraphRunHandle;
+pub use runtime::RuntimeConfig;
 #[doc(hidden)]
 pub use branchtracker::BranchTracker;
 #[doc(hidden)]
+pub use concurrency::ConcurrencyLimit;
+#[doc(hidden)]
 pub use dependencies::DependencyCache;
+#[doc(hidden)]
+pub use grapherror::catch_panic;
+#[doc(hidden)]
+pub use join::{FanOut, JoinArgs, JoinSlot};
+#[doc(hidden)]
+pub use matching::glob_match;
+#[doc(hidden)]
+pub use regex::Regex;
+#[doc(hidden)]
+pub use nodeoutput::{
+    check_enum_variant, EnumVariant, MatcherOutput, OptionOutput, ResultOutput, RetryCheck,
+    RetryDebugErr, RetryErr, RetryOutput
+};
+#[doc(hidden)]
+pub use race::Race;
 
 #[doc(hidden)]
 #[async_trait::async_trait]
@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+
+/// A key a `matcher` node can route on, returned as the first element of the node's output.
+///
+/// Edges with a plain `value` attribute are followed if [`matches_value`](MatchKey::matches_value)
+/// returns true for the value, and edges with a `pattern=glob` or `pattern=regex` attribute match
+/// their pattern against [`as_text`](MatchKey::as_text).  Edges with `pattern=range` compare the
+/// key directly against a Rust range, so they only need the key to be comparable to the range's
+/// bounds.
+///
+/// Implemented for strings, integers, `char` and `bool`.  Implement it for your own types to route
+/// on them:
+/// ```
+/// # use std::borrow::Cow;
+/// # use conflagrate::MatchKey;
+/// pub enum Method {
+///     Get,
+///     Post,
+/// }
+///
+/// impl MatchKey for Method {
+///     fn matches_value(&self, value: &str) -> bool {
+///         self.as_text().eq_ignore_ascii_case(value)
+///     }
+///
+///     fn as_text(&self) -> Cow<'_, str> {
+///         match self {
+///             Method::Get => Cow::Borrowed("GET"),
+///             Method::Post => Cow::Borrowed("POST"),
+///         }
+///     }
+/// }
+/// ```
+pub trait MatchKey {
+    /// Whether the key is equal to the `value` attribute of an edge.
+    fn matches_value(&self, value: &str) -> bool;
+
+    /// The key as text, for matching against `glob` and `regex` patterns.
+    fn as_text(&self) -> Cow<'_, str>;
+}
+
+impl MatchKey for String {
+    fn matches_value(&self, value: &str) -> bool {
+        self == value
+    }
+
+    fn as_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl MatchKey for &str {
+    fn matches_value(&self, value: &str) -> bool {
+        *self == value
+    }
+
+    fn as_text(&self) -> Cow<'_, str> {
+        Cow::Borrowed(self)
+    }
+}
+
+macro_rules! impl_match_key_from_str {
+    ($($key:ty),+) => {
+        $(
+            impl MatchKey for $key {
+                fn matches_value(&self, value: &str) -> bool {
+                    value.parse::<$key>().map_or(false, |value| value == *self)
+                }
+
+                fn as_text(&self) -> Cow<'_, str> {
+                    Cow::Owned(self.to_string())
+                }
+            }
+        )+
+    };
+}
+
+impl_match_key_from_str!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, char, bool
+);
+
+/// Matches text against a glob pattern, where `*` matches any run of characters (including none)
+/// and `?` matches any single character.
+#[doc(hidden)]
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` seen and the text position it was tried at, for backtracking.
+    let mut star: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_literal_text() {
+        assert!(glob_match("exit", "exit"));
+        assert!(!glob_match("exit", "exits"));
+        assert!(!glob_match("exit", "exi"));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn glob_star_matches_any_run() {
+        assert!(glob_match("cmd_*", "cmd_"));
+        assert!(glob_match("cmd_*", "cmd_run"));
+        assert!(glob_match("*_end", "the_end"));
+        assert!(glob_match("a*b*c", "aXXbYYc"));
+        assert!(glob_match("a*b*c", "abbbc"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("**", "anything"));
+        assert!(!glob_match("a*b", "aXXc"));
+        assert!(!glob_match("cmd_*", "cmd"));
+    }
+
+    #[test]
+    fn glob_question_mark_matches_one_character() {
+        assert!(glob_match("v?", "v1"));
+        assert!(glob_match("?é", "xé"));
+        assert!(!glob_match("v?", "v"));
+        assert!(!glob_match("v?", "v12"));
+        assert!(glob_match("v?*", "v12"));
+    }
+
+    #[test]
+    fn string_keys_match_exactly() {
+        assert!(String::from("exit").matches_value("exit"));
+        assert!(!String::from("exit").matches_value("Exit"));
+        assert!("exit".matches_value("exit"));
+        assert_eq!("exit".as_text(), "exit");
+    }
+
+    #[test]
+    fn parsed_keys_match_their_text() {
+        assert!(418u16.matches_value("418"));
+        assert!(!418u16.matches_value("419"));
+        assert!(!418u16.matches_value("teapot"));
+        assert!((-1i32).matches_value("-1"));
+        assert!('q'.matches_value("q"));
+        assert!(true.matches_value("true"));
+        assert!(!true.matches_value("yes"));
+        assert_eq!(418u16.as_text(), "418");
+    }
+}
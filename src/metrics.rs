@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::DependencyCache;
+
+/// Counts what happened to the nodes of a run of a graph, by node name.
+///
+/// Every run of a graph has its own metrics, which its [`GraphRunHandle`] gives access to.  Nodes
+/// can read them by taking a `node_metrics: &NodeMetrics` dependency, with the [`node_metrics`]
+/// dependency provider in scope.  Clones of the metrics share their counts.
+///
+/// [`GraphRunHandle`]: crate::GraphRunHandle
+#[derive(Clone, Default)]
+pub struct NodeMetrics(Arc<Mutex<HashMap<&'static str, usize>>>);
+impl NodeMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many times the node has been retried after failing (see the node's `retry` attribute).
+    pub fn retries(&self, node: &str) -> usize {
+        self.0.lock().unwrap().get(node).copied().unwrap_or(0)
+    }
+
+    #[doc(hidden)]
+    pub fn record_retry(&self, node: &'static str) {
+        *self.0.lock().unwrap().entry(node).or_insert(0) += 1;
+    }
+}
+
+/// Provides the [`NodeMetrics`] of the running graph to nodes that take a `node_metrics`
+/// dependency.
+///
+/// Graphs provide their metrics themselves, so this is only called for nodes run on their own,
+/// which get the metrics of the dependency cache they're run with.
+pub async fn node_metrics(_deps: &DependencyCache) {
+    _deps.insert("node_metrics", _deps.metrics()).await;
+}
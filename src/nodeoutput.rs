@@ -0,0 +1,77 @@
+/// Gives the type a `matcher` node passes on to the next node (the second element of its output).
+pub trait MatcherOutput {
+    type Output;
+}
+impl<K, T> MatcherOutput for (K, T) {
+    type Output = T;
+}
+
+/// Gives the types a `resultmatcher` node passes on to the next nodes for each variant.
+pub trait ResultOutput {
+    type Ok;
+    type Err;
+}
+impl<T, E> ResultOutput for Result<T, E> {
+    type Ok = T;
+    type Err = E;
+}
+
+/// The output of a node with a `retry` attribute, checked for an `Err` that should be retried.
+///
+/// Nodes of any branch kind may return a `Result`, which the generated code can't see, so the
+/// check is picked by autoref specialization: called as `(&&&RetryCheck(output)).failure()`,
+/// the impl closest to the receiver type applies.  An `Err` is described with its `Debug`
+/// representation if it has one, and by its type otherwise, and any other output never fails.
+pub struct RetryCheck<'a, T>(pub &'a T);
+
+/// Describes the `Err` of a `Result` output whose error has a `Debug` representation.
+pub trait RetryDebugErr {
+    fn failure(&self) -> Option<String>;
+}
+impl<T, E: std::fmt::Debug> RetryDebugErr for &&RetryCheck<'_, Result<T, E>> {
+    fn failure(&self) -> Option<String> {
+        self.0.as_ref().err().map(|error| format!("{:?}", error))
+    }
+}
+
+/// Describes the `Err` of a `Result` output by the type of the error.
+pub trait RetryErr {
+    fn failure(&self) -> Option<String>;
+}
+impl<T, E> RetryErr for &RetryCheck<'_, Result<T, E>> {
+    fn failure(&self) -> Option<String> {
+        self.0.as_ref().err().map(|_| format!("an `Err` of type `{}`", std::any::type_name::<E>()))
+    }
+}
+
+/// Treats an output that isn't a `Result` as never failing.
+pub trait RetryOutput {
+    fn failure(&self) -> Option<String>;
+}
+impl<T> RetryOutput for RetryCheck<'_, T> {
+    fn failure(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Gives the type an `optionmatcher` node passes on to the next nodes for `Some`.
+pub trait OptionOutput {
+    type Some;
+}
+impl<T> OptionOutput for Option<T> {
+    type Some = T;
+}
+
+/// The constructor of an enum variant holding exactly one value, which is what an `enummatcher`
+/// node passes on to the next nodes for that variant.
+#[diagnostic::on_unimplemented(
+    message = "`enummatcher` edges can only route enum variants holding exactly one value",
+    label = "not a variant like `Variant(value)`",
+    note = "give a variant without a value a unit value instead, like `Variant(())`"
+)]
+pub trait EnumVariant<E, T> {}
+impl<E, T, F: Fn(T) -> E> EnumVariant<E, T> for F {}
+
+/// Checks that an `enummatcher` edge routes a variant holding exactly one value, so any other
+/// variant is reported at the edge instead of as a mismatched pattern.
+pub fn check_enum_variant<E, T, F: EnumVariant<E, T>>(_variant: F) {}
@@ -0,0 +1,88 @@
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::SeqCst;
+use tokio::task::AbortHandle;
+
+const NO_WINNER: usize = usize::MAX;
+
+/// Decides which branch of a `race` node finishes first, and aborts the tasks of the others.
+///
+/// Each branch of the race is registered with its index once it's spawned, and calls `try_win`
+/// with the same index when its node has run.  Only the first call wins.  A branch registered
+/// after the race has been won is aborted as soon as it's registered.
+pub struct Race {
+    winner: AtomicUsize,
+    branches: Mutex<Vec<(usize, AbortHandle)>>,
+}
+impl Race {
+    pub fn new() -> Self {
+        Self {
+            winner: AtomicUsize::new(NO_WINNER),
+            branches: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn register(&self, index: usize, branch: AbortHandle) {
+        let mut branches = self.branches.lock().unwrap();
+        let winner = self.winner.load(SeqCst);
+        if winner != NO_WINNER && winner != index {
+            branch.abort();
+        }
+        branches.push((index, branch));
+    }
+
+    /// Claims the race for the branch at `index`, aborting every other branch, or returns false if
+    /// another branch already won.
+    pub fn try_win(&self, index: usize) -> bool {
+        if self.winner.compare_exchange(NO_WINNER, index, SeqCst, SeqCst).is_err() {
+            return false;
+        }
+        for (branch_index, branch) in self.branches.lock().unwrap().iter() {
+            if *branch_index != index {
+                branch.abort();
+            }
+        }
+        true
+    }
+}
+impl Default for Race {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending_task() -> tokio::task::JoinHandle<()> {
+        tokio::spawn(std::future::pending())
+    }
+
+    #[tokio::test]
+    async fn first_branch_to_finish_wins() {
+        let race = Race::new();
+        let first = pending_task();
+        let second = pending_task();
+        race.register(0, first.abort_handle());
+        race.register(1, second.abort_handle());
+        assert!(race.try_win(1));
+        assert!(!race.try_win(0));
+        assert!(!race.try_win(1));
+        assert!(first.await.unwrap_err().is_cancelled());
+        second.abort();
+    }
+
+    #[tokio::test]
+    async fn branches_registered_after_the_win_are_aborted() {
+        let race = Race::new();
+        assert!(race.try_win(0));
+        let winner = pending_task();
+        let late = pending_task();
+        race.register(0, winner.abort_handle());
+        race.register(1, late.abort_handle());
+        assert!(late.await.unwrap_err().is_cancelled());
+        assert!(!winner.is_finished());
+        winner.abort();
+    }
+}
@@ -0,0 +1,70 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::oneshot::Receiver;
+use crate::{CancellationToken, DependencyCache, GraphError, NodeMetrics};
+
+/// A run of a graph started with its `start_graph()` method.
+///
+/// Awaiting the handle gives the output of the run.  Cancelling it, or dropping it before the run
+/// ends, aborts the tasks of the run's nodes and keeps any more from being spawned.
+pub struct GraphRunHandle<T> {
+    receiver: Receiver<Result<T, GraphError>>,
+    cancellation: CancellationToken,
+    metrics: NodeMetrics,
+    cancelled: Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+impl<T> GraphRunHandle<T> {
+    #[doc(hidden)]
+    pub fn new(receiver: Receiver<Result<T, GraphError>>, deps: &DependencyCache) -> Self {
+        let cancellation = deps.cancellation();
+        let token = cancellation.clone();
+        Self {
+            receiver,
+            cancellation,
+            metrics: deps.metrics(),
+            cancelled: Box::pin(async move { token.cancelled().await }),
+        }
+    }
+
+    /// Cancels the run.  Awaiting the handle afterwards gives [`GraphError::Cancelled`], unless the
+    /// run had already ended.
+    pub fn cancel(&self) {
+        self.cancellation.cancel();
+    }
+
+    /// The token cancelled by [`cancel`](Self::cancel), which is also given to the run's nodes.
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// The metrics of the run's nodes, which are updated as the run goes on.
+    pub fn metrics(&self) -> &NodeMetrics {
+        &self.metrics
+    }
+}
+impl<T> Future for GraphRunHandle<T> {
+    type Output = Result<T, GraphError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // A node aborted by the cancellation can fail the run before it ends, so errors are
+        // reported as a cancellation once the run has been cancelled.
+        let cancelled = self.cancellation.is_cancelled();
+        match Pin::new(&mut self.receiver).poll(cx) {
+            Poll::Ready(Ok(Ok(output))) => return Poll::Ready(Ok(output)),
+            Poll::Ready(_) if cancelled => return Poll::Ready(Err(GraphError::Cancelled)),
+            Poll::Ready(Ok(Err(error))) => return Poll::Ready(Err(error)),
+            Poll::Ready(Err(_)) => return Poll::Ready(Err(GraphError::NoOutput)),
+            Poll::Pending => {},
+        }
+        match self.cancelled.as_mut().poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(GraphError::Cancelled)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+impl<T> Drop for GraphRunHandle<T> {
+    fn drop(&mut self) {
+        self.cancellation.cancel();
+    }
+}
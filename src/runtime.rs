@@ -0,0 +1,74 @@
+use tokio::runtime::{Builder, Runtime};
+
+/// The tokio runtime a graph's `run_with()` method creates to run the graph on.
+///
+/// The `run()` method uses the configuration set by the graph's own `flavor`, `worker_threads`,
+/// `max_blocking_threads` and `thread_name` attributes, given by its `runtime_config()` method.
+/// Without any of them, the runtime is the same as `tokio::runtime::Runtime::new()` builds.
+#[derive(Clone, Debug)]
+pub struct RuntimeConfig {
+    current_thread: bool,
+    worker_threads: Option<usize>,
+    max_blocking_threads: Option<usize>,
+    thread_name: Option<String>,
+}
+impl RuntimeConfig {
+    /// A runtime running the graph's nodes on a pool of worker threads.
+    pub fn multi_thread() -> Self {
+        Self {
+            current_thread: false,
+            worker_threads: None,
+            max_blocking_threads: None,
+            thread_name: None,
+        }
+    }
+
+    /// A runtime running the graph's nodes on the thread that runs the graph.  Blocking nodes
+    /// still run on threads of their own.
+    pub fn current_thread() -> Self {
+        Self { current_thread: true, ..Self::multi_thread() }
+    }
+
+    /// The number of worker threads of a multi-threaded runtime.  Defaults to the number of CPU
+    /// cores.
+    pub fn worker_threads(mut self, worker_threads: usize) -> Self {
+        self.worker_threads = Some(worker_threads);
+        self
+    }
+
+    /// The most threads the runtime spawns to run blocking nodes on.  Defaults to 512.
+    pub fn max_blocking_threads(mut self, max_blocking_threads: usize) -> Self {
+        self.max_blocking_threads = Some(max_blocking_threads);
+        self
+    }
+
+    /// The name of the threads the runtime spawns.  Defaults to tokio's own name for them.
+    pub fn thread_name(mut self, thread_name: impl Into<String>) -> Self {
+        self.thread_name = Some(thread_name.into());
+        self
+    }
+
+    /// Builds the runtime, with its timers enabled.
+    pub fn build(&self) -> std::io::Result<Runtime> {
+        let mut builder = match self.current_thread {
+            true => Builder::new_current_thread(),
+            false => Builder::new_multi_thread(),
+        };
+        builder.enable_all();
+        if let Some(worker_threads) = self.worker_threads {
+            builder.worker_threads(worker_threads);
+        }
+        if let Some(max_blocking_threads) = self.max_blocking_threads {
+            builder.max_blocking_threads(max_blocking_threads);
+        }
+        if let Some(thread_name) = &self.thread_name {
+            builder.thread_name(thread_name);
+        }
+        builder.build()
+    }
+}
+impl Default for RuntimeConfig {
+    fn default() -> Self {
+        Self::multi_thread()
+    }
+}
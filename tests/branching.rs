@@ -0,0 +1,1024 @@
+//! Runs a small graph for each kind of branching, checking which nodes run and what the graph
+//! outputs.
+
+mod join {
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Start(name: String) -> String {
+        name
+    }
+
+    #[nodetype]
+    pub async fn CountLetters(name: String) -> usize {
+        name.len()
+    }
+
+    #[nodetype]
+    pub async fn Reverse(name: String) -> String {
+        name.chars().rev().collect()
+    }
+
+    #[nodetype]
+    pub fn Summarize(letters: usize, reversed: String) -> String {
+        format!("{} {}", letters, reversed)
+    }
+
+    graph!{
+        digraph JoinGraph {
+            start[type=Start, start=true];
+            count_letters[type=CountLetters];
+            reverse[type=Reverse];
+            summarize[type=Summarize, join=all];
+            start -> count_letters -> summarize;
+            start -> reverse -> summarize;
+        }
+    }
+
+    #[tokio::test]
+    async fn waits_for_every_incoming_branch() {
+        let output = JoinGraph::run_graph(String::from("abc"), None).await.unwrap();
+        assert_eq!(output, "3 cba");
+    }
+
+    static PAIRS: Mutex<Vec<(u64, u64)>> = Mutex::new(Vec::new());
+
+    #[nodetype]
+    pub fn Split(count: u64) -> Vec<u64> {
+        (1..=count).collect()
+    }
+
+    #[nodetype]
+    pub fn Fan(number: u64) -> u64 {
+        number
+    }
+
+    /// Takes longer for smaller numbers, so later fan-outs reach the join node first.
+    #[nodetype]
+    pub async fn Slow(number: u64) -> u64 {
+        tokio::time::sleep(Duration::from_millis(300 - 100 * number)).await;
+        number
+    }
+
+    #[nodetype]
+    pub fn Fast(number: u64) -> u64 {
+        number
+    }
+
+    #[nodetype]
+    pub fn Pair(slow: u64, fast: u64) -> u64 {
+        PAIRS.lock().unwrap().push((slow, fast));
+        slow
+    }
+
+    graph!{
+        digraph InterleavedGraph {
+            split[type=Split, branch=foreach, start=true];
+            fan[type=Fan];
+            slow[type=Slow];
+            fast[type=Fast];
+            pair[type=Pair, join=all];
+            split -> fan;
+            fan -> slow -> pair;
+            fan -> fast -> pair;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn joins_outputs_of_the_same_fan_out() {
+        InterleavedGraph::run_graph(2, None).await.unwrap();
+        assert_eq!(*PAIRS.lock().unwrap(), vec![(2, 2), (1, 1)]);
+    }
+}
+
+mod enum_matcher {
+    use conflagrate::{graph, nodetype};
+
+    pub enum Shape {
+        Circle(f64),
+        Square(f64),
+    }
+
+    #[nodetype]
+    pub fn Pick(circle: bool) -> Shape {
+        if circle { Shape::Circle(1.0) } else { Shape::Square(2.0) }
+    }
+
+    #[nodetype]
+    pub fn CircleArea(radius: f64) -> f64 {
+        3.0 * radius * radius
+    }
+
+    #[nodetype]
+    pub fn SquareArea(side: f64) -> f64 {
+        side * side
+    }
+
+    graph!{
+        digraph EnumGraph {
+            pick[type=Pick, branch=enummatcher, start=true];
+            circle[type=CircleArea];
+            square[type=SquareArea];
+            pick -> circle [value=Circle];
+            pick -> square [value=Square];
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_variant_contents_to_its_edge() {
+        assert_eq!(EnumGraph::run_graph(true, None).await.unwrap(), 3.0);
+        assert_eq!(EnumGraph::run_graph(false, None).await.unwrap(), 4.0);
+    }
+}
+
+mod option_matcher {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Find(id: u32) -> Option<String> {
+        if id == 1 { Some(String::from("alice")) } else { None }
+    }
+
+    #[nodetype]
+    pub fn Greet(name: String) -> String {
+        format!("hello {}", name)
+    }
+
+    #[nodetype]
+    pub fn Missing() -> String {
+        String::from("nobody")
+    }
+
+    graph!{
+        digraph OptionGraph {
+            find[type=Find, branch=optionmatcher, start=true];
+            greet[type=Greet];
+            missing[type=Missing];
+            find -> greet [value=some];
+            find -> missing [value=none];
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_some_and_none_edges() {
+        assert_eq!(OptionGraph::run_graph(1, None).await.unwrap(), "hello alice");
+        assert_eq!(OptionGraph::run_graph(2, None).await.unwrap(), "nobody");
+    }
+}
+
+mod fanout {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use conflagrate::{graph, nodetype};
+
+    static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Route(command: String) -> (String, String) {
+        (command.clone(), command)
+    }
+
+    #[nodetype]
+    pub async fn Handle(command: String) -> String {
+        HANDLED.fetch_add(1, SeqCst);
+        format!("handled {}", command)
+    }
+
+    #[nodetype]
+    pub fn Ignore(command: String) -> String {
+        format!("ignored {}", command)
+    }
+
+    graph!{
+        digraph FanoutGraph {
+            route[type=Route, branch=matcher, fanout=true, start=true];
+            log[type=Handle];
+            save[type=Handle];
+            ignore[type=Ignore];
+            route -> log [value=save];
+            route -> save [value=save];
+            route -> ignore;
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_every_edge_with_the_matching_value() {
+        let output = FanoutGraph::run_graph(String::from("save"), None).await.unwrap();
+        assert_eq!(output, "handled save");
+        assert_eq!(HANDLED.load(SeqCst), 2);
+        let output = FanoutGraph::run_graph(String::from("load"), None).await.unwrap();
+        assert_eq!(output, "ignored load");
+        assert_eq!(HANDLED.load(SeqCst), 2);
+    }
+}
+
+mod matcher {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Classify(code: u16) -> (u16, u16) {
+        (code, code)
+    }
+
+    #[nodetype]
+    pub fn Describe(code: u16) -> String {
+        format!("code {}", code)
+    }
+
+    #[nodetype]
+    pub fn Teapot(_code: u16) -> String {
+        String::from("teapot")
+    }
+
+    #[nodetype]
+    pub fn Success(_code: u16) -> String {
+        String::from("success")
+    }
+
+    graph!{
+        digraph MatcherGraph {
+            classify[type=Classify, branch=matcher, start=true];
+            teapot[type=Teapot];
+            success[type=Success];
+            describe[type=Describe];
+            classify -> teapot [value=418];
+            classify -> success [value="200..300", pattern=range];
+            classify -> describe;
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_the_edge_matching_the_key() {
+        assert_eq!(MatcherGraph::run_graph(418, None).await.unwrap(), "teapot");
+        assert_eq!(MatcherGraph::run_graph(204, None).await.unwrap(), "success");
+        assert_eq!(MatcherGraph::run_graph(500, None).await.unwrap(), "code 500");
+    }
+}
+
+mod glob_matcher {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Command(command: String) -> (String, String) {
+        (command.clone(), command)
+    }
+
+    #[nodetype]
+    pub fn Known(command: String) -> String {
+        format!("known {}", command)
+    }
+
+    graph!{
+        digraph GlobGraph {
+            command[type=Command, branch=matcher, start=true];
+            known[type=Known];
+            command -> known [value="cmd_*", pattern=glob];
+        }
+    }
+
+    #[tokio::test]
+    async fn ends_the_graph_when_nothing_matches() {
+        let output = GlobGraph::run_graph(String::from("cmd_run"), None).await.unwrap();
+        assert_eq!(output, "known cmd_run");
+        assert_eq!(GlobGraph::run_graph(String::from("other"), None).await.unwrap(), "other");
+    }
+}
+
+mod guard {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use conflagrate::{graph, nodetype};
+
+    static LOGGED: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Start(input: u32) -> u32 {
+        input
+    }
+
+    #[nodetype]
+    pub async fn Double(input: u32) -> u32 {
+        input * 2
+    }
+
+    #[nodetype]
+    pub async fn Log(input: u32) -> u32 {
+        LOGGED.fetch_add(1, SeqCst);
+        input
+    }
+
+    fn is_even(input: &u32) -> bool {
+        input % 2 == 0
+    }
+
+    fn is_large(input: &u32) -> bool {
+        *input > 100
+    }
+
+    graph!{
+        digraph GuardGraph {
+            start[type=Start, start=true];
+            double[type=Double];
+            log[type=Log];
+            start -> double [when=is_even];
+            start -> log [when=is_large];
+        }
+    }
+
+    #[tokio::test]
+    async fn skips_edges_whose_guard_is_false() {
+        assert_eq!(GuardGraph::run_graph(4, None).await.unwrap(), 8);
+        assert_eq!(LOGGED.load(SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn ends_with_the_node_output_when_no_guard_passes() {
+        assert_eq!(GuardGraph::run_graph(3, None).await.unwrap(), 3);
+        assert_eq!(LOGGED.load(SeqCst), 0);
+    }
+}
+
+mod race {
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype};
+
+    static SLOW_STARTED: Notify = Notify::const_new();
+    static SLOW_ABORTED: Notify = Notify::const_new();
+
+    struct NotifyOnDrop(&'static Notify);
+    impl Drop for NotifyOnDrop {
+        fn drop(&mut self) {
+            self.0.notify_one();
+        }
+    }
+
+    #[nodetype]
+    pub fn Request() {}
+
+    #[nodetype]
+    pub async fn Fast() -> String {
+        SLOW_STARTED.notified().await;
+        String::from("fast")
+    }
+
+    #[nodetype]
+    pub async fn Slow() -> String {
+        let _aborted = NotifyOnDrop(&SLOW_ABORTED);
+        SLOW_STARTED.notify_one();
+        std::future::pending().await
+    }
+
+    #[nodetype]
+    pub fn Record(winner: String) -> String {
+        format!("{} won", winner)
+    }
+
+    graph!{
+        digraph RaceGraph {
+            request[type=Request, branch=race, start=true];
+            fast[type=Fast];
+            slow[type=Slow];
+            record_fast[type=Record];
+            record_slow[type=Record];
+            request -> fast -> record_fast;
+            request -> slow -> record_slow;
+        }
+    }
+
+    #[tokio::test]
+    async fn only_the_winner_continues() {
+        assert_eq!(RaceGraph::run_graph((), None).await.unwrap(), "fast won");
+        // Resolves once the losing branch's task has been aborted and dropped.
+        SLOW_ABORTED.notified().await;
+    }
+}
+
+mod foreach {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use conflagrate::{graph, nodetype};
+
+    static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+    static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Split(text: String) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[nodetype]
+    pub async fn Upper(word: String) -> String {
+        PEAK.fetch_max(IN_FLIGHT.fetch_add(1, SeqCst) + 1, SeqCst);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        IN_FLIGHT.fetch_sub(1, SeqCst);
+        word.to_uppercase()
+    }
+
+    #[nodetype]
+    pub fn Join(words: Vec<String>) -> String {
+        words.join(" ")
+    }
+
+    graph!{
+        digraph ForEachGraph {
+            split[type=Split, branch=foreach, max_concurrent_elements=2, start=true];
+            upper[type=Upper];
+            join[type=Join, gather=true];
+            split -> upper -> join;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn gathers_outputs_in_element_order() {
+        let words = String::from("a b c d e f g h");
+        assert_eq!(ForEachGraph::run_graph(words, None).await.unwrap(), "A B C D E F G H");
+        assert!(PEAK.load(SeqCst) <= 2, "{} elements ran at once", PEAK.load(SeqCst));
+    }
+}
+
+mod map {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Read(text: String) -> String {
+        text
+    }
+
+    #[nodetype]
+    pub fn Parity(number: u64) -> (u64, u64) {
+        (number % 2, number)
+    }
+
+    #[nodetype]
+    pub fn Describe(description: String) -> String {
+        description
+    }
+
+    #[nodetype]
+    pub fn Split(text: String) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    #[nodetype]
+    pub async fn Double(length: usize) -> usize {
+        length * 2
+    }
+
+    #[nodetype]
+    pub fn Total(lengths: Vec<usize>) -> String {
+        format!("{:?}", lengths)
+    }
+
+    fn parse(text: String) -> u64 {
+        text.parse().unwrap()
+    }
+
+    fn describe_even(number: u64) -> String {
+        format!("{} is even", number)
+    }
+
+    fn describe_odd(number: u64) -> String {
+        format!("{} is odd", number)
+    }
+
+    fn length(word: String) -> usize {
+        word.len()
+    }
+
+    graph!{
+        digraph MapGraph {
+            read[type=Read, start=true];
+            parity[type=Parity, branch=matcher];
+            even[type=Describe];
+            odd[type=Describe];
+            read -> parity [map=parse];
+            parity -> even [value=0, map=describe_even];
+            parity -> odd [map=describe_odd];
+        }
+    }
+
+    graph!{
+        digraph MapForEachGraph {
+            split[type=Split, branch=foreach, start=true];
+            double[type=Double];
+            total[type=Total, gather=true];
+            split -> double [map=length];
+            double -> total;
+        }
+    }
+
+    #[tokio::test]
+    async fn converts_outputs_on_fused_and_spawned_edges() {
+        let output = MapGraph::run_graph(String::from("4"), None).await.unwrap();
+        assert_eq!(output, "4 is even");
+        let output = MapGraph::run_graph(String::from("7"), None).await.unwrap();
+        assert_eq!(output, "7 is odd");
+    }
+
+    #[tokio::test]
+    async fn converts_each_element_on_foreach_edges() {
+        let output = MapForEachGraph::run_graph(String::from("a bb ccc"), None).await.unwrap();
+        assert_eq!(output, "[2, 4, 6]");
+    }
+}
+
+mod sequential {
+    use std::sync::Mutex;
+    use conflagrate::{graph, nodetype};
+
+    static ORDER: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    #[nodetype]
+    pub fn Save() {}
+
+    #[nodetype]
+    pub async fn WriteDb() {
+        // Give `notify` every chance to run first if the nodes weren't run one after another.
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        ORDER.lock().unwrap().push("write_db");
+    }
+
+    #[nodetype]
+    pub async fn Notify() {
+        ORDER.lock().unwrap().push("notify");
+    }
+
+    graph!{
+        digraph SequentialGraph {
+            save[type=Save, branch=sequential, start=true];
+            write_db[type=WriteDb];
+            notify[type=Notify];
+            save -> write_db;
+            save -> notify;
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn runs_trailing_nodes_in_edge_order() {
+        SequentialGraph::run_graph((), None).await.unwrap();
+        assert_eq!(*ORDER.lock().unwrap(), vec!["write_db", "notify"]);
+    }
+}
+
+mod pipe {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Start(input: u32) -> u32 {
+        input
+    }
+
+    #[nodetype]
+    pub fn AddOne(input: u32) -> u32 {
+        input + 1
+    }
+
+    #[nodetype]
+    pub fn Double(input: u32) -> u32 {
+        input * 2
+    }
+
+    graph!{
+        digraph PipeGraph {
+            start[type=Start, branch=pipe, start=true];
+            add_one[type=AddOne];
+            double[type=Double];
+            start -> add_one;
+            start -> double;
+        }
+    }
+
+    #[tokio::test]
+    async fn passes_each_output_to_the_next_trailing_node() {
+        assert_eq!(PipeGraph::run_graph(3, None).await.unwrap(), 8);
+    }
+}
+
+mod bounded_loop {
+    use conflagrate::{graph, nodetype, GraphError};
+
+    #[nodetype]
+    pub fn Count(number: u32) -> (String, u32) {
+        (String::from("again"), number + 1)
+    }
+
+    #[nodetype]
+    pub fn Finish(number: u32) -> u32 {
+        number
+    }
+
+    fn double(number: u32) -> u32 {
+        number * 2
+    }
+
+    graph!{
+        digraph ExhaustedGraph {
+            count[type=Count, branch=matcher, start=true];
+            finish[type=Finish];
+            count -> count [max_iterations=3, map=double];
+            count -> finish [on_exhausted=true];
+        }
+    }
+
+    graph!{
+        digraph UnboundedGraph {
+            count[type=Count, branch=matcher, max_iterations=3, start=true];
+            finish[type=Finish];
+            count -> count;
+            count -> finish [value=stop];
+        }
+    }
+
+    #[tokio::test]
+    async fn follows_on_exhausted_after_the_last_iteration() {
+        // 1 -> 2 -> 4 -> 5 -> 10 -> 11 -> 22 -> 23, then the loop edge is exhausted.
+        assert_eq!(ExhaustedGraph::run_graph(1, None).await.unwrap(), 23);
+    }
+
+    #[tokio::test]
+    async fn fails_without_an_on_exhausted_edge() {
+        match UnboundedGraph::run_graph(1, None).await {
+            Err(GraphError::IterationLimitExceeded { edge, max_iterations }) => {
+                assert_eq!((edge, max_iterations), ("count -> count", 3));
+            },
+            result => panic!("expected the iteration limit to be exceeded, got {:?}", result),
+        }
+    }
+}
+
+mod worker_loop {
+    use std::sync::Mutex;
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype};
+
+    static SEEN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    static LOOPED: Notify = Notify::const_new();
+
+    #[nodetype]
+    pub fn Next(number: u32) -> u32 {
+        number + 1
+    }
+
+    #[nodetype]
+    pub async fn Record(number: u32) -> u32 {
+        let mut seen = SEEN.lock().unwrap();
+        seen.push(number);
+        if seen.len() == 3 {
+            LOOPED.notify_one();
+        }
+        number
+    }
+
+    graph!{
+        digraph WorkerLoop {
+            a[type=Next, start=true];
+            b[type=Record, yield=true];
+            a -> b -> a;
+        }
+    }
+
+    #[tokio::test]
+    async fn loops_back_to_the_first_node() {
+        // The loop never ends the graph, so the run is left to be dropped with the runtime.
+        tokio::spawn(WorkerLoop::run_graph(0, None));
+        LOOPED.notified().await;
+        assert_eq!(SEEN.lock().unwrap()[..3], [1, 2, 3]);
+    }
+}
+
+mod mid_task_loop {
+    use std::sync::Mutex;
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype};
+
+    static SEEN: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+    static LOOPED: Notify = Notify::const_new();
+
+    #[nodetype]
+    pub fn Next(number: u32) -> u32 {
+        number + 1
+    }
+
+    #[nodetype]
+    pub async fn Record(number: u32) -> u32 {
+        let mut seen = SEEN.lock().unwrap();
+        seen.push(number);
+        if seen.len() == 3 {
+            LOOPED.notify_one();
+        }
+        number
+    }
+
+    graph!{
+        digraph MidTaskLoop {
+            a[type=Next, start=true];
+            b[type=Next];
+            c[type=Record, yield=true];
+            a -> b -> c -> b;
+        }
+    }
+
+    #[tokio::test]
+    async fn loops_back_to_a_node_in_the_middle_of_the_task() {
+        // `a` only runs once, so each time around `b` adds one to what `c` saw last.
+        tokio::spawn(MidTaskLoop::run_graph(0, None));
+        LOOPED.notified().await;
+        assert_eq!(SEEN.lock().unwrap()[..3], [2, 3, 4]);
+    }
+}
+
+mod panics {
+    use conflagrate::{graph, nodetype, GraphError};
+
+    #[nodetype]
+    pub fn Start(number: u32) -> u32 {
+        number
+    }
+
+    #[nodetype]
+    pub async fn ExplodeAsync(number: u32) -> u32 {
+        if number == 3 {
+            panic!("async node exploded");
+        }
+        number + 1
+    }
+
+    #[nodetype]
+    pub fn ExplodeBlocking(number: u32) -> u32 {
+        if number == 3 {
+            panic!("blocking node exploded");
+        }
+        number + 1
+    }
+
+    graph!{
+        digraph AsyncPanic {
+            start[type=Start, start=true];
+            explode[type=ExplodeAsync];
+            start -> explode;
+        }
+    }
+
+    graph!{
+        digraph BlockingPanic {
+            start[type=Start, start=true];
+            explode[type=ExplodeBlocking];
+            start -> explode;
+        }
+    }
+
+    graph!{
+        digraph LoopPanic {
+            start[type=Start, start=true];
+            explode[type=ExplodeAsync];
+            start -> explode -> start;
+        }
+    }
+
+    fn assert_panicked<T: std::fmt::Debug>(
+        result: Result<T, GraphError>,
+        expected_node: &str,
+        expected_nodetype: &str,
+        expected_message: &str
+    ) {
+        match result {
+            Err(GraphError::NodePanicked { node, nodetype, payload }) => {
+                assert_eq!((node, nodetype), (expected_node, expected_nodetype));
+                assert_eq!(payload.downcast_ref::<&str>(), Some(&expected_message));
+            },
+            result => panic!("expected node `{}` to panic, got {:?}", expected_node, result),
+        }
+    }
+
+    #[tokio::test]
+    async fn async_node_panic_ends_the_graph() {
+        let result = AsyncPanic::run_graph(3, None).await;
+        assert_panicked(result, "explode", "ExplodeAsync", "async node exploded");
+        assert_eq!(AsyncPanic::run_graph(1, None).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn blocking_node_panic_ends_the_graph() {
+        let result = BlockingPanic::run_graph(3, None).await;
+        assert_panicked(result, "explode", "ExplodeBlocking", "blocking node exploded");
+    }
+
+    #[tokio::test]
+    async fn panic_ends_a_worker_loop() {
+        let result = LoopPanic::run_graph(0, None).await;
+        assert_panicked(result, "explode", "ExplodeAsync", "async node exploded");
+    }
+}
+
+mod parallel {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use conflagrate::{graph, nodetype};
+
+    static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Start(input: u32) -> u32 {
+        input
+    }
+
+    #[nodetype]
+    pub async fn Count(input: u32) -> u32 {
+        RUNS.fetch_add(1, SeqCst);
+        input
+    }
+
+    graph!{
+        digraph ParallelGraph {
+            start[type=Start, start=true];
+            first[type=Count];
+            second[type=Count];
+            start -> first;
+            start -> second;
+        }
+    }
+
+    #[test]
+    fn run_returns_the_output_once_every_branch_ends() {
+        assert_eq!(ParallelGraph::run(7).unwrap(), 7);
+        assert_eq!(RUNS.load(SeqCst), 2);
+    }
+}
+
+mod result_matcher {
+    use conflagrate::{graph, nodetype};
+
+    #[nodetype]
+    pub fn Parse(text: String) -> Result<i32, String> {
+        text.parse::<i32>().map_err(|_| text)
+    }
+
+    #[nodetype]
+    pub fn Double(number: i32) -> String {
+        (number * 2).to_string()
+    }
+
+    #[nodetype]
+    pub fn Complain(text: String) -> String {
+        format!("not a number: {}", text)
+    }
+
+    graph!{
+        digraph ResultGraph {
+            parse[type=Parse, branch=resultmatcher, start=true];
+            double[type=Double];
+            complain[type=Complain];
+            parse -> double [value=ok];
+            parse -> complain [value=err];
+        }
+    }
+
+    #[test]
+    fn follows_ok_and_err_edges() {
+        assert_eq!(ResultGraph::run(String::from("21")).unwrap(), "42");
+        assert_eq!(ResultGraph::run(String::from("x")).unwrap(), "not a number: x");
+    }
+}
+
+mod timeout {
+    use std::time::Duration;
+    use conflagrate::{graph, nodetype, GraphError};
+
+    #[nodetype]
+    pub async fn LookUp(city: String) -> String {
+        if city == "Atlantis" {
+            std::future::pending::<()>().await;
+        }
+        format!("sunny in {}", city)
+    }
+
+    #[nodetype]
+    pub fn NoForecast(city: String) -> String {
+        format!("no forecast for {}", city)
+    }
+
+    graph!{
+        digraph Forecast {
+            look_up[type=LookUp, start=true, timeout="500ms"];
+            no_forecast[type=NoForecast];
+            look_up -> no_forecast [value=timeout];
+        }
+    }
+
+    graph!{
+        digraph StrictForecast {
+            look_up[type=LookUp, start=true, timeout="500ms"];
+        }
+    }
+
+    // Paused time skips ahead to the next timer once every task is waiting, so the timeouts fire
+    // without the tests waiting on the clock.
+    #[tokio::test(start_paused = true)]
+    async fn follows_the_timeout_edge() {
+        let output = Forecast::run_graph(String::from("Paris"), None).await.unwrap();
+        assert_eq!(output, "sunny in Paris");
+        let output = Forecast::run_graph(String::from("Atlantis"), None).await.unwrap();
+        assert_eq!(output, "no forecast for Atlantis");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fails_without_a_timeout_edge() {
+        match StrictForecast::run_graph(String::from("Atlantis"), None).await {
+            Err(GraphError::Timeout { node, nodetype, timeout }) => {
+                assert_eq!((node, nodetype), ("look_up", "LookUp"));
+                assert_eq!(timeout, Duration::from_millis(500));
+            },
+            result => panic!("expected the node to time out, got {:?}", result),
+        }
+    }
+}
+
+mod race_timeout {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use std::time::Duration;
+    use conflagrate::{graph, nodetype};
+
+    static MEDIUM_FINISHED: AtomicUsize = AtomicUsize::new(0);
+    static FALLBACKS: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Request() {}
+
+    #[nodetype]
+    pub async fn Fast() -> String {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        String::from("fast")
+    }
+
+    #[nodetype]
+    pub async fn Medium() -> String {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        String::from("medium")
+    }
+
+    #[nodetype]
+    pub async fn Slow() -> String {
+        std::future::pending().await
+    }
+
+    #[nodetype]
+    pub fn RecordMedium(output: String) -> String {
+        MEDIUM_FINISHED.fetch_add(1, SeqCst);
+        output
+    }
+
+    #[nodetype]
+    pub async fn Fallback() -> String {
+        // Long enough for `medium` to finish while the run is still going.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        String::from("fallback")
+    }
+
+    #[nodetype]
+    pub fn CountFallback() -> String {
+        FALLBACKS.fetch_add(1, SeqCst);
+        String::from("fallback")
+    }
+
+    graph!{
+        digraph TimedOutRacerWins {
+            request[type=Request, branch=race, start=true];
+            slow[type=Slow, timeout="10ms"];
+            medium[type=Medium];
+            record_medium[type=RecordMedium];
+            fallback[type=Fallback];
+            request -> slow;
+            request -> medium -> record_medium;
+            slow -> fallback [value=timeout];
+        }
+    }
+
+    graph!{
+        digraph TimingOutRacerLoses {
+            request[type=Request, branch=race, start=true];
+            fast[type=Fast];
+            slow[type=Slow, timeout="200ms"];
+            fallback[type=CountFallback];
+            request -> fast;
+            request -> slow;
+            slow -> fallback [value=timeout];
+        }
+    }
+
+    // Each graph's timers fire in order on paused time, however long the test takes to run.
+    #[tokio::test(start_paused = true)]
+    async fn racer_that_times_out_first_wins_the_race() {
+        assert_eq!(TimedOutRacerWins::run_graph((), None).await.unwrap(), "fallback");
+        assert_eq!(MEDIUM_FINISHED.load(SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn racer_that_would_time_out_loses_to_a_faster_one() {
+        assert_eq!(TimingOutRacerLoses::run_graph((), None).await.unwrap(), "fast");
+        // Well past `slow`'s timeout, which would have run the fallback if `slow` hadn't lost.
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(FALLBACKS.load(SeqCst), 0);
+    }
+}
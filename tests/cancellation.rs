@@ -0,0 +1,112 @@
+//! Runs graphs started with `start_graph`, checking how cancelling or dropping a run stops it.
+
+struct NotifyOnDrop(&'static tokio::sync::Notify);
+impl Drop for NotifyOnDrop {
+    fn drop(&mut self) {
+        self.0.notify_one();
+    }
+}
+
+mod in_flight {
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype, GraphError};
+    use super::NotifyOnDrop;
+
+    static STARTED: Notify = Notify::const_new();
+    static ABORTED: Notify = Notify::const_new();
+
+    #[nodetype]
+    pub fn Start() {}
+
+    #[nodetype]
+    pub async fn Wait() -> u32 {
+        let _aborted = NotifyOnDrop(&ABORTED);
+        STARTED.notify_one();
+        std::future::pending().await
+    }
+
+    graph!{
+        digraph WaitGraph {
+            start[type=Start, start=true];
+            wait[type=Wait];
+            start -> wait;
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_aborts_running_nodes() {
+        let run = WaitGraph::start_graph((), None);
+        STARTED.notified().await;
+        run.cancel();
+        assert!(matches!(run.await, Err(GraphError::Cancelled)));
+        // Resolves once the node's task has been aborted and dropped.
+        ABORTED.notified().await;
+    }
+}
+
+mod dropped {
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype};
+    use super::NotifyOnDrop;
+
+    static STARTED: Notify = Notify::const_new();
+    static ABORTED: Notify = Notify::const_new();
+
+    #[nodetype]
+    pub async fn Wait() -> u32 {
+        let _aborted = NotifyOnDrop(&ABORTED);
+        STARTED.notify_one();
+        std::future::pending().await
+    }
+
+    graph!{
+        digraph WaitGraph {
+            wait[type=Wait, start=true];
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_the_handle_cancels_the_run() {
+        let run = WaitGraph::start_graph((), None);
+        STARTED.notified().await;
+        drop(run);
+        ABORTED.notified().await;
+    }
+}
+
+mod worker_loop {
+    use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+    use tokio::sync::Notify;
+    use conflagrate::{graph, nodetype, GraphError};
+
+    static ITERATIONS: AtomicUsize = AtomicUsize::new(0);
+    static LOOPED: Notify = Notify::const_new();
+
+    #[nodetype]
+    pub async fn Step(number: u32) -> u32 {
+        if ITERATIONS.fetch_add(1, SeqCst) + 1 == 3 {
+            LOOPED.notify_one();
+        }
+        number + 1
+    }
+
+    graph!{
+        digraph Worker {
+            step[type=Step, yield=true, start=true];
+            step -> step;
+        }
+    }
+
+    #[tokio::test]
+    async fn cancel_ends_a_worker_loop() {
+        let run = Worker::start_graph(0, None);
+        LOOPED.notified().await;
+        run.cancel();
+        assert!(matches!(run.await, Err(GraphError::Cancelled)));
+        let iterations = ITERATIONS.load(SeqCst);
+        for _ in 0..10 {
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(ITERATIONS.load(SeqCst), iterations);
+    }
+}
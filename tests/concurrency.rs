@@ -0,0 +1,131 @@
+//! Runs graphs with concurrency limits, checking that invocations over the limit wait their turn
+//! instead of running at once.
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Counts the invocations of a node running at once, holding each one until the test opens the
+/// gate.
+struct Gauge {
+    in_flight: AtomicUsize,
+    peak: AtomicUsize,
+    gate: Semaphore,
+}
+impl Gauge {
+    const fn new() -> Self {
+        Self {
+            in_flight: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            gate: Semaphore::const_new(0),
+        }
+    }
+
+    async fn hold(&self) {
+        self.peak.fetch_max(self.in_flight.fetch_add(1, SeqCst) + 1, SeqCst);
+        self.gate.acquire().await.unwrap().forget();
+        self.in_flight.fetch_sub(1, SeqCst);
+    }
+}
+
+/// Lets every task run until it's either held at the gauge's gate or waiting for a free slot,
+/// since paused time only moves on once the runtime has nothing else to do.
+async fn settle() {
+    tokio::time::sleep(Duration::from_secs(1)).await;
+}
+
+mod node_attribute {
+    use conflagrate::{graph, nodetype};
+    use super::*;
+
+    static GAUGE: Gauge = Gauge::new();
+
+    #[nodetype]
+    pub fn Split(count: usize) -> Vec<usize> {
+        (0..count).collect()
+    }
+
+    #[nodetype]
+    pub async fn Work(number: usize) -> usize {
+        GAUGE.hold().await;
+        number * 10
+    }
+
+    #[nodetype]
+    pub fn Collect(numbers: Vec<usize>) -> Vec<usize> {
+        numbers
+    }
+
+    graph!{
+        digraph LimitedGraph {
+            split[type=Split, branch=foreach, start=true];
+            work[type=Work, max_concurrency=2];
+            collect[type=Collect, gather=true];
+            split -> work -> collect;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queues_invocations_over_the_limit_across_runs() {
+        let first = tokio::spawn(LimitedGraph::run_graph(3, None));
+        let second = tokio::spawn(LimitedGraph::run_graph(3, None));
+        settle().await;
+        assert_eq!(GAUGE.in_flight.load(SeqCst), 2);
+        GAUGE.gate.add_permits(6);
+        assert_eq!(first.await.unwrap().unwrap(), vec![0, 10, 20]);
+        assert_eq!(second.await.unwrap().unwrap(), vec![0, 10, 20]);
+        assert_eq!(GAUGE.peak.load(SeqCst), 2);
+    }
+}
+
+mod nodetype_option {
+    use conflagrate::{graph, nodetype};
+    use super::*;
+
+    static GAUGE: Gauge = Gauge::new();
+
+    #[nodetype]
+    pub fn Split(count: usize) -> Vec<usize> {
+        (0..count).collect()
+    }
+
+    #[nodetype(max_concurrency = 2)]
+    pub async fn Work(number: usize) -> usize {
+        GAUGE.hold().await;
+        number * 10
+    }
+
+    #[nodetype]
+    pub fn Collect(numbers: Vec<usize>) -> Vec<usize> {
+        numbers
+    }
+
+    graph!{
+        digraph FirstGraph {
+            split[type=Split, branch=foreach, start=true];
+            work[type=Work];
+            collect[type=Collect, gather=true];
+            split -> work -> collect;
+        }
+    }
+
+    graph!{
+        digraph SecondGraph {
+            split[type=Split, branch=foreach, start=true];
+            work[type=Work];
+            collect[type=Collect, gather=true];
+            split -> work -> collect;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn queues_invocations_over_the_limit_across_graphs() {
+        let first = tokio::spawn(FirstGraph::run_graph(3, None));
+        let second = tokio::spawn(SecondGraph::run_graph(3, None));
+        settle().await;
+        assert_eq!(GAUGE.in_flight.load(SeqCst), 2);
+        GAUGE.gate.add_permits(6);
+        assert_eq!(first.await.unwrap().unwrap(), vec![0, 10, 20]);
+        assert_eq!(second.await.unwrap().unwrap(), vec![0, 10, 20]);
+        assert_eq!(GAUGE.peak.load(SeqCst), 2);
+    }
+}
@@ -0,0 +1,179 @@
+//! Runs graphs with nodes that fail before they succeed, checking how they're retried.
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering::SeqCst};
+use conflagrate::{graph, nodetype, GraphError};
+
+mod hook {
+    use super::*;
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+    static RETRIES: Mutex<Vec<(String, usize, String)>> = Mutex::new(Vec::new());
+
+    fn record_retry(node: &str, retry: usize, error: &GraphError) {
+        RETRIES.lock().unwrap().push((String::from(node), retry, error.to_string()));
+    }
+
+    /// Panics on its first attempt and returns an `Err` on its second.
+    #[nodetype]
+    pub fn Flaky() -> Result<usize, String> {
+        match ATTEMPTS.fetch_add(1, SeqCst) {
+            0 => panic!("first attempt"),
+            1 => Err(String::from("second attempt")),
+            attempt => Ok(attempt + 1),
+        }
+    }
+
+    #[nodetype]
+    pub fn Report(attempts: usize) -> String {
+        format!("succeeded after {} attempts", attempts)
+    }
+
+    #[nodetype]
+    pub fn GiveUp(error: String) -> String {
+        error
+    }
+
+    graph!{
+        digraph RetryGraph {
+            on_retry=record_retry;
+            flaky[type=Flaky, branch=resultmatcher, retry=2, start=true];
+            report[type=Report];
+            give_up[type=GiveUp];
+            flaky -> report [value=ok];
+            flaky -> give_up [value=err];
+        }
+    }
+
+    #[test]
+    fn calls_the_hook_before_each_retry() {
+        let run = async {
+            let run = RetryGraph::start_graph((), None);
+            let metrics = run.metrics().clone();
+            (run.await, metrics)
+        };
+        let (output, metrics) = tokio::runtime::Runtime::new().unwrap().block_on(run);
+        assert_eq!(output.unwrap(), "succeeded after 3 attempts");
+        assert_eq!(metrics.retries("flaky"), 2);
+        assert_eq!(*RETRIES.lock().unwrap(), vec![
+            (
+                String::from("flaky"),
+                1,
+                String::from("node `flaky` of type `Flaky` panicked: first attempt"),
+            ),
+            (
+                String::from("flaky"),
+                2,
+                String::from("node `flaky` of type `Flaky` returned an error: \"second attempt\""),
+            ),
+        ]);
+    }
+}
+
+mod any_branch {
+    use super::*;
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Returns an `Err` until its third attempt, from a node without a `resultmatcher` branch.
+    #[nodetype]
+    pub fn Flaky() -> Result<usize, String> {
+        match ATTEMPTS.fetch_add(1, SeqCst) {
+            attempt @ (0 | 1) => Err(format!("attempt {}", attempt + 1)),
+            attempt => Ok(attempt + 1),
+        }
+    }
+
+    #[nodetype]
+    pub fn Report(attempts: Result<usize, String>) -> String {
+        match attempts {
+            Ok(attempts) => format!("succeeded after {} attempts", attempts),
+            Err(error) => format!("gave up after {}", error),
+        }
+    }
+
+    graph!{
+        digraph AnyBranchGraph {
+            flaky[type=Flaky, retry=2, start=true];
+            report[type=Report];
+            flaky -> report;
+        }
+    }
+
+    #[tokio::test]
+    async fn retries_an_err_from_a_node_of_any_branch() {
+        let output = AnyBranchGraph::run_graph((), None).await.unwrap();
+        assert_eq!(output, "succeeded after 3 attempts");
+    }
+}
+
+mod out_of_retries {
+    use super::*;
+
+    static RETRIES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn record_retry(_node: &str, _retry: usize, error: &GraphError) {
+        RETRIES.lock().unwrap().push(error.to_string());
+    }
+
+    /// An error without a `Debug` representation.
+    #[derive(Clone)]
+    pub struct Unreachable;
+
+    #[nodetype]
+    pub fn Fetch() -> Result<String, Unreachable> {
+        Err(Unreachable)
+    }
+
+    #[nodetype]
+    pub fn Report(text: Result<String, Unreachable>) -> String {
+        text.unwrap_or_else(|_| String::from("unreachable"))
+    }
+
+    graph!{
+        digraph OutOfRetriesGraph {
+            on_retry=record_retry;
+            fetch[type=Fetch, retry=1, retry_delay="100ms", backoff=exponential, start=true];
+            report[type=Report];
+            fetch -> report;
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn passes_on_the_err_once_out_of_retries() {
+        let started = tokio::time::Instant::now();
+        assert_eq!(OutOfRetriesGraph::run_graph((), None).await.unwrap(), "unreachable");
+        assert_eq!(started.elapsed(), std::time::Duration::from_millis(100));
+        let error = format!(
+            "node `fetch` of type `Fetch` returned an error: an `Err` of type `{}`",
+            std::any::type_name::<Unreachable>()
+        );
+        assert_eq!(*RETRIES.lock().unwrap(), vec![error]);
+    }
+}
+
+mod backoff {
+    use super::*;
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[nodetype]
+    pub fn Flaky() -> Result<usize, String> {
+        match ATTEMPTS.fetch_add(1, SeqCst) {
+            attempt @ 0..=2 => Err(format!("attempt {}", attempt + 1)),
+            attempt => Ok(attempt + 1),
+        }
+    }
+
+    graph!{
+        digraph BackoffGraph {
+            flaky[type=Flaky, retry=3, retry_delay="100ms", backoff=exponential, start=true];
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn doubles_the_delay_with_each_retry() {
+        let started = tokio::time::Instant::now();
+        assert_eq!(BackoffGraph::run_graph((), None).await.unwrap(), Ok(4));
+        assert_eq!(started.elapsed(), std::time::Duration::from_millis(700));
+    }
+}
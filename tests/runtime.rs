@@ -0,0 +1,87 @@
+//! Runs graphs with `run`, `run_with` and `run_on`, checking which runtime their nodes run on.
+use std::thread::{self, ThreadId};
+use conflagrate::{graph, nodetype, RuntimeConfig};
+use tokio::runtime::{Builder, Handle, RuntimeFlavor};
+
+/// Where a node ran: its thread and the runtime the thread belongs to.
+pub struct Placement {
+    thread: ThreadId,
+    thread_name: Option<String>,
+    flavor: RuntimeFlavor,
+    workers: usize,
+}
+
+#[nodetype]
+pub async fn Locate() -> Placement {
+    let runtime = Handle::current();
+    Placement {
+        thread: thread::current().id(),
+        thread_name: thread::current().name().map(String::from),
+        flavor: runtime.runtime_flavor(),
+        workers: runtime.metrics().num_workers(),
+    }
+}
+
+graph!{
+    digraph DefaultGraph {
+        locate[type=Locate, start=true];
+    }
+}
+
+graph!{
+    digraph ConfiguredGraph {
+        graph[flavor=multi_thread, worker_threads=2, thread_name="configured"];
+        locate[type=Locate, start=true];
+    }
+}
+
+graph!{
+    digraph CurrentThreadGraph {
+        flavor=current_thread;
+        locate[type=Locate, start=true];
+    }
+}
+
+#[test]
+fn runs_on_the_runtime_set_by_the_graph_attributes() {
+    let placement = ConfiguredGraph::run(()).unwrap();
+    assert_eq!(placement.flavor, RuntimeFlavor::MultiThread);
+    assert_eq!(placement.workers, 2);
+    assert_eq!(placement.thread_name.as_deref(), Some("configured"));
+}
+
+#[test]
+fn runs_on_the_calling_thread_with_flavor_current_thread() {
+    let placement = CurrentThreadGraph::run(()).unwrap();
+    assert_eq!(placement.flavor, RuntimeFlavor::CurrentThread);
+    assert_eq!(placement.thread, thread::current().id());
+}
+
+#[test]
+fn run_with_replaces_the_graph_attributes() {
+    let placement = ConfiguredGraph::run_with(RuntimeConfig::current_thread(), ()).unwrap();
+    assert_eq!(placement.flavor, RuntimeFlavor::CurrentThread);
+    assert_eq!(placement.thread, thread::current().id());
+
+    let config = RuntimeConfig::multi_thread().worker_threads(3).thread_name("chosen");
+    let placement = DefaultGraph::run_with(config, ()).unwrap();
+    assert_eq!(placement.flavor, RuntimeFlavor::MultiThread);
+    assert_eq!(placement.workers, 3);
+    assert_eq!(placement.thread_name.as_deref(), Some("chosen"));
+}
+
+#[test]
+fn run_on_uses_the_given_runtime() {
+    let rt = Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("existing")
+        .enable_all()
+        .build()
+        .unwrap();
+    // The runtime outlives each run, so it can run the graph again.
+    for _ in 0..2 {
+        let placement = DefaultGraph::run_on(rt.handle(), ()).unwrap();
+        assert_eq!(placement.workers, 1);
+        assert_eq!(placement.thread_name.as_deref(), Some("existing"));
+    }
+}